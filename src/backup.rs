@@ -0,0 +1,186 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDateTime};
+use rusqlite::{Connection, OpenFlags};
+
+use crate::client::Client;
+use crate::error::{TuduError, TuduResult};
+
+const BACKUP_PREFIX: &str = "tudu-";
+const BACKUP_EXTENSION: &str = ".db";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub created_at: NaiveDateTime,
+    pub task_count: i64,
+}
+
+impl BackupInfo {
+    pub fn get_file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+pub fn create_backup(
+    client: &Client,
+    backups_path: &Path,
+    max_backups: usize,
+) -> TuduResult<PathBuf> {
+    fs::create_dir_all(backups_path)
+        .map_err(|e| TuduError::io("Could not create backups folder", e))?;
+
+    let file_name = format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        Local::now().format(BACKUP_TIME_FORMAT),
+        BACKUP_EXTENSION
+    );
+    let backup_path = backups_path.join(file_name);
+
+    client.backup_database(&backup_path)?;
+    rotate_backups(backups_path, max_backups)?;
+    Ok(backup_path)
+}
+
+pub fn rotate_backups(backups_path: &Path, max_backups: usize) -> TuduResult<()> {
+    let mut backups = get_backup_paths(backups_path)?;
+    backups.sort_by_key(|backup| Reverse(backup.1));
+
+    for (path, _) in backups.iter().skip(max_backups) {
+        fs::remove_file(path).map_err(|e| TuduError::io("Could not remove old backup", e))?;
+    }
+    Ok(())
+}
+
+// Newest backup comes first
+pub fn list_backups(backups_path: &Path) -> TuduResult<Vec<BackupInfo>> {
+    let mut backups: Vec<BackupInfo> = get_backup_paths(backups_path)?
+        .into_iter()
+        .map(|(path, created_at)| {
+            let task_count = count_tasks(&path).unwrap_or(-1);
+            BackupInfo {
+                path,
+                created_at,
+                task_count,
+            }
+        })
+        .collect();
+    backups.sort_by_key(|backup| Reverse(backup.created_at));
+    Ok(backups)
+}
+
+pub fn restore_backup(client: &mut Client, backup_path: &Path) -> TuduResult<()> {
+    if !backup_path.is_file() {
+        return Err(TuduError::validation(&format!(
+            "Could not find backup {}",
+            backup_path.display()
+        )));
+    }
+    count_tasks(backup_path)
+        .map_err(|e| TuduError::storage("Backup is not a valid tudu database", e))?;
+
+    client.restore_database(backup_path)
+}
+
+pub fn find_backup(backups_path: &Path, name: &str) -> TuduResult<BackupInfo> {
+    let backups = list_backups(backups_path)?;
+    let backup = match name.parse::<usize>() {
+        Ok(index) if index > 0 => backups.get(index - 1),
+        _ => backups.iter().find(|backup| backup.get_file_name() == name),
+    };
+    backup
+        .cloned()
+        .ok_or_else(|| TuduError::validation(&format!("Could not find backup {}", name)))
+}
+
+fn get_backup_paths(backups_path: &Path) -> TuduResult<Vec<(PathBuf, NaiveDateTime)>> {
+    if !backups_path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let backups = fs::read_dir(backups_path)
+        .map_err(|e| TuduError::io("Could not read backups folder", e))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().into_string().ok()?;
+            let created_at = parse_backup_name(&file_name)?;
+            Some((entry.path(), created_at))
+        })
+        .collect();
+    Ok(backups)
+}
+
+fn parse_backup_name(file_name: &str) -> Option<NaiveDateTime> {
+    let timestamp = file_name
+        .strip_prefix(BACKUP_PREFIX)?
+        .strip_suffix(BACKUP_EXTENSION)?;
+    NaiveDateTime::parse_from_str(timestamp, BACKUP_TIME_FORMAT).ok()
+}
+
+fn count_tasks(path: &Path) -> Result<i64, rusqlite::Error> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    connection.query_row("SELECT COUNT(*) FROM todos", [], |row| row.get(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::get_test_dir;
+
+    #[test]
+    fn test_parse_backup_name() {
+        let created_at = parse_backup_name("tudu-20241019-153000-123.db");
+        assert_eq!(
+            created_at.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string()),
+            Some(String::from("2024-10-19 15:30:00"))
+        );
+        assert_eq!(parse_backup_name("tudu.db"), None);
+        assert_eq!(parse_backup_name("tudu-20241019.db"), None);
+    }
+
+    #[test]
+    fn test_backup_rotation_and_restore() {
+        let backups_path = get_test_dir("backup").join("backups");
+
+        let mut client = Client {
+            connection: Some(Connection::open_in_memory().expect("Could not open connection")),
+        };
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+
+        for index in 0..3 {
+            client
+                .create_task(format!("task {}", index))
+                .expect("Could not create task");
+            create_backup(&client, &backups_path, 2).expect("Could not create backup");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        let backups = list_backups(&backups_path).expect("Could not list backups");
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].task_count, 3);
+        assert_eq!(backups[1].task_count, 2);
+
+        let oldest = find_backup(&backups_path, "2").expect("Could not find backup");
+        assert_eq!(oldest, backups[1]);
+        let by_name =
+            find_backup(&backups_path, &backups[0].get_file_name()).expect("Could not find");
+        assert_eq!(by_name, backups[0]);
+        assert!(find_backup(&backups_path, "3").is_err());
+
+        restore_backup(&mut client, &oldest.path).expect("Could not restore backup");
+        let tasks = client.get_tasks().expect("Could not get tasks");
+        assert_eq!(tasks.len(), 2);
+
+        let missing = backups_path.join("tudu-20000101-000000-000.db");
+        assert!(restore_backup(&mut client, &missing).is_err());
+    }
+}
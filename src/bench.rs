@@ -0,0 +1,163 @@
+// Timings on a database with tens of thousands of tasks, run them with
+// cargo test --release bench -- --ignored --nocapture
+use std::fs;
+use std::process;
+use std::time::{Duration, Instant};
+
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+
+use crate::cache::TitleCache;
+use crate::client::Client;
+use crate::constants::{SortField, TASK_PAGE_SIZE, TASK_WINDOW_MARGIN};
+use crate::encdec::encrypt;
+use crate::state::State;
+use crate::stats::get_heatmap_start;
+use crate::view::View;
+
+const TASK_COUNT: usize = 50_000;
+const FRAME_COUNT: u32 = 20;
+const MASTER_KEY: &str = "BENCH";
+
+struct Timings;
+
+impl Timings {
+    fn measure<T>(&mut self, label: &str, run: impl FnOnce() -> T) -> T {
+        let started_at = Instant::now();
+        let result = run();
+        self.push(label, started_at.elapsed());
+        result
+    }
+
+    fn push(&mut self, label: &str, duration: Duration) {
+        println!("{:<44} {:>10.2} ms", label, duration.as_secs_f64() * 1000.0);
+    }
+}
+
+fn create_tasks(client: &Client) {
+    let connection = client.get_connection().expect("Could not get connection");
+    let transaction = connection
+        .unchecked_transaction()
+        .expect("Could not start transaction");
+    {
+        let mut stmt = transaction
+            .prepare(
+                "INSERT INTO todos (title, status, created_at, updated_at)
+                 VALUES(?1, ?2, datetime('now', 'localtime'), datetime('now', 'localtime'))",
+            )
+            .expect("Could not prepare insert");
+        for index in 0..TASK_COUNT {
+            let title = encrypt(&format!("Benchmark task {}", index), MASTER_KEY);
+            let status = if index % 3 == 0 {
+                "completed"
+            } else {
+                "in-progress"
+            };
+            stmt.execute((format!("{:?}", title), status))
+                .expect("Could not insert task");
+        }
+    }
+    transaction.commit().expect("Could not commit tasks");
+}
+
+#[test]
+#[ignore]
+fn bench_large_database() {
+    let data_path = std::env::temp_dir().join(format!("tudu-bench-{}", process::id()));
+    fs::create_dir_all(&data_path).expect("Could not create bench folder");
+    let mut client = Client::default();
+    client
+        .open_connection(data_path.clone(), "tudu.db")
+        .expect("Could not open connection");
+    client
+        .create_todos_table()
+        .expect("Could not create todos table");
+
+    let mut timings = Timings;
+    timings.measure(&format!("insert {} tasks", TASK_COUNT), || {
+        create_tasks(&client)
+    });
+
+    let task_list = timings.measure("get all tasks", || {
+        client.get_tasks().expect("Could not get tasks")
+    });
+    let task_order = timings.measure("get task order", || {
+        client
+            .get_task_ids(SortField::Status)
+            .expect("Could not get task ids")
+    });
+    let today = chrono::Local::now().date_naive();
+    timings.measure("get task totals", || {
+        client
+            .get_task_totals(get_heatmap_start(today))
+            .expect("Could not get task totals")
+    });
+    timings.measure("get first page", || {
+        client
+            .get_task_page(0, TASK_PAGE_SIZE)
+            .expect("Could not get page")
+    });
+    let middle_id = task_list[TASK_COUNT / 2].id;
+    timings.measure("get page in the middle", || {
+        client
+            .get_task_page(middle_id, TASK_PAGE_SIZE)
+            .expect("Could not get page")
+    });
+    let ids: Vec<i32> = task_list.iter().step_by(100).map(|task| task.id).collect();
+    timings.measure(&format!("get {} tasks by id", ids.len()), || {
+        client.get_tasks_by_ids(&ids).expect("Could not get tasks")
+    });
+    timings.measure("update status of one task", || {
+        client
+            .update_tasks_status(&[middle_id], "completed")
+            .expect("Could not update task")
+    });
+    timings.measure("get history of one task", || {
+        client
+            .get_task_events(Some(middle_id), 100)
+            .expect("Could not get task events")
+    });
+
+    let mut title_cache = TitleCache::new();
+    let page_size = 38;
+    let list_offset = (TASK_COUNT / 2) as i32;
+    let start = (list_offset - TASK_WINDOW_MARGIN) as usize;
+    let end = (list_offset + page_size + TASK_WINDOW_MARGIN) as usize;
+    let (window, _) = timings.measure("load and decrypt window", || {
+        let window = client
+            .get_tasks_by_ids(&task_order[start..end])
+            .expect("Could not get tasks");
+        title_cache.decrypt_tasks(window, MASTER_KEY)
+    });
+    let first_page = task_list[..TASK_PAGE_SIZE].to_vec();
+    timings.measure("decrypt first page", || {
+        title_cache.decrypt_tasks(first_page, MASTER_KEY)
+    });
+    timings.measure("decrypt all tasks, cold cache", || {
+        title_cache.decrypt_tasks(task_list.clone(), MASTER_KEY)
+    });
+    timings.measure("decrypt all tasks, warm cache", || {
+        title_cache.decrypt_tasks(task_list.clone(), MASTER_KEY)
+    });
+
+    let mut state = State::new();
+    state.set_task_order(task_order);
+    state.set_loaded_tasks(window);
+    state.set_page_size(page_size);
+    state.set_list_offset(list_offset);
+    state.set_line(list_offset + 10);
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).expect("Could not create terminal");
+    let started_at = Instant::now();
+    for _ in 0..FRAME_COUNT {
+        View::draw(&mut terminal, &state).expect("Could not draw frame");
+    }
+    timings.push(
+        "draw main screen, per frame",
+        started_at.elapsed() / FRAME_COUNT,
+    );
+
+    client
+        .close_connection()
+        .expect("Could not close connection");
+    fs::remove_dir_all(&data_path).expect("Could not remove bench folder");
+}
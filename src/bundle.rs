@@ -0,0 +1,463 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use csv::{ReaderBuilder, WriterBuilder};
+use hex::{decode, encode};
+use hmac::{Hmac, Mac};
+use magic_crypt::{new_magic_crypt, MagicCryptTrait};
+use pbkdf2::pbkdf2_hmac_array;
+use sha2::Sha256;
+
+use crate::error::{TuduError, TuduResult};
+
+const BUNDLE_MAGIC: &str = "TUDU-BUNDLE";
+const BUNDLE_VERSION: u32 = 2;
+const BUNDLE_KDF: &str = "pbkdf2-sha256";
+const BUNDLE_KDF_ITERATIONS: u32 = 100_000;
+// Bounds the work a crafted header can ask for before the passphrase is checked
+const MIN_KDF_ITERATIONS: u32 = 10_000;
+const MAX_KDF_ITERATIONS: u32 = 10_000_000;
+const BUNDLE_SALT_LENGTH: usize = 16;
+const PAYLOAD_MAGIC: &str = "TUDU-BUNDLE-DATA";
+const TABLE_MARKER: &str = "#table";
+const CHECK_LABEL: &[u8] = b"TUDU-BUNDLE-CHECK";
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, PartialEq)]
+pub enum BundleError {
+    InvalidHeader,
+    UnsupportedVersion(u32),
+    UnsupportedKdf(String),
+    InvalidIterations(u32),
+    WrongPassphrase,
+    Corrupted,
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::InvalidHeader => write!(f, "File is not a tudu bundle"),
+            BundleError::UnsupportedVersion(version) => {
+                write!(f, "Bundle version {} is not supported", version)
+            }
+            BundleError::UnsupportedKdf(kdf) => write!(f, "Bundle kdf {} is not supported", kdf),
+            BundleError::InvalidIterations(iterations) => write!(
+                f,
+                "Bundle kdf iterations must be between {} and {}, got {}",
+                MIN_KDF_ITERATIONS, MAX_KDF_ITERATIONS, iterations
+            ),
+            BundleError::WrongPassphrase => write!(f, "Bundle passphrase is wrong"),
+            BundleError::Corrupted => write!(f, "Bundle is corrupted"),
+        }
+    }
+}
+
+impl Error for BundleError {}
+
+impl From<BundleError> for TuduError {
+    fn from(error: BundleError) -> Self {
+        match error {
+            BundleError::WrongPassphrase => TuduError::auth(&error.to_string()),
+            BundleError::InvalidIterations(_) => TuduError::validation(&error.to_string()),
+            _ => TuduError::crypto("Could not read bundle", error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleHeader {
+    pub version: u32,
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: Vec<u8>,
+}
+
+// The cipher key encrypts the payload, the mac key signs the header and the payload
+struct BundleKeys {
+    cipher_key: String,
+    mac_key: Vec<u8>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TableDump {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl TableDump {
+    pub fn column_index(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|name| name == column)
+    }
+}
+
+pub fn write_bundle(path: &Path, passphrase: &str, tables: &[TableDump]) -> TuduResult<()> {
+    let mut salt = [0u8; BUNDLE_SALT_LENGTH];
+    getrandom::getrandom(&mut salt).map_err(|e| TuduError::crypto("Could not generate salt", e))?;
+
+    let header = BundleHeader {
+        version: BUNDLE_VERSION,
+        kdf: String::from(BUNDLE_KDF),
+        iterations: BUNDLE_KDF_ITERATIONS,
+        salt: salt.to_vec(),
+    };
+    fs::write(path, encode_bundle(&header, passphrase, tables)?)
+        .map_err(|e| TuduError::export("Could not save bundle file", e))
+}
+
+pub fn read_bundle(path: &Path, passphrase: &str) -> TuduResult<Vec<TableDump>> {
+    let content =
+        fs::read_to_string(path).map_err(|e| TuduError::io("Could not read bundle file", e))?;
+    Ok(decode_bundle(&content, passphrase)?)
+}
+
+pub fn encode_bundle(
+    header: &BundleHeader,
+    passphrase: &str,
+    tables: &[TableDump],
+) -> TuduResult<String> {
+    let mut writer = WriterBuilder::new().flexible(true).from_writer(vec![]);
+    writer.write_record([PAYLOAD_MAGIC])?;
+    for table in tables.iter() {
+        let mut marker = vec![TABLE_MARKER, table.name.as_str()];
+        marker.extend(table.columns.iter().map(String::as_str));
+        writer.write_record(&marker)?;
+        for row in table.rows.iter() {
+            writer.write_record(row)?;
+        }
+    }
+    let payload = writer
+        .into_inner()
+        .map_err(|e| TuduError::export("Could not write bundle payload", e.into_error()))?;
+
+    let keys = derive_keys(header, passphrase);
+    let mc = new_magic_crypt!(&keys.cipher_key, 256);
+    let encrypted = encode(mc.encrypt_bytes_to_bytes(&payload));
+
+    let signed = format!(
+        "{}\nversion: {}\nkdf: {}\niterations: {}\nsalt: {}\n\n{}",
+        BUNDLE_MAGIC,
+        header.version,
+        header.kdf,
+        header.iterations,
+        encode(&header.salt),
+        encrypted
+    );
+    let map_error = |e| TuduError::crypto("Could not sign bundle", e);
+    let check = sign(&keys.mac_key, CHECK_LABEL).map_err(map_error)?;
+    let mac = sign(&keys.mac_key, signed.as_bytes()).map_err(map_error)?;
+    Ok(format!(
+        "{}\ncheck: {}\nmac: {}\n",
+        signed,
+        encode(check),
+        encode(mac)
+    ))
+}
+
+pub fn decode_bundle(content: &str, passphrase: &str) -> Result<Vec<TableDump>, BundleError> {
+    let (header, body) = parse_header(content)?;
+    let keys = derive_keys(&header, passphrase);
+
+    let mut body_lines = body.lines();
+    let encrypted = body_lines.next().ok_or(BundleError::Corrupted)?;
+    let check = read_trailer(body_lines.next(), "check")?;
+    let mac = read_trailer(body_lines.next(), "mac")?;
+    if !verify(&keys.mac_key, CHECK_LABEL, &check)? {
+        return Err(BundleError::WrongPassphrase);
+    }
+    let signed_length = content.len() - body.len() + encrypted.len();
+    if !verify(&keys.mac_key, &content.as_bytes()[..signed_length], &mac)? {
+        return Err(BundleError::Corrupted);
+    }
+
+    // The mac already matched, a payload that does not decrypt was not written by tudu
+    let encrypted = decode(encrypted.trim()).map_err(|_| BundleError::Corrupted)?;
+    let mc = new_magic_crypt!(&keys.cipher_key, 256);
+    let payload = mc
+        .decrypt_bytes_to_bytes(&encrypted)
+        .map_err(|_| BundleError::Corrupted)?;
+
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(payload.as_slice());
+    let mut records = reader.records();
+
+    match records.next() {
+        Some(Ok(record)) if record.get(0) == Some(PAYLOAD_MAGIC) => {}
+        _ => return Err(BundleError::Corrupted),
+    }
+
+    let mut tables: Vec<TableDump> = Vec::new();
+    for record in records {
+        let record = record.map_err(|_| BundleError::Corrupted)?;
+        let fields: Vec<String> = record.iter().map(String::from).collect();
+
+        if fields.first().map(String::as_str) == Some(TABLE_MARKER) {
+            let name = fields.get(1).ok_or(BundleError::Corrupted)?;
+            tables.push(TableDump {
+                name: name.clone(),
+                columns: fields[2..].to_vec(),
+                rows: Vec::new(),
+            });
+            continue;
+        }
+
+        let table = tables.last_mut().ok_or(BundleError::Corrupted)?;
+        if fields.len() != table.columns.len() {
+            return Err(BundleError::Corrupted);
+        }
+        table.rows.push(fields);
+    }
+
+    Ok(tables)
+}
+
+pub fn parse_header(content: &str) -> Result<(BundleHeader, &str), BundleError> {
+    let (head, body) = content
+        .split_once("\n\n")
+        .ok_or(BundleError::InvalidHeader)?;
+    let mut lines = head.lines();
+
+    if lines.next() != Some(BUNDLE_MAGIC) {
+        return Err(BundleError::InvalidHeader);
+    }
+
+    let mut header = BundleHeader {
+        version: 0,
+        kdf: String::new(),
+        iterations: 0,
+        salt: Vec::new(),
+    };
+    for line in lines {
+        let (key, value) = line.split_once(": ").ok_or(BundleError::InvalidHeader)?;
+        match key {
+            "version" => header.version = value.parse().map_err(|_| BundleError::InvalidHeader)?,
+            "kdf" => header.kdf = String::from(value),
+            "iterations" => {
+                header.iterations = value.parse().map_err(|_| BundleError::InvalidHeader)?
+            }
+            "salt" => header.salt = decode(value).map_err(|_| BundleError::InvalidHeader)?,
+            _ => {}
+        }
+    }
+
+    if header.version != BUNDLE_VERSION {
+        return Err(BundleError::UnsupportedVersion(header.version));
+    }
+    if header.kdf != BUNDLE_KDF {
+        return Err(BundleError::UnsupportedKdf(header.kdf));
+    }
+    if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&header.iterations) {
+        return Err(BundleError::InvalidIterations(header.iterations));
+    }
+    if header.salt.is_empty() {
+        return Err(BundleError::InvalidHeader);
+    }
+
+    Ok((header, body))
+}
+
+fn derive_keys(header: &BundleHeader, passphrase: &str) -> BundleKeys {
+    let password = passphrase.as_bytes();
+    let key = pbkdf2_hmac_array::<Sha256, 64>(password, &header.salt, header.iterations);
+    BundleKeys {
+        cipher_key: encode(&key[..32]),
+        mac_key: key[32..].to_vec(),
+    }
+}
+
+fn sign(mac_key: &[u8], data: &[u8]) -> Result<Vec<u8>, BundleError> {
+    Ok(get_mac(mac_key, data)?.finalize().into_bytes().to_vec())
+}
+
+// The comparison takes the same time however many bytes match
+fn verify(mac_key: &[u8], data: &[u8], tag: &[u8]) -> Result<bool, BundleError> {
+    Ok(get_mac(mac_key, data)?.verify_slice(tag).is_ok())
+}
+
+fn get_mac(mac_key: &[u8], data: &[u8]) -> Result<HmacSha256, BundleError> {
+    let mut mac = HmacSha256::new_from_slice(mac_key).map_err(|_| BundleError::Corrupted)?;
+    mac.update(data);
+    Ok(mac)
+}
+
+fn read_trailer(line: Option<&str>, key: &str) -> Result<Vec<u8>, BundleError> {
+    line.and_then(|line| line.strip_prefix(key))
+        .and_then(|rest| rest.strip_prefix(": "))
+        .and_then(|value| decode(value).ok())
+        .ok_or(BundleError::Corrupted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::get_test_dir;
+    use std::path::PathBuf;
+
+    fn sample_header() -> BundleHeader {
+        BundleHeader {
+            version: BUNDLE_VERSION,
+            kdf: String::from(BUNDLE_KDF),
+            iterations: MIN_KDF_ITERATIONS,
+            salt: vec![1, 2, 3, 4],
+        }
+    }
+
+    fn sample_tables() -> Vec<TableDump> {
+        vec![
+            TableDump {
+                name: String::from("todos"),
+                columns: vec![
+                    String::from("id"),
+                    String::from("title"),
+                    String::from("status"),
+                ],
+                rows: vec![vec![
+                    String::from("1"),
+                    String::from("Pay rent, \"today\""),
+                    String::from("in-progress"),
+                ]],
+            },
+            TableDump {
+                name: String::from("user"),
+                columns: vec![String::from("id"), String::from("secret")],
+                rows: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_encode_decode_bundle() {
+        let content = encode_bundle(&sample_header(), "passphrase", &sample_tables())
+            .expect("Could not encode bundle");
+
+        assert!(content.starts_with("TUDU-BUNDLE\nversion: 2\nkdf: pbkdf2-sha256\n"));
+        assert!(!content.contains("Pay rent"));
+
+        let tables = decode_bundle(&content, "passphrase").expect("Could not decode bundle");
+        assert_eq!(tables, sample_tables());
+        assert_eq!(tables[0].column_index("status"), Some(2));
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let content = encode_bundle(&sample_header(), "passphrase", &sample_tables())
+            .expect("Could not encode bundle");
+
+        let result = decode_bundle(&content, "wrong");
+        assert_eq!(result, Err(BundleError::WrongPassphrase));
+    }
+
+    #[test]
+    fn test_corrupted_bundle() {
+        let content = encode_bundle(&sample_header(), "passphrase", &sample_tables())
+            .expect("Could not encode bundle");
+        let (head, body) = content.split_once("\n\n").expect("Could not split bundle");
+
+        // A changed payload byte is caught by the mac, whatever the passphrase
+        let flipped = if body.starts_with('0') { "1" } else { "0" };
+        let tampered = format!("{}\n\n{}{}", head, flipped, &body[1..]);
+        assert_eq!(
+            decode_bundle(&tampered, "passphrase"),
+            Err(BundleError::Corrupted)
+        );
+        assert_eq!(
+            decode_bundle(&tampered, "wrong"),
+            Err(BundleError::WrongPassphrase)
+        );
+
+        let truncated = &content[..content.find("\nmac: ").expect("Could not find mac")];
+        assert_eq!(
+            decode_bundle(truncated, "passphrase"),
+            Err(BundleError::Corrupted)
+        );
+        let reordered = content.replacen("\n\n", "\nnote: x\n\n", 1);
+        assert_eq!(
+            decode_bundle(&reordered, "passphrase"),
+            Err(BundleError::Corrupted)
+        );
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        assert_eq!(
+            decode_bundle("not a bundle", "passphrase"),
+            Err(BundleError::InvalidHeader)
+        );
+        assert_eq!(
+            decode_bundle(
+                "TUDU-BUNDLE\nversion: 3\nkdf: pbkdf2-sha256\niterations: 1\nsalt: 01\n\n00\n",
+                "passphrase"
+            ),
+            Err(BundleError::UnsupportedVersion(3))
+        );
+        // Bundles without a mac are not read anymore
+        assert_eq!(
+            decode_bundle(
+                "TUDU-BUNDLE\nversion: 1\nkdf: pbkdf2-sha256\niterations: 1\nsalt: 01\n\n00\n",
+                "passphrase"
+            ),
+            Err(BundleError::UnsupportedVersion(1))
+        );
+        assert_eq!(
+            decode_bundle(
+                "TUDU-BUNDLE\nversion: 2\nkdf: scrypt\niterations: 1\nsalt: 01\n\n00\n",
+                "passphrase"
+            ),
+            Err(BundleError::UnsupportedKdf(String::from("scrypt")))
+        );
+
+        for iterations in [0, MIN_KDF_ITERATIONS - 1, MAX_KDF_ITERATIONS + 1] {
+            let content = format!(
+                "TUDU-BUNDLE\nversion: 2\nkdf: pbkdf2-sha256\niterations: {}\nsalt: 01\n\n00\n",
+                iterations
+            );
+            assert_eq!(
+                decode_bundle(&content, "passphrase"),
+                Err(BundleError::InvalidIterations(iterations))
+            );
+        }
+        assert!(matches!(
+            TuduError::from(BundleError::InvalidIterations(0)),
+            TuduError::Validation(_)
+        ));
+    }
+
+    #[test]
+    fn test_write_read_file() {
+        let path = get_test_dir("bundle").join("tudu.tudubak");
+
+        write_bundle(&path, "passphrase", &sample_tables()).expect("Could not write bundle");
+        let tables = read_bundle(&path, "passphrase").expect("Could not read bundle");
+        assert_eq!(tables, sample_tables());
+    }
+
+    #[test]
+    fn test_read_sample_file() {
+        let mut path = PathBuf::new();
+        path.push("./test/bundle/sample.tudubak");
+
+        let tables = read_bundle(&path, "SECRET").expect("Could not read bundle");
+        assert_eq!(tables[0].name, "todos");
+        assert_eq!(tables[0].rows.len(), 2);
+        assert_eq!(tables[0].rows[1][1], "Water the plants");
+
+        // The sample is signed, a changed payload no longer matches its mac
+        let content = fs::read_to_string(&path).expect("Could not read sample");
+        let (header, payload) = content.split_once("\n\n").expect("Sample has no payload");
+        let flipped = if payload.starts_with('0') { "1" } else { "0" };
+        let tampered = format!("{}\n\n{}{}", header, flipped, &payload[1..]);
+        assert_eq!(
+            decode_bundle(&tampered, "SECRET"),
+            Err(BundleError::Corrupted)
+        );
+        assert_eq!(
+            decode_bundle(&content, "wrong"),
+            Err(BundleError::WrongPassphrase)
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::encdec::decrypt;
+use crate::task::Task;
+
+// Decrypted titles by row id, a row is only decrypted again once its version changed
+#[derive(Debug, Default)]
+pub struct TitleCache {
+    master_key: String,
+    titles: HashMap<i32, (i64, String)>,
+    // Rows that did not decrypt are not tried again until they change
+    failed_ids: HashSet<i32>,
+}
+
+impl TitleCache {
+    pub fn new() -> Self {
+        TitleCache::default()
+    }
+
+    // Rows that do not decrypt with the master key are left out and counted
+    pub fn decrypt_tasks(&mut self, task_list: Vec<Task>, master_key: &str) -> (Vec<Task>, usize) {
+        if self.master_key != master_key {
+            self.clear();
+            self.master_key = String::from(master_key);
+        }
+
+        let mut decrypted_task_list = Vec::with_capacity(task_list.len());
+        let mut failed_count = 0;
+        for mut task in task_list {
+            match self.titles.get(&task.id) {
+                Some((version, title)) if *version == task.version => {
+                    task.title = title.clone();
+                }
+                _ => match decrypt(&task.title, master_key) {
+                    Ok(title) => {
+                        self.failed_ids.remove(&task.id);
+                        self.titles.insert(task.id, (task.version, title.clone()));
+                        task.title = title;
+                    }
+                    Err(_) => {
+                        self.failed_ids.insert(task.id);
+                        failed_count += 1;
+                        continue;
+                    }
+                },
+            }
+            decrypted_task_list.push(task);
+        }
+        (decrypted_task_list, failed_count)
+    }
+
+    // Titles are only kept while the row is unchanged, so any cached one is current
+    pub fn get_title(&self, id: i32) -> Option<&String> {
+        self.titles.get(&id).map(|(_, title)| title)
+    }
+
+    pub fn has_failed(&self, id: i32) -> bool {
+        self.failed_ids.contains(&id)
+    }
+
+    pub fn remove(&mut self, ids: &[i32]) {
+        for id in ids.iter() {
+            self.titles.remove(id);
+            self.failed_ids.remove(id);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.titles.clear();
+        self.failed_ids.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.titles.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encdec::encrypt;
+
+    #[test]
+    fn test_title_cache() {
+        let get_task = |id: i32, title: &str, version: i64| Task {
+            id,
+            title: format!("{:?}", encrypt(title, "KEY")),
+            version,
+            ..Default::default()
+        };
+        let mut cache = TitleCache::new();
+        let (task_list, failed_count) = cache.decrypt_tasks(
+            vec![
+                get_task(1, "apples", 1),
+                get_task(2, "bread", 1),
+                Task {
+                    id: 3,
+                    title: String::from("\"broken\""),
+                    ..Default::default()
+                },
+            ],
+            "KEY",
+        );
+        assert_eq!(task_list[1].title, "bread");
+        assert_eq!((task_list.len(), failed_count, cache.len()), (2, 1, 2));
+        assert_eq!(cache.get_title(2), Some(&String::from("bread")));
+        assert!(cache.has_failed(3));
+
+        // A cached title is reused while the version stays the same
+        let stale_task = Task {
+            title: String::from("\"not decrypted again\""),
+            ..get_task(1, "apples", 1)
+        };
+        let (task_list, _) = cache.decrypt_tasks(vec![stale_task, get_task(2, "eggs", 2)], "KEY");
+        assert_eq!(task_list[0].title, "apples");
+        assert_eq!(task_list[1].title, "eggs");
+
+        cache.remove(&[1, 3]);
+        assert_eq!(cache.len(), 1);
+        assert!(!cache.has_failed(3));
+        let (task_list, failed_count) = cache.decrypt_tasks(vec![get_task(2, "eggs", 2)], "OTHER");
+        assert_eq!((task_list.len(), failed_count), (0, 1));
+        assert!(cache.is_empty());
+    }
+}
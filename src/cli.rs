@@ -0,0 +1,292 @@
+use std::io::{stdin, stdout, Write};
+use std::path::PathBuf;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+
+use crate::backup::{create_backup, find_backup, list_backups, restore_backup};
+use crate::client::Client;
+use crate::config::Config;
+use crate::constants::DEFAULT_LOG_LENGTH;
+use crate::doctor::{diagnose, quarantine_tasks, verify_master_key};
+use crate::encdec::decrypt;
+use crate::error::{TuduError, TuduResult};
+use crate::filesystem;
+
+const USAGE: &str = "Usage:
+  tudu [--config <path>] [command]
+  tudu                           Start the app
+  tudu backup list               List database backups
+  tudu backup create             Create a database backup
+  tudu backup restore <backup>   Restore a backup by number or file name
+  tudu doctor [--yes]            Check the database and repair damaged rows
+  tudu log [count]               Show the latest task changes, 20 by default";
+
+// Pulls `--config <path>` out of the arguments so it works before any command
+pub fn split_config_arg(args: &[String]) -> TuduResult<(Option<PathBuf>, Vec<String>)> {
+    let mut config_path = None;
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            let path = args
+                .next()
+                .ok_or_else(|| TuduError::validation("Missing path after --config"))?;
+            config_path = Some(filesystem::expand_path(path));
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            config_path = Some(filesystem::expand_path(path));
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    Ok((config_path, rest))
+}
+
+pub fn run_command(args: &[String], config: &Config) -> TuduResult<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["backup", "list"] => list_backups_command(config),
+        ["backup", "create"] => create_backup_command(config),
+        ["backup", "restore", name] => restore_backup_command(config, name),
+        ["doctor"] => doctor_command(config, false),
+        ["doctor", "--yes"] => doctor_command(config, true),
+        ["log"] => log_command(config, DEFAULT_LOG_LENGTH),
+        ["log", count] => match count.parse::<usize>() {
+            Ok(count) if count > 0 => log_command(config, count),
+            _ => Err(TuduError::validation(&format!(
+                "Invalid count {}\n{}",
+                count, USAGE
+            ))),
+        },
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(TuduError::validation(&format!(
+            "Unknown command\n{}",
+            USAGE
+        ))),
+    }
+}
+
+fn open_client(config: &Config) -> TuduResult<(Client, PathBuf)> {
+    let data_path = config.get_data_path()?;
+    if !filesystem::file_exists(&data_path, &config.paths.database) {
+        return Err(TuduError::validation(
+            "Could not find tudu database, start the app first",
+        ));
+    }
+
+    let mut client = Client::default();
+    client.open_connection(data_path, &config.paths.database)?;
+    Ok((client, config.get_backups_path()?))
+}
+
+fn list_backups_command(config: &Config) -> TuduResult<()> {
+    let backups_path = config.get_backups_path()?;
+    let backups = list_backups(&backups_path)?;
+
+    if backups.is_empty() {
+        println!("No backups in {}", backups_path.display());
+    }
+    for (index, backup) in backups.iter().enumerate() {
+        println!(
+            "{:>3}  {}  {:>6} tasks  {}",
+            index + 1,
+            backup.created_at.format("%Y-%m-%d %H:%M:%S"),
+            backup.task_count,
+            backup.get_file_name()
+        );
+    }
+    Ok(())
+}
+
+fn create_backup_command(config: &Config) -> TuduResult<()> {
+    let (mut client, backups_path) = open_client(config)?;
+    let backup_path = create_backup(&client, &backups_path, config.backups.max_backups)?;
+    client.close_connection()?;
+
+    println!("Created backup {}", backup_path.display());
+    Ok(())
+}
+
+fn restore_backup_command(config: &Config, name: &str) -> TuduResult<()> {
+    let (mut client, backups_path) = open_client(config)?;
+    let backup = find_backup(&backups_path, name)?;
+
+    let max_backups = config.backups.max_backups;
+    let safety_backup_path = create_backup(&client, &backups_path, max_backups + 1)?;
+    restore_backup(&mut client, &backup.path)?;
+    client.close_connection()?;
+
+    println!(
+        "Restored backup from {} ({} tasks), previous database saved as {}",
+        backup.created_at.format("%Y-%m-%d %H:%M:%S"),
+        backup.task_count,
+        safety_backup_path.display()
+    );
+    Ok(())
+}
+
+fn doctor_command(config: &Config, assume_yes: bool) -> TuduResult<()> {
+    let (mut client, backups_path) = open_client(config)?;
+    let max_backups = config.backups.max_backups;
+    let master_key = prompt_master_key()?;
+    if !verify_master_key(&client, &master_key)? {
+        return Err(TuduError::auth("Master key is wrong"));
+    }
+
+    let report = diagnose(&client, &master_key)?;
+    println!("Checked {} tasks", report.task_count);
+    if report.integrity_errors.is_empty() {
+        println!("Integrity check: ok");
+    }
+    for integrity_error in report.integrity_errors.iter() {
+        println!("Integrity check: {}", integrity_error);
+    }
+
+    if report.undecryptable_tasks.is_empty() {
+        println!("All tasks decrypt with the master key");
+    } else {
+        let ids: Vec<String> = report
+            .undecryptable_tasks
+            .iter()
+            .map(|task| task.id.to_string())
+            .collect();
+        println!(
+            "{} tasks could not be decrypted, ids: {}",
+            ids.len(),
+            ids.join(", ")
+        );
+
+        if confirm("Move them to the quarantine table?", assume_yes)? {
+            create_backup(&client, &backups_path, max_backups)?;
+            let quarantined = quarantine_tasks(&client, &report.undecryptable_tasks)?;
+            println!("Quarantined {} tasks", quarantined);
+        }
+    }
+
+    if confirm("Vacuum and rebuild the database?", assume_yes)? {
+        create_backup(&client, &backups_path, max_backups)?;
+        client.rebuild_database()?;
+        let integrity_errors = client.integrity_check()?;
+        if integrity_errors.is_empty() {
+            println!("Database rebuilt, integrity check: ok");
+        } else {
+            println!(
+                "Database rebuilt but {} problems remain, restore a backup with `tudu backup restore`",
+                integrity_errors.len()
+            );
+        }
+    }
+
+    client.close_connection()?;
+    Ok(())
+}
+
+fn log_command(config: &Config, count: usize) -> TuduResult<()> {
+    let (mut client, _) = open_client(config)?;
+    // Databases from older versions get the task_events table here
+    client.create_todos_table()?;
+    let master_key = prompt_master_key()?;
+    if !verify_master_key(&client, &master_key)? {
+        return Err(TuduError::auth("Master key is wrong"));
+    }
+
+    let task_events = client.get_task_events(None, count)?;
+    if task_events.is_empty() {
+        println!("No changes recorded yet");
+    }
+    for mut event in task_events.into_iter().rev() {
+        event.title =
+            decrypt(&event.title, &master_key).unwrap_or_else(|_| String::from("[undecryptable]"));
+        println!("{:>5}  {}", event.task_id, event.format_line());
+    }
+
+    client.close_connection()?;
+    Ok(())
+}
+
+fn prompt_master_key() -> TuduResult<String> {
+    print!("Master key: ");
+    stdout().flush()?;
+
+    let mut master_key = String::new();
+    enable_raw_mode()?;
+    let result: TuduResult<()> = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Esc => break Err(TuduError::validation("Cancelled")),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(TuduError::validation("Cancelled"))
+                }
+                KeyCode::Char(ch) => master_key.push(ch),
+                KeyCode::Backspace => {
+                    master_key.pop();
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(TuduError::io("Could not read key", e)),
+        }
+    };
+    disable_raw_mode()?;
+    println!();
+
+    result.map(|_| master_key)
+}
+
+fn confirm(question: &str, assume_yes: bool) -> TuduResult<bool> {
+    if assume_yes {
+        println!("{} [y/N] y", question);
+        return Ok(true);
+    }
+
+    print!("{} [y/N] ", question);
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_command() {
+        let config = Config::default();
+        let result = run_command(&[String::from("unknown")], &config);
+        assert!(result.is_err());
+
+        let result = run_command(&[String::from("backup"), String::from("restore")], &config);
+        assert!(result.is_err());
+
+        let result = run_command(&[String::from("log"), String::from("many")], &config);
+        assert!(result.is_err());
+
+        let result = run_command(&[String::from("help")], &config);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_split_config_arg() {
+        let args: Vec<String> = ["--config", "./tudu.toml", "backup", "list"]
+            .iter()
+            .map(|arg| String::from(*arg))
+            .collect();
+        let (config_path, rest) = split_config_arg(&args).expect("Could not split args");
+        assert_eq!(config_path, Some(PathBuf::from("./tudu.toml")));
+        assert_eq!(rest, vec![String::from("backup"), String::from("list")]);
+
+        let args = vec![String::from("doctor"), String::from("--config=./tudu.toml")];
+        let (config_path, rest) = split_config_arg(&args).expect("Could not split args");
+        assert_eq!(config_path, Some(PathBuf::from("./tudu.toml")));
+        assert_eq!(rest, vec![String::from("doctor")]);
+
+        assert!(split_config_arg(&[String::from("--config")]).is_err());
+    }
+}
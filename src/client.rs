@@ -1,24 +1,64 @@
-use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use rusqlite::{Connection, Result};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rusqlite::backup::Progress;
+use rusqlite::types::{ToSql, Type, Value};
+use rusqlite::{params_from_iter, Connection, DatabaseName, Result, Row};
 
+use crate::bundle::TableDump;
+use crate::constants::SortField;
+use crate::error::{TuduError, TuduResult};
+use crate::history::{TaskEvent, TaskEventKind};
+use crate::pomodoro::PomodoroRecord;
+use crate::stats::TaskTotals;
 use crate::task::Task;
+use crate::timer::TimeEntry;
 use crate::user::User;
 
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const TIMESTAMP_COLUMNS: [&str; 3] = ["created_at", "updated_at", "completed_at"];
+const PLANNING_COLUMNS: [(&str, &str); 5] = [
+    ("due", "TEXT"),
+    ("priority", "INTEGER"),
+    ("recurrence", "TEXT"),
+    ("list", "TEXT"),
+    ("tags", "TEXT"),
+];
+const TASK_COLUMNS: &str = "id, title, status, created_at, updated_at, completed_at, version,
+     due, priority, recurrence, list, tags";
+// Tags are kept in one column, separated by commas
+const TAG_SEPARATOR: char = ',';
+// Lookups by task and the running timer check stay fast on large databases
+const INDEX_QUERIES: [&str; 3] = [
+    "CREATE INDEX IF NOT EXISTS task_events_task_id ON task_events (task_id, id)",
+    "CREATE INDEX IF NOT EXISTS time_entries_stopped_at ON time_entries (stopped_at, task_id)",
+    "CREATE INDEX IF NOT EXISTS pomodoros_task_id ON pomodoros (task_id)",
+];
+// Stays well below the number of parameters sqlite accepts in one statement
+const MAX_QUERY_PARAMETERS: usize = 500;
+// Completing a task stamps it, reopening clears the stamp
+const UPDATE_STATUS_QUERY: &str =
+    "UPDATE todos SET status=?1, updated_at=datetime('now', 'localtime'), version=version + 1,
+     completed_at=CASE WHEN ?1 = 'completed'
+     THEN COALESCE(completed_at, datetime('now', 'localtime')) END WHERE id=?2";
+// Stops the running timer of one task, or of any task when the id is null
+const STOP_TIMER_QUERY: &str = "UPDATE time_entries SET stopped_at=datetime('now', 'localtime')
+     WHERE stopped_at IS NULL AND (?1 IS NULL OR task_id=?1)";
+
 #[derive(Debug, Default)]
 pub struct Client {
     pub connection: Option<Connection>,
 }
 
 impl Client {
-    pub fn get_connection(&self) -> Result<&Connection, Error> {
+    pub fn get_connection(&self) -> TuduResult<&Connection> {
         match &self.connection {
-            Some(connection) => Ok(&connection),
-            None => Err(Error::new(
-                ErrorKind::Other,
-                String::from("Could not open connection"),
-            )),
+            Some(connection) => Ok(connection),
+            None => Err(TuduError::Storage {
+                context: String::from("Could not open connection"),
+                source: None,
+            }),
         }
     }
 
@@ -26,7 +66,7 @@ impl Client {
         &mut self,
         mut app_config_path: PathBuf,
         db_name: &str,
-    ) -> Result<(), Error> {
+    ) -> TuduResult<()> {
         app_config_path.push(db_name);
 
         match Connection::open(app_config_path) {
@@ -34,169 +74,908 @@ impl Client {
                 self.connection = Some(connection);
                 Ok(())
             }
-            Err(e) => Err(Error::new(
-                ErrorKind::Other,
-                format!("Could not open connection, e: {}", e),
-            )),
+            Err(e) => Err(TuduError::storage("Could not open connection", e)),
         }
     }
 
-    pub fn close_connection(&mut self) -> Result<(), Error> {
+    pub fn close_connection(&mut self) -> TuduResult<()> {
         match self.connection.take() {
             Some(connection) => connection
                 .close()
-                .map_err(|_| Error::new(ErrorKind::Other, "Could not close connection")),
-            None => Err(Error::new(ErrorKind::Other, "Could not find connection")),
+                .map_err(|(_, e)| TuduError::storage("Could not close connection", e)),
+            None => Err(TuduError::Storage {
+                context: String::from("Could not find connection"),
+                source: None,
+            }),
         }
     }
 
-    pub fn create_todos_table(&self) -> Result<usize, Error> {
+    pub fn create_todos_table(&self) -> TuduResult<usize> {
         let query = "CREATE TABLE IF NOT EXISTS todos (
                      id INTEGER NOT NULL PRIMARY KEY,
                      title TEXT,
-                     status TEXT
+                     status TEXT,
+                     created_at TEXT,
+                     updated_at TEXT,
+                     completed_at TEXT,
+                     version INTEGER NOT NULL DEFAULT 1,
+                     due TEXT,
+                     priority INTEGER,
+                     recurrence TEXT,
+                     list TEXT,
+                     tags TEXT
                     );";
-        let connection = self.get_connection();
-
-        match connection {
-            Ok(c) => {
-                let result = c.execute(query, []);
-                match result {
-                    Ok(r) => Ok(r),
-                    // How do i test here
-                    Err(e) => Err(Error::new(
-                        ErrorKind::Other,
-                        format!("Could not create todos table, e: {}", e),
-                    )),
-                }
+        let created_count = self
+            .get_connection()?
+            .execute(query, [])
+            .map_err(|e| TuduError::storage("Could not create todos table", e))?;
+        self.update_schema()?;
+        Ok(created_count)
+    }
+
+    // Databases from before timestamps were recorded get the columns, left empty,
+    // the row version, the planning columns, and the history, time tracking and pomodoro tables
+    fn update_schema(&self) -> TuduResult<()> {
+        let map_error = |e| TuduError::storage("Could not update database schema", e);
+        let connection = self.get_connection()?;
+        let columns = connection
+            .prepare("SELECT name FROM pragma_table_info('todos')")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<String>>>()
+            })
+            .map_err(map_error)?;
+
+        for column in TIMESTAMP_COLUMNS {
+            if !columns.iter().any(|name| name == column) {
+                connection
+                    .execute(&format!("ALTER TABLE todos ADD COLUMN {} TEXT", column), [])
+                    .map_err(map_error)?;
             }
-            Err(e) => Err(Error::new(
-                ErrorKind::Other,
-                format!("Could not get connection, e: {}", e),
-            )),
         }
+        if !columns.iter().any(|name| name == "version") {
+            connection
+                .execute(
+                    "ALTER TABLE todos ADD COLUMN version INTEGER NOT NULL DEFAULT 1",
+                    [],
+                )
+                .map_err(map_error)?;
+        }
+        for (column, column_type) in PLANNING_COLUMNS {
+            if !columns.iter().any(|name| name == column) {
+                connection
+                    .execute(
+                        &format!("ALTER TABLE todos ADD COLUMN {} {}", column, column_type),
+                        [],
+                    )
+                    .map_err(map_error)?;
+            }
+        }
+
+        connection
+            .execute(
+                "CREATE TABLE IF NOT EXISTS task_events (
+                 id INTEGER NOT NULL PRIMARY KEY,
+                 task_id INTEGER,
+                 kind TEXT,
+                 title TEXT,
+                 status TEXT,
+                 created_at TEXT
+                );",
+                [],
+            )
+            .map_err(map_error)?;
+        connection
+            .execute(
+                "CREATE TABLE IF NOT EXISTS time_entries (
+                 id INTEGER NOT NULL PRIMARY KEY,
+                 task_id INTEGER NOT NULL,
+                 started_at TEXT NOT NULL,
+                 stopped_at TEXT
+                );",
+                [],
+            )
+            .map_err(map_error)?;
+        connection
+            .execute(
+                "CREATE TABLE IF NOT EXISTS pomodoros (
+                 id INTEGER NOT NULL PRIMARY KEY,
+                 task_id INTEGER NOT NULL,
+                 minutes INTEGER NOT NULL,
+                 completed_at TEXT NOT NULL
+                );",
+                [],
+            )
+            .map_err(map_error)?;
+        for query in INDEX_QUERIES {
+            connection.execute(query, []).map_err(map_error)?;
+        }
+        Ok(())
     }
 
-    pub fn create_user_table(&self) -> Result<usize, Error> {
+    pub fn create_user_table(&self) -> TuduResult<usize> {
         let query = "CREATE TABLE IF NOT EXISTS user (
                    id INTEGER NOT NULL PRIMARY KEY,
                    secret TEXT
                   );";
-        let connection = self.get_connection();
-
-        match connection {
-            Ok(c) => {
-                let result = c.execute(query, []);
-                match result {
-                    Ok(r) => Ok(r),
-                    Err(e) => Err(Error::new(
-                        ErrorKind::Other,
-                        format!("Could not create user table, e: {}", e),
-                    )),
-                }
-            }
-            Err(e) => Err(Error::new(
-                ErrorKind::Other,
-                format!("Could not get connection, e: {}", e),
-            )),
+        self.get_connection()?
+            .execute(query, [])
+            .map_err(|e| TuduError::storage("Could not create user table", e))
+    }
+
+    pub fn get_tasks(&self) -> TuduResult<Vec<Task>> {
+        let map_error = |e| TuduError::storage("Could not get tasks", e);
+        let mut stmt = self
+            .get_connection()?
+            .prepare(&format!("SELECT {} FROM todos", TASK_COLUMNS))
+            .map_err(map_error)?;
+        let rows = stmt.query_map([], get_task_from_row).map_err(map_error)?;
+
+        rows.collect::<Result<Vec<Task>>>().map_err(map_error)
+    }
+
+    // Keyset paging by id, the next page starts after the last id of the previous one
+    pub fn get_task_page(&self, after_id: i32, limit: usize) -> TuduResult<Vec<Task>> {
+        let map_error = |e| TuduError::storage("Could not get tasks", e);
+        let mut stmt = self
+            .get_connection()?
+            .prepare(&format!(
+                "SELECT {} FROM todos WHERE id > ?1 ORDER BY id LIMIT ?2",
+                TASK_COLUMNS
+            ))
+            .map_err(map_error)?;
+        let rows = stmt
+            .query_map((after_id, limit as i64), get_task_from_row)
+            .map_err(map_error)?;
+
+        rows.collect::<Result<Vec<Task>>>().map_err(map_error)
+    }
+
+    // Rows that no longer exist are left out, used to refresh only what a change touched
+    pub fn get_tasks_by_ids(&self, ids: &[i32]) -> TuduResult<Vec<Task>> {
+        let map_error = |e| TuduError::storage("Could not get tasks", e);
+        let connection = self.get_connection()?;
+
+        let mut task_list = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(MAX_QUERY_PARAMETERS) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = connection
+                .prepare(&format!(
+                    "SELECT {} FROM todos WHERE id IN ({})",
+                    TASK_COLUMNS, placeholders
+                ))
+                .map_err(map_error)?;
+            let rows = stmt
+                .query_map(params_from_iter(chunk.iter()), get_task_from_row)
+                .map_err(map_error)?;
+            task_list.extend(rows.collect::<Result<Vec<Task>>>().map_err(map_error)?);
         }
+        Ok(task_list)
     }
 
-    pub fn get_tasks(&self) -> Result<Vec<Task>, Box<dyn std::error::Error>> {
-        let mut stmt = self.get_connection()?.prepare("SELECT * FROM todos")?;
-        let rows = stmt.query_map([], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                status: row.get(2)?,
-            })
+    // Ids in list order, sorting by title is left to whoever can decrypt them
+    pub fn get_task_ids(&self, sort_field: SortField) -> TuduResult<Vec<i32>> {
+        let map_error = |e| TuduError::storage("Could not get tasks", e);
+        let order = sort_field.get_order_clause().ok_or_else(|| {
+            TuduError::validation(&format!("Cannot sort by {} here", sort_field.get_name()))
         })?;
+        let mut stmt = self
+            .get_connection()?
+            .prepare(&format!("SELECT id FROM todos ORDER BY {}", order))
+            .map_err(map_error)?;
+        let rows = stmt.query_map([], |row| row.get(0)).map_err(map_error)?;
+
+        rows.collect::<Result<Vec<i32>>>().map_err(map_error)
+    }
 
-        let mut tasks = Vec::new();
-        for task_result in rows {
-            tasks.push(task_result?);
+    // Counted by the database so no title has to be read, completions from the day given on
+    pub fn get_task_totals(&self, since: NaiveDate) -> TuduResult<TaskTotals> {
+        let map_error = |e| TuduError::storage("Could not get statistics", e);
+        let connection = self.get_connection()?;
+        let mut task_totals = connection
+            .query_row(
+                "SELECT COALESCE(SUM(status IS NOT 'completed'), 0),
+                 COALESCE(SUM(status = 'completed'), 0),
+                 COALESCE(SUM(created_at IS NULL), 0),
+                 AVG(CASE WHEN created_at IS NOT NULL AND completed_at IS NOT NULL
+                 THEN MAX(0, strftime('%s', completed_at) - strftime('%s', created_at)) END)
+                 FROM todos",
+                [],
+                |row| {
+                    Ok(TaskTotals {
+                        open_count: row.get::<_, i64>(0)? as usize,
+                        done_count: row.get::<_, i64>(1)? as usize,
+                        untracked_count: row.get::<_, i64>(2)? as usize,
+                        average_completion: row
+                            .get::<_, Option<f64>>(3)?
+                            .map(|seconds| Duration::seconds(seconds as i64)),
+                        ..Default::default()
+                    })
+                },
+            )
+            .map_err(map_error)?;
+
+        let mut stmt = connection
+            .prepare(
+                "SELECT date(completed_at), COUNT(*) FROM todos
+                 WHERE completed_at >= ?1 GROUP BY date(completed_at)",
+            )
+            .map_err(map_error)?;
+        let rows = stmt
+            .query_map([since.format("%Y-%m-%d").to_string()], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?))
+            })
+            .map_err(map_error)?;
+        for row in rows {
+            let (day, count) = row.map_err(map_error)?;
+            let day = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                .map_err(|e| TuduError::storage("Could not get statistics", e))?;
+            task_totals.completions.insert(day, count);
         }
+        Ok(task_totals)
+    }
 
-        Ok(tasks)
+    pub fn create_task(&self, title: String) -> TuduResult<i32> {
+        self.create_task_with_status(title, "in-progress")
     }
 
-    pub fn create_task(&self, title: String) -> Result<usize, Error> {
-        self.get_connection()?
-            .execute(
-                "INSERT INTO todos (title, status) VALUES(?1, 'in-progress')",
-                [format!("{:?}", title)],
-            )
-            .map_err(|e| Error::new(ErrorKind::Other, format!("Could not insert task, e: {}", e)))
+    // Returns the id of the new row
+    pub fn create_task_with_status(&self, title: String, status: &str) -> TuduResult<i32> {
+        self.import_task(&Task {
+            title,
+            status: String::from(status),
+            ..Default::default()
+        })
     }
 
-    pub fn create_user(&self, secret: String) -> Result<usize, Error> {
+    // Inserts a new row keeping the timestamps of the task, missing ones are set to now
+    pub fn import_task(&self, task: &Task) -> TuduResult<i32> {
+        let map_error = |e| TuduError::storage("Could not insert task", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        let id = insert_task(&transaction, task).map_err(map_error)?;
+        transaction.commit().map_err(map_error)?;
+        Ok(id)
+    }
+
+    pub fn create_user(&self, secret: String) -> TuduResult<usize> {
         self.get_connection()?
             .execute(
                 "INSERT INTO user (secret) VALUES(?1)",
                 [format!("{:?}", secret)],
             )
-            .map_err(|e| Error::new(ErrorKind::Other, format!("Could not insert user, e: {}", e)))
+            .map_err(|e| TuduError::storage("Could not insert user", e))
     }
 
-    pub fn get_user(&self) -> Result<Vec<User>, Box<dyn std::error::Error>> {
+    pub fn get_user(&self) -> TuduResult<Vec<User>> {
+        let map_error = |e| TuduError::storage("Could not get user", e);
         let mut stmt = self
             .get_connection()?
-            .prepare("SELECT * FROM user where id=1")?;
-        let rows = stmt.query_map([], |row| {
-            Ok(User {
-                id: row.get(0)?,
-                secret: row.get(1)?,
+            .prepare("SELECT * FROM user where id=1")
+            .map_err(map_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(User {
+                    id: row.get(0)?,
+                    secret: row.get(1)?,
+                })
             })
-        })?;
-
-        let mut user = Vec::new();
-        for user_result in rows {
-            user.push(user_result?);
-        }
+            .map_err(map_error)?;
 
-        Ok(user)
+        rows.collect::<Result<Vec<User>>>().map_err(map_error)
     }
 
-    pub fn remove_task(&self, id: i32) -> Result<usize, Error> {
-        self.get_connection()?
-            .execute("DELETE FROM todos where id=?1", [id])
-            .map_err(|e| Error::new(ErrorKind::Other, format!("Could not remove task, e: {}", e)))
+    pub fn remove_task(&self, id: i32) -> TuduResult<usize> {
+        self.remove_tasks(&[id])
     }
 
-    pub fn update_task(&self, id: i32, current_status: &str) -> Result<usize, Error> {
+    pub fn update_task(&self, id: i32, current_status: &str) -> TuduResult<usize> {
         let new_status = if current_status == "in-progress" {
             "completed"
         } else {
             "in-progress"
         };
+        self.update_tasks_status(&[id], new_status)
+    }
+
+    // Bulk changes run in one transaction so they either all apply or none do
+    pub fn update_tasks_status(&self, ids: &[i32], status: &str) -> TuduResult<usize> {
+        let map_error = |e| TuduError::storage("Could not update tasks", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        let mut updated_count = 0;
+        for id in ids.iter() {
+            updated_count += transaction
+                .execute(UPDATE_STATUS_QUERY, (status, id))
+                .map_err(map_error)?;
+            record_event(&transaction, *id, TaskEventKind::StatusChanged).map_err(map_error)?;
+        }
+        transaction.commit().map_err(map_error)?;
+        Ok(updated_count)
+    }
+
+    // A task keeps one list, none takes it out of its list
+    pub fn update_tasks_list(&self, ids: &[i32], list: Option<&str>) -> TuduResult<usize> {
+        self.update_tasks_details(ids, "list=?1", list)
+    }
+
+    // Tags a task already has are not added again
+    pub fn add_tasks_tag(&self, ids: &[i32], tag: &str) -> TuduResult<usize> {
+        if tag.is_empty() || tag.contains(TAG_SEPARATOR) || tag.contains(char::is_whitespace) {
+            return Err(TuduError::validation(
+                "Tags cannot be empty or contain commas or spaces",
+            ));
+        }
+        self.update_tasks_details(
+            ids,
+            "tags=CASE WHEN tags IS NULL OR tags = '' THEN ?1
+             WHEN instr(',' || tags || ',', ',' || ?1 || ',') > 0 THEN tags
+             ELSE tags || ',' || ?1 END",
+            tag,
+        )
+    }
+
+    pub fn update_tasks_priority(&self, ids: &[i32], priority: Option<u8>) -> TuduResult<usize> {
+        self.update_tasks_details(ids, "priority=?1", priority)
+    }
+
+    // All rows change in one transaction, each one gets a history event
+    fn update_tasks_details<T: ToSql>(
+        &self,
+        ids: &[i32],
+        assignment: &str,
+        value: T,
+    ) -> TuduResult<usize> {
+        let map_error = |e| TuduError::storage("Could not update tasks", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        let query = format!(
+            "UPDATE todos SET {}, updated_at=datetime('now', 'localtime'),
+             version=version + 1 WHERE id=?2",
+            assignment
+        );
+        let mut updated_count = 0;
+        for id in ids.iter() {
+            updated_count += transaction
+                .execute(&query, (&value, id))
+                .map_err(map_error)?;
+            record_event(&transaction, *id, TaskEventKind::DetailsChanged).map_err(map_error)?;
+        }
+        transaction.commit().map_err(map_error)?;
+        Ok(updated_count)
+    }
+
+    // Ids of the tasks in a list or with a tag, in no particular order
+    pub fn get_task_ids_in_list(&self, list: &str) -> TuduResult<Vec<i32>> {
+        self.get_task_ids_where("list=?1", list)
+    }
+
+    pub fn get_task_ids_with_tag(&self, tag: &str) -> TuduResult<Vec<i32>> {
+        self.get_task_ids_where("instr(',' || tags || ',', ',' || ?1 || ',') > 0", tag)
+    }
+
+    fn get_task_ids_where(&self, condition: &str, value: &str) -> TuduResult<Vec<i32>> {
+        let map_error = |e| TuduError::storage("Could not get tasks", e);
+        let mut stmt = self
+            .get_connection()?
+            .prepare(&format!("SELECT id FROM todos WHERE {}", condition))
+            .map_err(map_error)?;
+        let rows = stmt
+            .query_map([value], |row| row.get(0))
+            .map_err(map_error)?;
+
+        rows.collect::<Result<Vec<i32>>>().map_err(map_error)
+    }
+
+    pub fn remove_tasks(&self, ids: &[i32]) -> TuduResult<usize> {
+        let map_error = |e| TuduError::storage("Could not remove tasks", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        let mut removed_count = 0;
+        for id in ids.iter() {
+            record_event(&transaction, *id, TaskEventKind::Deleted).map_err(map_error)?;
+            transaction
+                .execute(STOP_TIMER_QUERY, [id])
+                .map_err(map_error)?;
+            removed_count += transaction
+                .execute("DELETE FROM todos where id=?1", [id])
+                .map_err(map_error)?;
+        }
+        transaction.commit().map_err(map_error)?;
+        Ok(removed_count)
+    }
+
+    // Puts rows back as they were stored, used to undo changes,
+    // the version still moves forward so cached titles are not reused
+    pub fn restore_tasks(&self, task_list: &[Task]) -> TuduResult<usize> {
+        let map_error = |e| TuduError::storage("Could not restore tasks", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        for task in task_list.iter() {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO todos
+                     (id, title, status, created_at, updated_at, completed_at, version,
+                     due, priority, recurrence, list, tags)
+                     VALUES(?1, ?2, ?3, ?4, ?5, ?6,
+                     MAX(?7, COALESCE((SELECT version FROM todos WHERE id=?1), 0)) + 1,
+                     ?8, ?9, ?10, ?11, ?12)",
+                    (
+                        task.id,
+                        &task.title,
+                        &task.status,
+                        task.created_at.map(format_timestamp),
+                        task.updated_at.map(format_timestamp),
+                        task.completed_at.map(format_timestamp),
+                        task.version,
+                        task.due.map(format_timestamp),
+                        task.priority,
+                        &task.recurrence,
+                        &task.list,
+                        format_tags(&task.tags),
+                    ),
+                )
+                .map_err(map_error)?;
+            record_event(&transaction, task.id, TaskEventKind::Restored).map_err(map_error)?;
+        }
+        transaction.commit().map_err(map_error)?;
+        Ok(task_list.len())
+    }
+
+    pub fn update_task_title(&self, id: i32, title: String) -> TuduResult<usize> {
+        let map_error = |e| TuduError::storage("Could not update task", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        let updated_count = transaction
+            .execute(
+                "UPDATE todos SET title=?1, updated_at=datetime('now', 'localtime'),
+                 version=version + 1 WHERE id=?2",
+                (format!("{:?}", title), id),
+            )
+            .map_err(map_error)?;
+        record_event(&transaction, id, TaskEventKind::TitleChanged).map_err(map_error)?;
+        transaction.commit().map_err(map_error)?;
+        Ok(updated_count)
+    }
+
+    // Newest first, for one task or for all of them
+    pub fn get_task_events(
+        &self,
+        task_id: Option<i32>,
+        limit: usize,
+    ) -> TuduResult<Vec<TaskEvent>> {
+        let map_error = |e| TuduError::storage("Could not get task history", e);
+        // Without an OR in the filter sqlite can use the task_id index
+        let filter = if task_id.is_some() {
+            "task_id=?1"
+        } else {
+            "?1 IS NULL"
+        };
+        let mut stmt = self
+            .get_connection()?
+            .prepare(&format!(
+                "SELECT id, task_id, kind, title, status, created_at FROM task_events
+                 WHERE {} ORDER BY id DESC LIMIT ?2",
+                filter
+            ))
+            .map_err(map_error)?;
+        let rows = stmt
+            .query_map((task_id, limit as i64), |row| {
+                let kind: String = row.get(2)?;
+                Ok(TaskEvent {
+                    id: row.get(0)?,
+                    task_id: row.get(1)?,
+                    kind: TaskEventKind::from_name(&kind)
+                        .ok_or(rusqlite::Error::InvalidColumnType(2, kind, Type::Text))?,
+                    title: row.get(3)?,
+                    status: row.get(4)?,
+                    created_at: parse_timestamp(row.get(5)?),
+                })
+            })
+            .map_err(map_error)?;
+
+        rows.collect::<Result<Vec<TaskEvent>>>().map_err(map_error)
+    }
+
+    // Only one timer runs at a time, starting one stops the others
+    pub fn start_timer(&self, task_id: i32) -> TuduResult<usize> {
+        let map_error = |e| TuduError::storage("Could not start timer", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        transaction
+            .execute(STOP_TIMER_QUERY, [None::<i32>])
+            .map_err(map_error)?;
+        let started_count = transaction
+            .execute(
+                "INSERT INTO time_entries (task_id, started_at)
+                 VALUES(?1, datetime('now', 'localtime'))",
+                [task_id],
+            )
+            .map_err(map_error)?;
+        transaction.commit().map_err(map_error)?;
+        Ok(started_count)
+    }
+
+    pub fn stop_timer(&self) -> TuduResult<usize> {
         self.get_connection()?
-            .execute("UPDATE todos SET status=?1 WHERE id=?2", (new_status, id))
-            .map_err(|e| Error::new(ErrorKind::Other, format!("Could not update task, e: {}", e)))
+            .execute(STOP_TIMER_QUERY, [None::<i32>])
+            .map_err(|e| TuduError::storage("Could not stop timer", e))
     }
 
-    pub fn remove_user(&self) -> Result<usize, Error> {
+    pub fn get_time_entries(&self) -> TuduResult<Vec<TimeEntry>> {
+        let map_error = |e| TuduError::storage("Could not get time entries", e);
+        let mut stmt = self
+            .get_connection()?
+            .prepare("SELECT id, task_id, started_at, stopped_at FROM time_entries ORDER BY id")
+            .map_err(map_error)?;
+        let rows = stmt
+            .query_map([], |row| {
+                let started_at: String = row.get(2)?;
+                Ok(TimeEntry {
+                    id: row.get(0)?,
+                    task_id: row.get(1)?,
+                    started_at: parse_timestamp(Some(started_at.clone())).ok_or(
+                        rusqlite::Error::InvalidColumnType(2, started_at, Type::Text),
+                    )?,
+                    stopped_at: parse_timestamp(row.get(3)?),
+                })
+            })
+            .map_err(map_error)?;
+
+        rows.collect::<Result<Vec<TimeEntry>>>().map_err(map_error)
+    }
+
+    pub fn record_pomodoro(&self, task_id: i32, minutes: u64) -> TuduResult<usize> {
+        self.get_connection()?
+            .execute(
+                "INSERT INTO pomodoros (task_id, minutes, completed_at)
+                 VALUES(?1, ?2, datetime('now', 'localtime'))",
+                (task_id, minutes as i64),
+            )
+            .map_err(|e| TuduError::storage("Could not record pomodoro", e))
+    }
+
+    // Puts back the history, time entries and pomodoros of restored tasks in one transaction
+    // Tasks get new ids and the records follow them through the old ids, a failure on any
+    // row leaves the database as it was. Returns the new ids in the order of the tasks
+    pub fn import_rows(
+        &self,
+        task_list: &[Task],
+        task_events: &[TaskEvent],
+        time_entries: &[TimeEntry],
+        pomodoros: &[PomodoroRecord],
+    ) -> TuduResult<Vec<i32>> {
+        let map_error = |e| TuduError::storage("Could not import rows", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        let mut task_ids = HashMap::new();
+        for task in task_list.iter() {
+            let id = insert_task(&transaction, task).map_err(map_error)?;
+            task_ids.insert(task.id, id);
+        }
+        // Records of tasks that were not imported are left out
+        for event in task_events.iter() {
+            let Some(task_id) = task_ids.get(&event.task_id) else {
+                continue;
+            };
+            transaction
+                .execute(
+                    "INSERT INTO task_events (task_id, kind, title, status, created_at)
+                     VALUES(?1, ?2, ?3, ?4, ?5)",
+                    (
+                        task_id,
+                        event.kind.get_name(),
+                        &event.title,
+                        &event.status,
+                        event.created_at.map(format_timestamp),
+                    ),
+                )
+                .map_err(map_error)?;
+        }
+        for time_entry in time_entries.iter() {
+            let Some(task_id) = task_ids.get(&time_entry.task_id) else {
+                continue;
+            };
+            transaction
+                .execute(
+                    "INSERT INTO time_entries (task_id, started_at, stopped_at) VALUES(?1, ?2, ?3)",
+                    (
+                        task_id,
+                        format_timestamp(time_entry.started_at),
+                        time_entry.stopped_at.map(format_timestamp),
+                    ),
+                )
+                .map_err(map_error)?;
+        }
+        for pomodoro in pomodoros.iter() {
+            let Some(task_id) = task_ids.get(&pomodoro.task_id) else {
+                continue;
+            };
+            transaction
+                .execute(
+                    "INSERT INTO pomodoros (task_id, minutes, completed_at) VALUES(?1, ?2, ?3)",
+                    (
+                        task_id,
+                        pomodoro.minutes as i64,
+                        format_timestamp(pomodoro.completed_at),
+                    ),
+                )
+                .map_err(map_error)?;
+        }
+        transaction.commit().map_err(map_error)?;
+
+        Ok(task_list
+            .iter()
+            .filter_map(|task| task_ids.get(&task.id).copied())
+            .collect())
+    }
+
+    pub fn get_pomodoro_counts(&self) -> TuduResult<HashMap<i32, u32>> {
+        let map_error = |e| TuduError::storage("Could not get pomodoros", e);
+        let mut stmt = self
+            .get_connection()?
+            .prepare("SELECT task_id, COUNT(*) FROM pomodoros GROUP BY task_id")
+            .map_err(map_error)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(map_error)?;
+
+        rows.collect::<Result<HashMap<i32, u32>>>()
+            .map_err(map_error)
+    }
+
+    pub fn dump_tables(&self) -> TuduResult<Vec<TableDump>> {
+        let map_error = |e| TuduError::storage("Could not dump tables", e);
+        let connection = self.get_connection()?;
+        let mut stmt = connection
+            .prepare(
+                "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            )
+            .map_err(map_error)?;
+        let table_names = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<String>>>())
+            .map_err(map_error)?;
+
+        let mut tables = Vec::new();
+        for name in table_names {
+            let mut stmt = connection
+                .prepare(&format!("SELECT * FROM \"{}\"", name))
+                .map_err(map_error)?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let rows = stmt
+                .query_map([], |row| {
+                    (0..columns.len())
+                        .map(|index| {
+                            Ok(match row.get::<_, Value>(index)? {
+                                Value::Null => String::new(),
+                                Value::Integer(value) => value.to_string(),
+                                Value::Real(value) => value.to_string(),
+                                Value::Text(value) => value,
+                                Value::Blob(value) => hex::encode(value),
+                            })
+                        })
+                        .collect::<Result<Vec<String>>>()
+                })
+                .and_then(|rows| rows.collect::<Result<Vec<Vec<String>>>>())
+                .map_err(map_error)?;
+
+            tables.push(TableDump {
+                name,
+                columns,
+                rows,
+            });
+        }
+
+        Ok(tables)
+    }
+
+    pub fn backup_database(&self, path: &Path) -> TuduResult<()> {
+        self.get_connection()?
+            .backup(DatabaseName::Main, path, None)
+            .map_err(|e| TuduError::storage("Could not backup database", e))
+    }
+
+    // Backups taken before timestamps were recorded are brought up to date
+    pub fn restore_database(&mut self, path: &Path) -> TuduResult<()> {
+        match self.connection.as_mut() {
+            Some(connection) => connection
+                .restore(DatabaseName::Main, path, None::<fn(Progress)>)
+                .map_err(|e| TuduError::storage("Could not restore database", e))?,
+            None => {
+                return Err(TuduError::Storage {
+                    context: String::from("Could not find connection"),
+                    source: None,
+                })
+            }
+        }
+        self.update_schema()
+    }
+
+    pub fn create_quarantine_table(&self) -> TuduResult<usize> {
+        let query = "CREATE TABLE IF NOT EXISTS quarantine (
+                     id INTEGER NOT NULL PRIMARY KEY,
+                     task_id INTEGER,
+                     title TEXT,
+                     status TEXT,
+                     reason TEXT,
+                     quarantined_at TEXT
+                    );";
+        self.get_connection()?
+            .execute(query, [])
+            .map_err(|e| TuduError::storage("Could not create quarantine table", e))
+    }
+
+    // Returns the problems reported by sqlite, an empty list means the database is fine
+    pub fn integrity_check(&self) -> TuduResult<Vec<String>> {
+        let map_error = |e| TuduError::storage("Could not check integrity", e);
+        let connection = self.get_connection()?;
+        let mut stmt = connection
+            .prepare("PRAGMA integrity_check")
+            .map_err(map_error)?;
+        let messages = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<Result<Vec<String>>>())
+            .map_err(map_error)?;
+
+        Ok(messages
+            .into_iter()
+            .filter(|message| message != "ok")
+            .collect())
+    }
+
+    pub fn quarantine_task(&self, task: &Task, reason: &str) -> TuduResult<()> {
+        let map_error = |e| TuduError::storage("Could not quarantine task", e);
+        let transaction = self
+            .get_connection()?
+            .unchecked_transaction()
+            .map_err(map_error)?;
+
+        transaction
+            .execute(
+                "INSERT INTO quarantine (task_id, title, status, reason, quarantined_at)
+                 VALUES(?1, ?2, ?3, ?4, datetime('now', 'localtime'))",
+                (task.id, &task.title, &task.status, reason),
+            )
+            .map_err(map_error)?;
+        transaction
+            .execute("DELETE FROM todos where id=?1", [task.id])
+            .map_err(map_error)?;
+        transaction.commit().map_err(map_error)
+    }
+
+    pub fn rebuild_database(&self) -> TuduResult<()> {
+        self.get_connection()?
+            .execute_batch("REINDEX; VACUUM;")
+            .map_err(|e| TuduError::storage("Could not rebuild database", e))
+    }
+
+    pub fn remove_user(&self) -> TuduResult<usize> {
         self.get_connection()?
             .execute("DELETE FROM user", [])
-            .map_err(|e| Error::new(ErrorKind::Other, format!("Could not remove user, e: {}", e)))
+            .map_err(|e| TuduError::storage("Could not remove user", e))
+    }
+}
+
+// Missing timestamps are stamped now, the created event is recorded with the row
+fn insert_task(connection: &Connection, task: &Task) -> Result<i32> {
+    connection.execute(
+        "INSERT INTO todos
+         (title, status, created_at, updated_at, completed_at, due, priority, recurrence,
+         list, tags)
+         VALUES(?1, ?2, COALESCE(?3, datetime('now', 'localtime')),
+         COALESCE(?4, datetime('now', 'localtime')),
+         CASE WHEN ?2 = 'completed' THEN COALESCE(?5, datetime('now', 'localtime')) END,
+         ?6, ?7, ?8, ?9, ?10)",
+        (
+            format!("{:?}", task.title),
+            &task.status,
+            task.created_at.map(format_timestamp),
+            task.updated_at.map(format_timestamp),
+            task.completed_at.map(format_timestamp),
+            task.due.map(format_timestamp),
+            task.priority,
+            &task.recurrence,
+            &task.list,
+            format_tags(&task.tags),
+        ),
+    )?;
+    let id = connection.last_insert_rowid() as i32;
+    record_event(connection, id, TaskEventKind::Created)?;
+    Ok(id)
+}
+
+// Copies the task row as it is now, so deleted and renamed tasks keep their history
+fn record_event(connection: &Connection, task_id: i32, kind: TaskEventKind) -> Result<usize> {
+    connection.execute(
+        "INSERT INTO task_events (task_id, kind, title, status, created_at)
+         SELECT id, ?2, title, status, datetime('now', 'localtime') FROM todos WHERE id=?1",
+        (task_id, kind.get_name()),
+    )
+}
+
+fn get_task_from_row(row: &Row) -> Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        status: row.get(2)?,
+        created_at: parse_timestamp(row.get(3)?),
+        updated_at: parse_timestamp(row.get(4)?),
+        completed_at: parse_timestamp(row.get(5)?),
+        version: row.get(6)?,
+        due: parse_timestamp(row.get(7)?),
+        priority: row.get(8)?,
+        recurrence: row.get(9)?,
+        list: row.get(10)?,
+        tags: parse_tags(row.get(11)?),
+    })
+}
+
+fn parse_tags(value: Option<String>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(TAG_SEPARATOR)
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// No tags are stored as null
+fn format_tags(tags: &[String]) -> Option<String> {
+    if tags.is_empty() {
+        None
+    } else {
+        Some(tags.join(&TAG_SEPARATOR.to_string()))
     }
 }
 
+fn parse_timestamp(value: Option<String>) -> Option<NaiveDateTime> {
+    value.and_then(|value| NaiveDateTime::parse_from_str(&value, TIMESTAMP_FORMAT).ok())
+}
+
+fn format_timestamp(timestamp: NaiveDateTime) -> String {
+    timestamp.format(TIMESTAMP_FORMAT).to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::constants::{self, DB_NAME};
+    use crate::filesystem::get_test_dir;
 
     use super::*;
 
     #[test]
     fn test_connection_operations() {
         let mut client = Client::default();
-        let mut path = PathBuf::new();
+        let path = get_test_dir("client");
 
         let title = String::from("Test client module");
         let secret = String::from("SECRET");
 
-        path.push("./test/client/");
-
         client
             .open_connection(path, constants::DB_NAME)
             .expect("Could not open connection");
@@ -220,21 +999,91 @@ mod tests {
         assert_eq!(tasks.len(), 1);
         assert_eq!(users.len(), 1);
 
+        let tables = client.dump_tables().expect("Could not dump tables");
+        let todos = tables
+            .iter()
+            .find(|table| table.name == "todos")
+            .expect("Could not find todos table");
+        assert_eq!(
+            todos.columns,
+            vec![
+                "id",
+                "title",
+                "status",
+                "created_at",
+                "updated_at",
+                "completed_at",
+                "version",
+                "due",
+                "priority",
+                "recurrence",
+                "list",
+                "tags"
+            ]
+        );
+        assert_eq!(todos.rows.len(), 1);
+        assert!(tables.iter().any(|table| table.name == "user"));
+
         client
             .update_task(1, "in-progress")
             .expect("Could not update task");
         tasks = client.get_tasks().expect("Could not get tasks");
-        let task = tasks.get(0).expect("Could not get task 0");
+        let task = tasks.first().expect("Could not get task 0");
 
         assert_eq!(task.status, "completed");
+        assert!(task.created_at.is_some());
+        assert!(task.completed_at >= task.created_at);
 
         client
             .update_task(1, "completed")
             .expect("Could not update task");
         tasks = client.get_tasks().expect("Could not get tasks");
-        let task = tasks.get(0).expect("Could not get task 0");
+        let task = tasks.first().expect("Could not get task 0");
 
         assert_eq!(task.status, "in-progress");
+        assert_eq!(task.completed_at, None);
+
+        client
+            .update_task_title(1, String::from("Renamed"))
+            .expect("Could not update task title");
+        tasks = client.get_tasks().expect("Could not get tasks");
+        let task = tasks.first().expect("Could not get task 0");
+
+        assert_eq!(task.title, "\"Renamed\"");
+
+        client
+            .create_task(String::from("Second"))
+            .expect("Could not create task");
+        let saved_tasks = client.get_tasks().expect("Could not get tasks");
+        client
+            .update_tasks_status(&[1, 2], "completed")
+            .expect("Could not update tasks");
+        tasks = client.get_tasks().expect("Could not get tasks");
+        assert!(tasks.iter().all(|task| task.status == "completed"));
+
+        assert_eq!(
+            client
+                .remove_tasks(&[1, 2])
+                .expect("Could not remove tasks"),
+            2
+        );
+        assert_eq!(client.get_tasks().expect("Could not get tasks").len(), 0);
+        client
+            .restore_tasks(&saved_tasks)
+            .expect("Could not restore tasks");
+        // Restored rows come back with a newer version
+        let restored_tasks: Vec<Task> = saved_tasks
+            .iter()
+            .map(|task| Task {
+                version: task.version + 1,
+                ..task.clone()
+            })
+            .collect();
+        assert_eq!(
+            client.get_tasks().expect("Could not get tasks"),
+            restored_tasks
+        );
+        client.remove_task(2).expect("Could not remove task");
 
         client.remove_task(1).expect("Could not remove connection");
         client.remove_user().expect("Could not remove user");
@@ -243,6 +1092,380 @@ mod tests {
             .expect("Could not close connection");
     }
 
+    #[test]
+    fn test_task_pages() {
+        let mut client = Client::default();
+        client
+            .open_connection(PathBuf::new(), ":memory:")
+            .expect("Could not open connection");
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+        for title in ["a", "b", "c", "d", "e"] {
+            client
+                .create_task(String::from(title))
+                .expect("Could not create task");
+        }
+        client.remove_task(2).expect("Could not remove task");
+
+        let page = client.get_task_page(0, 2).expect("Could not get page");
+        assert_eq!(
+            page.iter().map(|task| task.id).collect::<Vec<i32>>(),
+            [1, 3]
+        );
+        let page = client.get_task_page(3, 2).expect("Could not get page");
+        assert_eq!(
+            page.iter().map(|task| task.id).collect::<Vec<i32>>(),
+            [4, 5]
+        );
+        assert!(client
+            .get_task_page(5, 2)
+            .expect("Could not get page")
+            .is_empty());
+
+        let index_count: i32 = client
+            .get_connection()
+            .expect("Could not get connection")
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='index' AND sql IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .expect("Could not count indices");
+        assert_eq!(index_count, INDEX_QUERIES.len() as i32);
+    }
+
+    #[test]
+    fn test_timestamp_columns() {
+        let mut client = Client::default();
+        client
+            .open_connection(PathBuf::new(), ":memory:")
+            .expect("Could not open connection");
+        client
+            .get_connection()
+            .expect("Could not get connection")
+            .execute_batch(
+                "CREATE TABLE todos (id INTEGER NOT NULL PRIMARY KEY, title TEXT, status TEXT);
+                 INSERT INTO todos (title, status) VALUES('\"old\"', 'completed');",
+            )
+            .expect("Could not create old todos table");
+
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+        let id = client
+            .create_task_with_status(String::from("new"), "completed")
+            .expect("Could not create task");
+        assert_eq!(id, 2);
+        let tasks = client.get_tasks().expect("Could not get tasks");
+        assert_eq!(
+            client
+                .get_tasks_by_ids(&[2, 7])
+                .expect("Could not get tasks by ids"),
+            tasks[1..]
+        );
+        assert_eq!(tasks[0].created_at, None);
+        assert_eq!(tasks[0].completed_at, None);
+        assert!(tasks[1].created_at.is_some());
+        assert_eq!(tasks[1].completed_at, tasks[1].created_at);
+
+        let created_at = NaiveDateTime::parse_from_str("2024-10-19 15:30:00", TIMESTAMP_FORMAT)
+            .expect("Could not parse timestamp");
+        let task_list = vec![Task {
+            id: 1,
+            title: String::from("\"old\""),
+            status: String::from("in-progress"),
+            created_at: Some(created_at),
+            updated_at: Some(created_at),
+            completed_at: None,
+            version: 1,
+            due: Some(created_at),
+            priority: Some(3),
+            recurrence: Some(String::from("FREQ=DAILY")),
+            list: Some(String::from("home")),
+            tags: vec![String::from("garden")],
+        }];
+        client
+            .restore_tasks(&task_list)
+            .expect("Could not restore tasks");
+        let restored_task = Task {
+            version: 2,
+            ..task_list[0].clone()
+        };
+        assert_eq!(
+            client.get_tasks().expect("Could not get tasks")[0],
+            restored_task
+        );
+
+        // Imported tasks keep their timestamps and planning
+        let id = client
+            .import_task(&Task {
+                title: String::from("imported"),
+                status: String::from("completed"),
+                created_at: Some(created_at),
+                due: Some(created_at),
+                priority: Some(1),
+                recurrence: Some(String::from("FREQ=WEEKLY;BYDAY=FR")),
+                ..Default::default()
+            })
+            .expect("Could not import task");
+        let imported_task = &client
+            .get_tasks_by_ids(&[id])
+            .expect("Could not get tasks by ids")[0];
+        assert_eq!(imported_task.created_at, Some(created_at));
+        assert!(imported_task
+            .updated_at
+            .is_some_and(|updated_at| updated_at > created_at));
+        assert!(imported_task.completed_at.is_some());
+        assert_eq!(imported_task.due, Some(created_at));
+        assert_eq!(imported_task.priority, Some(1));
+        assert_eq!(
+            imported_task.recurrence.as_deref(),
+            Some("FREQ=WEEKLY;BYDAY=FR")
+        );
+    }
+
+    #[test]
+    fn test_task_totals() {
+        let mut client = Client::default();
+        client
+            .open_connection(PathBuf::new(), ":memory:")
+            .expect("Could not open connection");
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+        let parse = |timestamp: &str| {
+            NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+                .expect("Could not parse timestamp")
+        };
+        let get_task = |status: &str, created_at: Option<&str>, completed_at: Option<&str>| Task {
+            title: String::from("\"title\""),
+            status: String::from(status),
+            created_at: created_at.map(parse),
+            updated_at: completed_at.or(created_at).map(parse),
+            completed_at: completed_at.map(parse),
+            ..Default::default()
+        };
+        let task_list = [
+            get_task(
+                "completed",
+                Some("2024-10-17 09:00:00"),
+                Some("2024-10-19 10:00:00"),
+            ),
+            get_task("in-progress", Some("2024-10-18 08:00:00"), None),
+            get_task(
+                "completed",
+                Some("2024-10-19 08:00:00"),
+                Some("2024-10-19 09:00:00"),
+            ),
+            get_task(
+                "completed",
+                Some("2024-07-01 08:00:00"),
+                Some("2024-07-02 08:00:00"),
+            ),
+        ];
+        for task in task_list.iter() {
+            client.import_task(task).expect("Could not import task");
+        }
+        client
+            .get_connection()
+            .expect("Could not get connection")
+            .execute_batch("INSERT INTO todos (title, status) VALUES('\"old\"', 'in-progress')")
+            .expect("Could not insert old task");
+
+        assert_eq!(
+            client
+                .get_task_ids(SortField::Status)
+                .expect("Could not get task ids"),
+            [2, 5, 1, 3, 4]
+        );
+        assert_eq!(
+            client
+                .get_task_ids(SortField::Created)
+                .expect("Could not get task ids"),
+            [1, 2, 3, 4, 5]
+        );
+        assert!(client.get_task_ids(SortField::Title).is_err());
+
+        let since = NaiveDate::from_ymd_opt(2024, 7, 29).expect("Could not build date");
+        let task_totals = client
+            .get_task_totals(since)
+            .expect("Could not get task totals");
+        assert_eq!(task_totals.open_count, 2);
+        assert_eq!(task_totals.done_count, 3);
+        assert_eq!(task_totals.untracked_count, 1);
+        // 49, 1 and 24 hours
+        assert_eq!(
+            task_totals.average_completion,
+            Some(Duration::hours(74) / 3)
+        );
+        let today = NaiveDate::from_ymd_opt(2024, 10, 19).expect("Could not build date");
+        assert_eq!(task_totals.completions, HashMap::from([(today, 2)]));
+    }
+
+    #[test]
+    fn test_task_events() {
+        let mut client = Client::default();
+        client
+            .open_connection(PathBuf::new(), ":memory:")
+            .expect("Could not open connection");
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+
+        client
+            .create_task(String::from("milk"))
+            .expect("Could not create task");
+        client
+            .create_task(String::from("bread"))
+            .expect("Could not create task");
+        client
+            .update_task(1, "in-progress")
+            .expect("Could not update task");
+        client
+            .update_task_title(1, String::from("oat milk"))
+            .expect("Could not update task title");
+        let saved_tasks = client.get_tasks().expect("Could not get tasks");
+        client.remove_task(1).expect("Could not remove task");
+        client
+            .restore_tasks(&saved_tasks[..1])
+            .expect("Could not restore tasks");
+
+        let events = client
+            .get_task_events(Some(1), 10)
+            .expect("Could not get task events");
+        let kinds: Vec<TaskEventKind> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TaskEventKind::Restored,
+                TaskEventKind::Deleted,
+                TaskEventKind::TitleChanged,
+                TaskEventKind::StatusChanged,
+                TaskEventKind::Created,
+            ]
+        );
+        assert_eq!(events[1].title, "\"oat milk\"");
+        assert_eq!(events[3].status, "completed");
+        assert_eq!(events[4].title, "\"milk\"");
+        assert!(events.iter().all(|event| event.created_at.is_some()));
+
+        let events = client
+            .get_task_events(None, 2)
+            .expect("Could not get task events");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, TaskEventKind::Restored);
+
+        let task = &client.get_tasks().expect("Could not get tasks")[0];
+        assert!(task.updated_at >= task.created_at);
+        assert_eq!(task.title, saved_tasks[0].title);
+        assert_eq!(task.version, saved_tasks[0].version + 1);
+    }
+
+    #[test]
+    fn test_task_details() {
+        let mut client = Client::default();
+        client
+            .open_connection(PathBuf::new(), ":memory:")
+            .expect("Could not open connection");
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+        for title in ["milk", "bread", "eggs"] {
+            client
+                .create_task(String::from(title))
+                .expect("Could not create task");
+        }
+
+        assert_eq!(
+            client.update_tasks_list(&[1, 2], Some("shop")).ok(),
+            Some(2)
+        );
+        assert_eq!(client.add_tasks_tag(&[1, 2], "urgent").ok(), Some(2));
+        assert_eq!(client.add_tasks_tag(&[2, 3], "urgent").ok(), Some(2));
+        assert_eq!(client.add_tasks_tag(&[2], "home").ok(), Some(1));
+        assert_eq!(client.update_tasks_priority(&[3], Some(2)).ok(), Some(1));
+        assert!(client.add_tasks_tag(&[1], "a,b").is_err());
+
+        let tasks = client.get_tasks().expect("Could not get tasks");
+        assert_eq!(tasks[0].list.as_deref(), Some("shop"));
+        assert_eq!(tasks[2].list, None);
+        assert_eq!(tasks[1].tags, vec!["urgent", "home"]);
+        assert_eq!(tasks[2].tags, vec!["urgent"]);
+        assert_eq!(tasks[2].priority, Some(2));
+        assert_eq!(tasks[1].version, 5);
+
+        client
+            .update_tasks_list(&[1], None)
+            .expect("Could not update list");
+        let events = client
+            .get_task_events(Some(1), 10)
+            .expect("Could not get task events");
+        assert_eq!(events[0].kind, TaskEventKind::DetailsChanged);
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            client.get_tasks().expect("Could not get tasks")[0].list,
+            None
+        );
+
+        assert_eq!(client.get_task_ids_in_list("shop").ok(), Some(vec![2]));
+        assert_eq!(
+            client.get_task_ids_with_tag("urgent").ok(),
+            Some(vec![1, 2, 3])
+        );
+        assert_eq!(client.get_task_ids_with_tag("urg").ok(), Some(vec![]));
+    }
+
+    #[test]
+    fn test_time_entries() {
+        let mut client = Client::default();
+        client
+            .open_connection(PathBuf::new(), ":memory:")
+            .expect("Could not open connection");
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+        client
+            .create_task(String::from("milk"))
+            .expect("Could not create task");
+        client
+            .create_task(String::from("bread"))
+            .expect("Could not create task");
+
+        client.start_timer(1).expect("Could not start timer");
+        client.start_timer(2).expect("Could not start timer");
+        let time_entries = client
+            .get_time_entries()
+            .expect("Could not get time entries");
+        assert_eq!(time_entries.len(), 2);
+        assert!(!time_entries[0].is_running());
+        assert!(time_entries[1].is_running());
+        assert_eq!(time_entries[1].task_id, 2);
+
+        assert_eq!(client.stop_timer().expect("Could not stop timer"), 1);
+        assert_eq!(client.stop_timer().expect("Could not stop timer"), 0);
+
+        client.start_timer(2).expect("Could not start timer");
+        client.remove_task(2).expect("Could not remove task");
+        let time_entries = client
+            .get_time_entries()
+            .expect("Could not get time entries");
+        assert!(time_entries
+            .iter()
+            .all(|time_entry| !time_entry.is_running()));
+
+        client
+            .record_pomodoro(1, 25)
+            .expect("Could not record pomodoro");
+        client
+            .record_pomodoro(1, 25)
+            .expect("Could not record pomodoro");
+        let pomodoro_counts = client
+            .get_pomodoro_counts()
+            .expect("Could not get pomodoros");
+        assert_eq!(pomodoro_counts, HashMap::from([(1, 2)]));
+    }
+
     #[test]
     fn test_open_connection_error() {
         let mut client = Client::default();
@@ -0,0 +1,320 @@
+use crate::constants::{Action, ExportFormat, ReportFormat, SortField};
+use crate::error::{TuduError, TuduResult};
+use crate::filesystem::{complete_directory, expand_path};
+
+const COMMANDS: [&str; 18] = [
+    "add", "complete", "delete", "done", "export", "filter", "list", "move", "priority", "q",
+    "quit", "reopen", "report", "sort", "tag", "theme", "timer", "unlist",
+];
+const EXPORT_FORMATS: [ExportFormat; 4] = [
+    ExportFormat::Csv,
+    ExportFormat::Ics,
+    ExportFormat::Markdown,
+    ExportFormat::Bundle,
+];
+const REPORT_FORMATS: [ReportFormat; 2] = [ReportFormat::Csv, ReportFormat::Json];
+const SORT_FIELDS: [SortField; 4] = [
+    SortField::Created,
+    SortField::Title,
+    SortField::Status,
+    SortField::Due,
+];
+
+// Turns a line typed after `:` into the action it stands for
+pub fn parse_command(line: &str) -> TuduResult<Action> {
+    let line = line.trim();
+    let (name, argument) = match line.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (line, ""),
+    };
+
+    match name {
+        "" => Ok(Action::Empty),
+        "q" | "quit" => Ok(Action::Exit),
+        "add" if argument.is_empty() => Err(usage("add <title>")),
+        "add" => Ok(Action::CreateTask(String::from(argument))),
+        "done" => match argument.parse::<usize>() {
+            Ok(number) if number > 0 => Ok(Action::CompleteTask(number - 1)),
+            _ => Err(usage("done <task number>")),
+        },
+        "sort" if argument.is_empty() => Err(usage("sort <created|title|status|due>")),
+        "sort" => SORT_FIELDS
+            .iter()
+            .find(|field| field.get_name() == argument)
+            .map(|field| Action::SortTasks(*field))
+            .ok_or_else(|| {
+                TuduError::validation(&format!(
+                    "Cannot sort by {}, use created, title, status or due",
+                    argument
+                ))
+            }),
+        "complete" => Ok(Action::SetTaskStatus(String::from("completed"))),
+        "reopen" => Ok(Action::SetTaskStatus(String::from("in-progress"))),
+        "delete" => Ok(Action::RemoveTask),
+        "move" if argument.is_empty() => Err(usage("move <list>")),
+        "move" => Ok(Action::MoveTasks(Some(String::from(argument)))),
+        "unlist" => Ok(Action::MoveTasks(None)),
+        "filter" if argument.is_empty() => Err(usage("filter <tag:name|list:name|text>")),
+        "filter" => Ok(Action::FilterTasks(String::from(argument))),
+        "list" if argument.is_empty() => Err(usage("list <name>")),
+        "list" => Ok(Action::FilterTasks(format!("list:{}", argument))),
+        "tag" if argument.is_empty() => Err(usage("tag <tag>")),
+        "tag" => Ok(Action::TagTasks(String::from(argument))),
+        "priority" => parse_priority(argument),
+        "export" => parse_export(argument),
+        "report" => parse_report(argument),
+        "timer" => Ok(Action::ToggleTimer),
+        "theme" if argument.is_empty() => Ok(Action::SwitchTheme),
+        "theme" => Ok(Action::SetTheme(String::from(argument))),
+        _ => match name.parse::<usize>() {
+            Ok(line) if argument.is_empty() => Ok(Action::MenuGoTo(line.saturating_sub(1))),
+            _ => Err(TuduError::validation(&format!("Not a command: {}", name))),
+        },
+    }
+}
+
+// 1 is the highest priority, none clears it
+fn parse_priority(argument: &str) -> TuduResult<Action> {
+    match argument {
+        "none" => Ok(Action::SetTaskPriority(None)),
+        _ => match argument.parse::<u8>() {
+            Ok(priority) if (1..=9).contains(&priority) => {
+                Ok(Action::SetTaskPriority(Some(priority)))
+            }
+            _ => Err(usage("priority <1-9|none>")),
+        },
+    }
+}
+
+fn parse_export(argument: &str) -> TuduResult<Action> {
+    let export_usage = || usage("export <csv|ics|md|tudubak> <path>");
+    let (extension, path) = argument
+        .split_once(char::is_whitespace)
+        .ok_or_else(export_usage)?;
+    let export_format = EXPORT_FORMATS
+        .iter()
+        .find(|export_format| export_format.extension() == extension)
+        .ok_or_else(export_usage)?;
+
+    Ok(Action::Export(*export_format, expand_path(path.trim())))
+}
+
+fn parse_report(argument: &str) -> TuduResult<Action> {
+    let report_usage = || usage("report <csv|json> <path>");
+    let (extension, path) = argument
+        .split_once(char::is_whitespace)
+        .ok_or_else(report_usage)?;
+    let report_format = REPORT_FORMATS
+        .iter()
+        .find(|report_format| report_format.extension() == extension)
+        .ok_or_else(report_usage)?;
+
+    Ok(Action::ExportTimeReport(
+        *report_format,
+        expand_path(path.trim()),
+    ))
+}
+
+fn usage(command: &str) -> TuduError {
+    TuduError::validation(&format!("Usage: :{}", command))
+}
+
+// Completes command names, their fixed arguments, theme names and export or report paths
+pub fn complete_command(line: &str, theme_names: &[&str]) -> String {
+    match line.split_once(' ') {
+        None => complete_word(line, &COMMANDS),
+        Some(("sort", field)) => {
+            let names: Vec<&str> = SORT_FIELDS.iter().map(SortField::get_name).collect();
+            format!("sort {}", complete_word(field, &names))
+        }
+        Some(("theme", name)) => format!("theme {}", complete_word(name, theme_names)),
+        Some(("export", rest)) => match rest.split_once(' ') {
+            None => {
+                let extensions: Vec<&str> =
+                    EXPORT_FORMATS.iter().map(ExportFormat::extension).collect();
+                format!("export {}", complete_word(rest, &extensions))
+            }
+            Some((extension, path)) => {
+                format!("export {} {}", extension, complete_directory(path))
+            }
+        },
+        Some(("report", rest)) => match rest.split_once(' ') {
+            None => {
+                let extensions: Vec<&str> =
+                    REPORT_FORMATS.iter().map(ReportFormat::extension).collect();
+                format!("report {}", complete_word(rest, &extensions))
+            }
+            Some((extension, path)) => {
+                format!("report {} {}", extension, complete_directory(path))
+            }
+        },
+        _ => String::from(line),
+    }
+}
+
+fn complete_word(word: &str, candidates: &[&str]) -> String {
+    let matches: Vec<&str> = candidates
+        .iter()
+        .filter(|candidate| candidate.starts_with(word))
+        .copied()
+        .collect();
+
+    match matches.as_slice() {
+        [] => String::from(word),
+        [single] => format!("{} ", single),
+        [first, rest @ ..] => {
+            let common_length = rest.iter().fold(first.len(), |length, candidate| {
+                first
+                    .chars()
+                    .zip(candidate.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum::<usize>()
+                    .min(length)
+            });
+            String::from(&first[..common_length])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("").expect("Empty command"), Action::Empty);
+        assert_eq!(parse_command("quit").expect("Quit command"), Action::Exit);
+        assert_eq!(
+            parse_command("add Buy  milk ").expect("Add command"),
+            Action::CreateTask(String::from("Buy  milk"))
+        );
+        assert_eq!(
+            parse_command("done 3").expect("Done command"),
+            Action::CompleteTask(2)
+        );
+        assert_eq!(
+            parse_command("sort title").expect("Sort command"),
+            Action::SortTasks(SortField::Title)
+        );
+        assert_eq!(
+            parse_command("export md ./test/markdown/out.md").expect("Export command"),
+            Action::Export(
+                ExportFormat::Markdown,
+                PathBuf::from("./test/markdown/out.md")
+            )
+        );
+        assert_eq!(
+            parse_command("report json ./test/timer/report.json").expect("Report command"),
+            Action::ExportTimeReport(
+                ReportFormat::Json,
+                PathBuf::from("./test/timer/report.json")
+            )
+        );
+        assert_eq!(
+            parse_command("timer").expect("Timer command"),
+            Action::ToggleTimer
+        );
+        assert_eq!(
+            parse_command("12").expect("Line number"),
+            Action::MenuGoTo(11)
+        );
+        assert_eq!(
+            parse_command("reopen").expect("Reopen command"),
+            Action::SetTaskStatus(String::from("in-progress"))
+        );
+        assert_eq!(
+            parse_command("delete").expect("Delete command"),
+            Action::RemoveTask
+        );
+        assert_eq!(
+            parse_command("sort due").expect("Sort command"),
+            Action::SortTasks(SortField::Due)
+        );
+        assert_eq!(
+            parse_command("filter tag:urgent").expect("Filter command"),
+            Action::FilterTasks(String::from("tag:urgent"))
+        );
+        assert_eq!(
+            parse_command("list work").expect("List command"),
+            Action::FilterTasks(String::from("list:work"))
+        );
+        assert_eq!(
+            parse_command("move work").expect("Move command"),
+            Action::MoveTasks(Some(String::from("work")))
+        );
+        assert_eq!(
+            parse_command("unlist").expect("Unlist command"),
+            Action::MoveTasks(None)
+        );
+        assert_eq!(
+            parse_command("tag urgent").expect("Tag command"),
+            Action::TagTasks(String::from("urgent"))
+        );
+        assert_eq!(
+            parse_command("priority 2").expect("Priority command"),
+            Action::SetTaskPriority(Some(2))
+        );
+        assert_eq!(
+            parse_command("priority none").expect("Priority command"),
+            Action::SetTaskPriority(None)
+        );
+        assert_eq!(
+            parse_command("theme light").expect("Theme command"),
+            Action::SetTheme(String::from("light"))
+        );
+
+        let message = |line: &str| {
+            parse_command(line)
+                .expect_err("Command should fail")
+                .user_message()
+        };
+        assert_eq!(message("add"), "Usage: :add <title>");
+        assert_eq!(message("done 0"), "Usage: :done <task number>");
+        assert_eq!(
+            message("sort size"),
+            "Cannot sort by size, use created, title, status or due"
+        );
+        assert_eq!(
+            message("filter"),
+            "Usage: :filter <tag:name|list:name|text>"
+        );
+        assert_eq!(
+            message("export pdf out.pdf"),
+            "Usage: :export <csv|ics|md|tudubak> <path>"
+        );
+        assert_eq!(
+            message("report xml out.xml"),
+            "Usage: :report <csv|json> <path>"
+        );
+        assert_eq!(message("move"), "Usage: :move <list>");
+        assert_eq!(message("priority 0"), "Usage: :priority <1-9|none>");
+        assert_eq!(message("tag"), "Usage: :tag <tag>");
+        assert_eq!(message("nope"), "Not a command: nope");
+    }
+
+    #[test]
+    fn test_complete_command() {
+        let complete = |line| complete_command(line, &["dark", "light", "paper"]);
+        assert_eq!(complete("ex"), "export ");
+        assert_eq!(complete("q"), "q");
+        assert_eq!(complete("d"), "d");
+        assert_eq!(complete("de"), "delete ");
+        assert_eq!(complete("z"), "z");
+        assert_eq!(complete("sort st"), "sort status ");
+        assert_eq!(complete("sort d"), "sort due ");
+        assert_eq!(complete("f"), "filter ");
+        assert_eq!(complete("l"), "list ");
+        assert_eq!(complete("t"), "t");
+        assert_eq!(complete("m"), "move ");
+        assert_eq!(complete("p"), "priority ");
+        assert_eq!(complete("export m"), "export md ");
+        assert_eq!(complete("export md ./test/bun"), "export md ./test/bundle/");
+        assert_eq!(complete("add Buy"), "add Buy");
+        assert_eq!(complete("th"), "theme ");
+        assert_eq!(complete("ti"), "timer ");
+        assert_eq!(complete("report j"), "report json ");
+        assert_eq!(complete("theme p"), "theme paper ");
+    }
+}
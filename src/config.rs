@@ -0,0 +1,406 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ratatui::style::Color;
+use serde::Deserialize;
+
+use crate::constants::{
+    BACKUPS_PATH, CONFIG_NAME, DB_NAME, MAX_BACKUPS, MAX_MASTER_KEY_LENGTH, MAX_TASK_TITLE_LENGTH,
+    MUTATIONS_PER_BACKUP, POMODOROS_PER_LONG_BREAK, POMODORO_LONG_BREAK_MINUTES,
+    POMODORO_SHORT_BREAK_MINUTES, POMODORO_WORK_MINUTES,
+};
+use crate::error::{TuduError, TuduResult};
+use crate::filesystem::{expand_path, get_app_config_path};
+use crate::keymap::Keymap;
+use crate::theme::{get_built_in_theme, get_default_theme_name, Theme, THEME_NAMES};
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keymap_preset: KeymapPreset,
+    pub theme: Option<String>,
+    pub limits: LimitsConfig,
+    pub paths: PathsConfig,
+    pub backups: BackupsConfig,
+    pub colors: ColorsConfig,
+    pub themes: BTreeMap<String, Theme>,
+    pub confirmations: ConfirmationsConfig,
+    pub pomodoro: PomodoroConfig,
+    pub keybindings: KeyBindingsConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub max_task_title_length: usize,
+    pub max_master_key_length: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_task_title_length: MAX_TASK_TITLE_LENGTH,
+            max_master_key_length: MAX_MASTER_KEY_LENGTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    pub data_dir: Option<String>,
+    pub database: String,
+    pub backups: String,
+    pub export_dir: Option<String>,
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        PathsConfig {
+            data_dir: None,
+            database: String::from(DB_NAME),
+            backups: String::from(BACKUPS_PATH),
+            export_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupsConfig {
+    pub max_backups: usize,
+    pub mutations_per_backup: u32,
+}
+
+impl Default for BackupsConfig {
+    fn default() -> Self {
+        BackupsConfig {
+            max_backups: MAX_BACKUPS,
+            mutations_per_backup: MUTATIONS_PER_BACKUP,
+        }
+    }
+}
+
+// Older configs set these directly, they now win over the chosen theme
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorsConfig {
+    pub text: Option<Color>,
+    pub highlight_fg: Option<Color>,
+    pub highlight_bg: Option<Color>,
+}
+
+// Setting one to false skips the question and runs the action right away
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmationsConfig {
+    pub exit: bool,
+    pub remove_task: bool,
+    pub restore_backup: bool,
+    pub overwrite_file: bool,
+}
+
+impl Default for ConfirmationsConfig {
+    fn default() -> Self {
+        ConfirmationsConfig {
+            exit: true,
+            remove_task: true,
+            restore_backup: true,
+            overwrite_file: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroConfig {
+    pub work_minutes: u64,
+    pub short_break_minutes: u64,
+    pub long_break_minutes: u64,
+    pub long_break_every: u32,
+    pub bell: bool,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        PomodoroConfig {
+            work_minutes: POMODORO_WORK_MINUTES,
+            short_break_minutes: POMODORO_SHORT_BREAK_MINUTES,
+            long_break_minutes: POMODORO_LONG_BREAK_MINUTES,
+            long_break_every: POMODOROS_PER_LONG_BREAK,
+            bell: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    Vim,
+}
+
+// Per screen overrides, for example `[keybindings.main] add = ["a", "ctrl+n"]`
+pub type KeyBindingsConfig = BTreeMap<String, BTreeMap<String, KeyList>>;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    pub fn get_keys(&self) -> Vec<&str> {
+        match self {
+            KeyList::One(key) => vec![key.as_str()],
+            KeyList::Many(keys) => keys.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+impl Config {
+    pub fn get_data_path(&self) -> TuduResult<PathBuf> {
+        match &self.paths.data_dir {
+            Some(data_dir) => Ok(expand_path(data_dir)),
+            None => get_app_config_path(),
+        }
+    }
+
+    pub fn get_backups_path(&self) -> TuduResult<PathBuf> {
+        Ok(self.get_data_path()?.join(expand_path(&self.paths.backups)))
+    }
+
+    pub fn get_export_path(&self) -> TuduResult<PathBuf> {
+        match &self.paths.export_dir {
+            Some(export_dir) => Ok(expand_path(export_dir)),
+            None => self.get_data_path(),
+        }
+    }
+
+    pub fn get_theme_name(&self) -> String {
+        match &self.theme {
+            Some(theme) => theme.clone(),
+            None => {
+                let no_color = std::env::var("NO_COLOR").ok();
+                String::from(get_default_theme_name(no_color.as_deref()))
+            }
+        }
+    }
+
+    // Custom themes are listed after the built in ones and replace them on a name clash
+    pub fn get_theme_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = THEME_NAMES.iter().map(|name| String::from(*name)).collect();
+        names.extend(
+            self.themes
+                .keys()
+                .filter(|name| !THEME_NAMES.contains(&name.as_str()))
+                .cloned(),
+        );
+        names
+    }
+
+    pub fn get_theme(&self, name: &str) -> Option<Theme> {
+        let mut theme = match self.themes.get(name) {
+            Some(theme) => theme.clone(),
+            None => get_built_in_theme(name)?,
+        };
+        if let Some(text) = self.colors.text {
+            theme.text = text;
+        }
+        if let Some(highlight_fg) = self.colors.highlight_fg {
+            theme.highlight_fg = highlight_fg;
+        }
+        if let Some(highlight_bg) = self.colors.highlight_bg {
+            theme.highlight_bg = highlight_bg;
+        }
+        Some(theme)
+    }
+
+    pub fn validate(&self) -> TuduResult<()> {
+        let mut problems: Vec<String> = Vec::new();
+
+        if self.limits.max_task_title_length == 0 {
+            problems.push(String::from("limits.max_task_title_length must be above 0"));
+        }
+        if self.limits.max_master_key_length == 0 {
+            problems.push(String::from("limits.max_master_key_length must be above 0"));
+        }
+        if self.backups.max_backups == 0 {
+            problems.push(String::from("backups.max_backups must be above 0"));
+        }
+        if self.backups.mutations_per_backup == 0 {
+            problems.push(String::from("backups.mutations_per_backup must be above 0"));
+        }
+        if self.pomodoro.work_minutes == 0 {
+            problems.push(String::from("pomodoro.work_minutes must be above 0"));
+        }
+        if self.pomodoro.long_break_every == 0 {
+            problems.push(String::from("pomodoro.long_break_every must be above 0"));
+        }
+        if self.paths.database.trim().is_empty() {
+            problems.push(String::from("paths.database cannot be empty"));
+        }
+
+        if let Some(theme) = &self.theme {
+            if self.get_theme(theme).is_none() {
+                problems.push(format!(
+                    "theme {} does not exist, use {}",
+                    theme,
+                    self.get_theme_names().join(", ")
+                ));
+            }
+        }
+
+        if let Err(e) = Keymap::from_config(self.keymap_preset, &self.keybindings) {
+            problems.push(e.user_message());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(TuduError::validation(&problems.join(", ")))
+        }
+    }
+}
+
+pub fn get_default_config_path() -> TuduResult<PathBuf> {
+    Ok(get_app_config_path()?.join(CONFIG_NAME))
+}
+
+// A missing default config means defaults, a missing explicit one is an error
+pub fn load_config(path: Option<&Path>) -> TuduResult<Config> {
+    let config_path = match path {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let default_path = get_default_config_path()?;
+            if !default_path.is_file() {
+                return Ok(Config::default());
+            }
+            default_path
+        }
+    };
+
+    let content = fs::read_to_string(&config_path).map_err(|e| {
+        TuduError::io(
+            &format!("Could not read config file {}", config_path.display()),
+            e,
+        )
+    })?;
+    parse_config(&content).map_err(|e| {
+        TuduError::validation(&format!(
+            "Invalid config file {}: {}",
+            config_path.display(),
+            e.user_message()
+        ))
+    })
+}
+
+pub fn parse_config(content: &str) -> TuduResult<Config> {
+    let config: Config = toml::from_str(content).map_err(|e| TuduError::validation(e.message()))?;
+    config.validate()?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let config = parse_config("").expect("Could not parse empty config");
+        assert_eq!(config, Config::default());
+        assert_eq!(config.limits.max_task_title_length, MAX_TASK_TITLE_LENGTH);
+        assert_eq!(config.paths.database, DB_NAME);
+        assert!(config.keybindings.is_empty());
+    }
+
+    #[test]
+    fn test_load_file() {
+        let path = PathBuf::from("./test/config/config.toml");
+        let config = load_config(Some(&path)).expect("Could not load config");
+
+        assert_eq!(config.keymap_preset, KeymapPreset::Vim);
+        assert_eq!(config.limits.max_task_title_length, 80);
+        assert_eq!(config.limits.max_master_key_length, MAX_MASTER_KEY_LENGTH);
+        assert_eq!(config.backups.max_backups, 10);
+        assert_eq!(config.pomodoro.work_minutes, 50);
+        assert_eq!(
+            config.pomodoro.short_break_minutes,
+            POMODORO_SHORT_BREAK_MINUTES
+        );
+        assert!(!config.pomodoro.bell);
+        assert_eq!(config.colors.highlight_bg, Some(Color::Rgb(0, 95, 135)));
+        assert_eq!(
+            config.keybindings["main"]["add"],
+            KeyList::Many(vec![String::from("o"), String::from("ctrl+n")])
+        );
+        assert_eq!(
+            config
+                .get_backups_path()
+                .expect("Could not get backups path"),
+            PathBuf::from("./test/config/data/snapshots")
+        );
+        assert_eq!(
+            config.get_export_path().expect("Could not get export path"),
+            PathBuf::from("./test/config/data")
+        );
+
+        let missing = PathBuf::from("./test/config/missing.toml");
+        assert!(load_config(Some(&missing)).is_err());
+    }
+
+    #[test]
+    fn test_themes() {
+        let path = PathBuf::from("./test/config/config.toml");
+        let config = load_config(Some(&path)).expect("Could not load config");
+
+        assert_eq!(config.get_theme_name(), "paper");
+        assert_eq!(
+            config.get_theme_names(),
+            vec!["dark", "light", "high-contrast", "monochrome", "paper"]
+        );
+        let paper = config
+            .get_theme("paper")
+            .expect("Could not get custom theme");
+        assert_eq!(paper.text, Color::Rgb(51, 51, 51));
+        assert_eq!(paper.error, Theme::default().error);
+        // The old colors section still applies on top of any theme
+        assert_eq!(paper.highlight_bg, Color::Rgb(0, 95, 135));
+        let light = config
+            .get_theme("light")
+            .expect("Could not get light theme");
+        assert_eq!(light.highlight_bg, Color::Rgb(0, 95, 135));
+        assert_eq!(light.text, Theme::light().text);
+        assert!(config.get_theme("solarized").is_none());
+
+        assert_eq!(get_default_theme_name(None), "dark");
+        assert_eq!(get_default_theme_name(Some("")), "dark");
+        assert_eq!(get_default_theme_name(Some("1")), "monochrome");
+    }
+
+    #[test]
+    fn test_validation() {
+        let path = PathBuf::from("./test/config/invalid.toml");
+        let error = load_config(Some(&path)).expect_err("Config should be invalid");
+        let message = error.user_message();
+        assert_eq!(error.exit_code(), 2);
+        assert!(message.contains("limits.max_task_title_length must be above 0"));
+        assert!(message.contains("keybindings.main: add and remove both use x"));
+        assert!(message.contains("pomodoro.long_break_every must be above 0"));
+        assert!(message.contains(
+            "theme solarized does not exist, use dark, light, high-contrast, monochrome, paper"
+        ));
+
+        assert!(parse_config("[limits]\nunknown = 1").is_err());
+        assert!(parse_config("[colors]\ntext = \"not-a-color\"").is_err());
+        assert!(parse_config("[keybindings.main]\nadd = \"ab\"").is_err());
+        assert!(parse_config("keymap_preset = \"emacs\"").is_err());
+        assert!(parse_config("[themes.solar]\nshadow = \"red\"").is_err());
+
+        let config = parse_config("keymap_preset = \"vim\"").expect("Could not parse preset");
+        assert_eq!(config.keymap_preset, KeymapPreset::Vim);
+    }
+}
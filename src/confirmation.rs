@@ -0,0 +1,19 @@
+use crate::constants::{Action, Screen};
+
+// A question shown over the screen it came from, the action only runs when accepted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Confirmation {
+    pub message: String,
+    pub action: Action,
+    pub previous_screen: Screen,
+}
+
+impl Confirmation {
+    pub fn new(message: String, action: Action, previous_screen: Screen) -> Self {
+        Confirmation {
+            message,
+            action,
+            previous_screen,
+        }
+    }
+}
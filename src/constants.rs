@@ -1,18 +1,172 @@
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
 pub const APP_PATH: &str = "tudu";
 pub const DB_NAME: &str = "tudu.db";
-pub const CSV_NAME: &str = "tudu.csv";
+pub const EXPORT_NAME: &str = "tudu";
+pub const BACKUPS_PATH: &str = "backups";
+pub const ICS_NAME: &str = "tudu.ics";
+pub const CONFIG_NAME: &str = "config.toml";
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum Screen {
     #[default]
     Main,
     Add,
     Greetings,
+    Export,
+    Import,
+    Restore,
+    Passphrase,
+    Backups,
+    Messages,
+    Search,
+    Command,
+    Help,
+    Confirm,
+    Stats,
+    History,
+    Focus,
 }
 
-#[derive(Debug, PartialEq)]
+impl Screen {
+    // Used as the table name for the screen in the config file
+    pub fn get_name(&self) -> &str {
+        match self {
+            Screen::Main => "main",
+            Screen::Add => "add",
+            Screen::Greetings => "greetings",
+            Screen::Export => "export",
+            Screen::Import => "import",
+            Screen::Restore => "restore",
+            Screen::Passphrase => "passphrase",
+            Screen::Backups => "backups",
+            Screen::Messages => "messages",
+            Screen::Search => "search",
+            Screen::Command => "command",
+            Screen::Help => "help",
+            Screen::Confirm => "confirm",
+            Screen::Stats => "stats",
+            Screen::History => "history",
+            Screen::Focus => "focus",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Ics,
+    Markdown,
+    Bundle,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ics => "ics",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Bundle => "tudubak",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ExportFormat::Csv => ExportFormat::Ics,
+            ExportFormat::Ics => ExportFormat::Markdown,
+            ExportFormat::Markdown => ExportFormat::Bundle,
+            ExportFormat::Bundle => ExportFormat::Csv,
+        }
+    }
+}
+
+// Formats of the time report, which is exported apart from the tasks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &str {
+        match self {
+            ReportFormat::Csv => "csv",
+            ReportFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortField {
+    #[default]
+    Created,
+    Title,
+    Status,
+    Due,
+}
+
+impl SortField {
+    pub fn get_name(&self) -> &str {
+        match self {
+            SortField::Created => "created",
+            SortField::Title => "title",
+            SortField::Status => "status",
+            SortField::Due => "due",
+        }
+    }
+
+    // Titles are encrypted, the database cannot order by them
+    pub fn get_order_clause(&self) -> Option<&str> {
+        match self {
+            SortField::Created => Some("id"),
+            SortField::Title => None,
+            SortField::Status => Some("status = 'completed', id"),
+            // Tasks without a due date go last
+            SortField::Due => Some("due IS NULL, due, id"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PassphrasePurpose {
+    #[default]
+    Export,
+    Restore,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Severity {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn label(&self) -> &str {
+        match self {
+            Severity::Info => "info",
+            Severity::Success => "success",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    // Errors stay longer so there is time to read the cause
+    pub fn timeout(&self) -> Duration {
+        match self {
+            Severity::Info | Severity::Success => Duration::from_secs(NOTIFICATION_TIMEOUT_SECS),
+            Severity::Warning | Severity::Error => {
+                Duration::from_secs(ERROR_NOTIFICATION_TIMEOUT_SECS)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     Init,
     Empty,
@@ -31,12 +185,121 @@ pub enum Action {
     MenuUp,
     MenuDown,
     ToggleTaskStatus,
-    ResetError,
+    DismissNotification,
+    ExpireNotification,
     AddSecret,
+    SubmitMasterKey,
     CheckSecret,
-    ExportCSV(Option<PathBuf>),
+    OpenExportScreen,
+    CancelExport,
+    SwitchExportFormat,
+    CompletePath,
+    ConfirmExport,
+    Export(ExportFormat, PathBuf),
+    OpenImportScreen,
+    CancelImport,
+    ConfirmImport,
+    OpenRestoreScreen,
+    CancelRestore,
+    ConfirmRestore,
+    RestoreBundle(PathBuf),
+    InputPassphraseChar(char),
+    RemovePassphraseChar,
+    ConfirmPassphrase,
+    CancelPassphrase,
+    CreateBackup,
+    OpenBackupsScreen,
+    CloseBackupsScreen,
+    BackupMenuUp,
+    BackupMenuDown,
+    RestoreBackup,
+    ImportICS(PathBuf),
+    OpenMessagesScreen,
+    CloseMessagesScreen,
+    MessagesUp,
+    MessagesDown,
+    MenuFirst,
+    MenuLast,
+    MenuGoTo(usize),
+    Repeat(usize, Box<Action>),
+    OpenEditScreen,
+    OpenChangeScreen,
+    OpenSearchScreen,
+    CancelSearch,
+    ConfirmSearch,
+    SearchNext,
+    SearchPrevious,
+    OpenCommandLine,
+    // Opens the command line with the start of a command typed in
+    PromptCommand(String),
+    CancelCommand,
+    ExecuteCommand,
+    CompleteCommand,
+    CommandHistoryUp,
+    CommandHistoryDown,
+    CreateTask(String),
+    CompleteTask(usize),
+    SortTasks(SortField),
+    OpenHelp,
+    CloseHelp,
+    ScrollHelp(i32),
+    RequestConfirmation(String, Box<Action>),
+    AcceptConfirmation,
+    RejectConfirmation,
+    OverwriteExport,
+    MenuPageUp,
+    MenuPageDown,
+    ToggleMark,
+    ToggleVisualMode,
+    MarkMatching,
+    // Marks the tasks matching tag:<tag>, list:<list> or a text in their title
+    FilterTasks(String),
+    ClearSelection,
+    SetTaskStatus(String),
+    MoveTasks(Option<String>),
+    TagTasks(String),
+    SetTaskPriority(Option<u8>),
+    Undo,
+    SwitchTheme,
+    SetTheme(String),
+    Resize(u16, u16),
+    OpenStatsScreen,
+    CloseStatsScreen,
+    OpenHistoryScreen,
+    CloseHistoryScreen,
+    HistoryUp,
+    HistoryDown,
+    ToggleTimer,
+    ExportTimeReport(ReportFormat, PathBuf),
+    Tick(Instant),
+    RecordPomodoro(i32, u64),
+    OpenFocusScreen,
+    CloseFocusScreen,
+    ToggleFocusPause,
+    SkipFocusPhase,
+    LoadTaskPage(u64, i32),
 }
 
-pub const MAX_TASK_TITLE_LENGTH: i32 = 40;
-pub const MAX_MASTER_KEY_LENGTH: i32 = 10;
+pub const MAX_TASK_TITLE_LENGTH: usize = 40;
+pub const MAX_MASTER_KEY_LENGTH: usize = 10;
+pub const MAX_BACKUPS: usize = 5;
+pub const MUTATIONS_PER_BACKUP: u32 = 50;
+pub const NOTIFICATION_TIMEOUT_SECS: u64 = 4;
+pub const ERROR_NOTIFICATION_TIMEOUT_SECS: u64 = 8;
+pub const MAX_NOTIFICATION_HISTORY: usize = 100;
+pub const MAX_COMMAND_HISTORY: usize = 50;
+pub const MAX_UNDO_HISTORY: usize = 20;
+pub const MAX_TASK_EVENTS: usize = 100;
+pub const MAX_DETAIL_EVENTS: usize = 5;
+pub const POMODORO_WORK_MINUTES: u64 = 25;
+pub const POMODORO_SHORT_BREAK_MINUTES: u64 = 5;
+pub const POMODORO_LONG_BREAK_MINUTES: u64 = 15;
+pub const POMODOROS_PER_LONG_BREAK: u32 = 4;
+// Timers and notifications are checked this often even without input
+pub const TICK_RATE: Duration = Duration::from_millis(250);
+pub const DEFAULT_LOG_LENGTH: usize = 20;
+// Titles are decrypted in pages this size when searching or sorting by them
+pub const TASK_PAGE_SIZE: usize = 500;
+// Rows loaded above and below the viewport, scrolling within them needs no query
+pub const TASK_WINDOW_MARGIN: i32 = 100;
 pub const VERY_SECRET_TEXT: &str = "THIS_IS_NOT_GOOD_PRACTICE_I_NEED_TIME_FOR_THIS";
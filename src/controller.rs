@@ -1,59 +1,220 @@
-use crossterm::event::{self, KeyCode, KeyEventKind};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chrono::Local;
+use crossterm::event::{
+    self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use ratatui::layout::Rect;
 use ratatui::prelude::{Backend, Terminal};
 
+use crate::backup::{create_backup, list_backups, restore_backup};
+use crate::bundle::{read_bundle, write_bundle};
+use crate::cache::TitleCache;
 use crate::client::Client;
-use crate::constants::{self, Action, Screen, MAX_TASK_TITLE_LENGTH, VERY_SECRET_TEXT};
+use crate::command::{complete_command, parse_command};
+use crate::confirmation::Confirmation;
+use crate::constants::{
+    self, Action, ExportFormat, PassphrasePurpose, Screen, Severity, SortField, TASK_PAGE_SIZE,
+    TASK_WINDOW_MARGIN, VERY_SECRET_TEXT,
+};
 use crate::csv::write_tasks_into_csv_file;
 use crate::encdec::{decrypt, encrypt};
-use crate::filesystem::{self, get_app_config_path};
+use crate::error::{TuduError, TuduResult};
+use crate::event::{AppEvent, EventLoop};
+use crate::filesystem::{self, complete_directory, expand_path};
+use crate::ics::{read_tasks_from_ics_file, write_tasks_into_ics_file};
+use crate::keymap::{KeySequenceParser, ParsedKeys};
+use crate::markdown::write_tasks_into_markdown_file;
+use crate::pomodoro::{Pomodoro, PomodoroPhase};
+use crate::restore::{restore_tables, RestoreSummary};
 use crate::state::State;
+use crate::stats::{compute_stats, get_heatmap_start};
+use crate::task::Task;
+use crate::timer::{build_time_report, format_elapsed, write_time_report};
+use crate::undo::UndoEntry;
 use crate::view::View;
+use crate::worker::{DatabaseWorker, JobOutcome, Loading};
+
+const SORTING_TASKS_LABEL: &str = "Sorting tasks by title";
 
 pub struct Controller {
     pub state: State,
-    client: Client,
+    worker: DatabaseWorker,
+    event_loop: EventLoop,
+    title_cache: TitleCache,
+    // Goes up with every reload or sort, pages of an older chain are dropped
+    load_generation: u64,
+    backups_path: Option<PathBuf>,
+    mutation_count: u32,
+    is_database_damaged: bool,
+    key_parser: KeySequenceParser,
+    area: Rect,
+    last_drawn_second: i64,
+    // Work that came up while a job was running, handled once it is done
+    deferred_actions: Vec<Action>,
+}
+
+impl Default for Controller {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Controller {
     pub fn new() -> Self {
         Self {
             state: State::new(),
-            client: Client::default(),
+            worker: DatabaseWorker::new(),
+            event_loop: EventLoop::new(),
+            title_cache: TitleCache::new(),
+            load_generation: 0,
+            backups_path: None,
+            mutation_count: 0,
+            is_database_damaged: false,
+            key_parser: KeySequenceParser::default(),
+            area: Rect::default(),
+            last_drawn_second: 0,
+            deferred_actions: Vec::new(),
         }
     }
 
     pub fn handle_action(&mut self, action: Action) {
+        // Finished pomodoros and due backups must not be lost nor wait on the job in the ui
+        let is_deferred = match action {
+            Action::RecordPomodoro(..) => self.get_running_job().is_some(),
+            Action::CreateBackup => self.state.get_loading().is_some(),
+            _ => false,
+        };
+        if is_deferred {
+            if !self.deferred_actions.contains(&action) {
+                self.deferred_actions.push(action);
+            }
+            return;
+        }
+
         match action {
             Action::Init => self.state.set_is_running(true),
             Action::Exit => {
                 self.state.set_is_running(false);
-                self.exit().expect("Could not exit");
+                if let Err(e) = self.exit() {
+                    self.state.set_error(e.user_message());
+                }
             }
             Action::GetTasks => {
-                match self.client.get_tasks() {
-                    Ok(mut task_list) => {
-                        for task in task_list.iter_mut() {
-                            task.title =
-                                decrypt(task.title.as_str(), &self.state.get_master_key()).unwrap();
-                        }
-                        self.state.set_task_list(task_list);
-                        self.handle_action(Action::ResetError);
-                    }
+                self.load_generation += 1;
+                self.finish_loading_tasks();
+                self.state.set_task_list(Vec::new());
+                self.load_time_entries();
+                self.load_pomodoro_counts();
+                self.handle_action(Action::DismissNotification);
+                if self.is_database_damaged {
+                    self.state.set_notification(
+                        Severity::Warning,
+                        String::from("Database integrity check failed, run tudu doctor to repair"),
+                    );
+                }
+
+                // Until every title is decrypted the list stays in creation order
+                let sort_field = self.state.get_sort_field();
+                let order_field = match sort_field {
+                    SortField::Title => SortField::Created,
+                    sort_field => sort_field,
+                };
+                if let Err(e) = self.load_task_order(order_field) {
+                    self.state.set_error(e.user_message());
+                    return;
+                }
+                if sort_field == SortField::Title {
+                    self.handle_action(Action::LoadTaskPage(self.load_generation, 0));
+                }
+            }
+            Action::LoadTaskPage(generation, after_id) => {
+                if generation != self.load_generation {
+                    return;
+                }
+                let result =
+                    self.call(move |client| client.get_task_page(after_id, TASK_PAGE_SIZE));
+                let task_list = match result {
+                    Ok(task_list) => task_list,
                     Err(e) => {
-                        self.state.set_error(format!("{}", e));
+                        self.finish_loading_tasks();
+                        self.state.set_error(e.user_message());
+                        return;
                     }
                 };
+                let is_last_page = task_list.len() < TASK_PAGE_SIZE;
+                let last_id = task_list.last().map_or(after_id, |task| task.id);
+                let task_list = task_list
+                    .into_iter()
+                    .filter(|task| !self.title_cache.has_failed(task.id))
+                    .collect();
+                self.decrypt_tasks(task_list);
+
+                // The next page waits behind the input that arrived in the meantime
+                if !is_last_page {
+                    if self.state.get_loading().is_none() {
+                        let loading =
+                            Loading::new(String::from(SORTING_TASKS_LABEL), Instant::now());
+                        self.state.set_loading(Some(loading));
+                    }
+                    let next_page = Action::LoadTaskPage(generation, last_id);
+                    if let Err(e) = self
+                        .event_loop
+                        .get_sender()
+                        .send(AppEvent::Action(next_page))
+                    {
+                        self.finish_loading_tasks();
+                        self.state
+                            .set_error(TuduError::io("Could not load tasks", e).user_message());
+                    }
+                    return;
+                }
+
+                self.finish_loading_tasks();
+                self.sort_tasks(SortField::Title);
             }
             Action::MenuDown => {
                 let current_line = self.state.get_line();
                 if current_line < self.state.get_task_list_length() - 1 {
-                    self.state.set_line(current_line + 1);
+                    self.go_to_line(current_line + 1);
                 }
             }
             Action::MenuUp => {
                 let current_line = self.state.get_line();
                 if current_line > 0 {
-                    self.state.set_line(current_line - 1);
+                    self.go_to_line(current_line - 1);
+                }
+            }
+            Action::MenuFirst => self.go_to_line(0),
+            Action::MenuLast => {
+                let last_line = (self.state.get_task_list_length() - 1).max(0);
+                self.go_to_line(last_line);
+            }
+            Action::MenuPageUp => {
+                let line = self.state.get_line() - self.state.get_page_size();
+                self.go_to_line(line.max(0));
+            }
+            Action::MenuPageDown => {
+                let last_line = (self.state.get_task_list_length() - 1).max(0);
+                let line = self.state.get_line() + self.state.get_page_size();
+                self.go_to_line(line.min(last_line));
+            }
+            Action::MenuGoTo(line) => {
+                let last_line = (self.state.get_task_list_length() - 1).max(0);
+                self.go_to_line((line as i32).min(last_line));
+            }
+            Action::Repeat(count, action) => {
+                // Stop once the action leaves the screen, like a removal that failed
+                let screen = *self.state.get_screen();
+                for _ in 0..count {
+                    self.handle_action(*action.clone());
+                    if self.state.get_screen() != &screen {
+                        break;
+                    }
                 }
             }
             Action::OpenMainScreen => {
@@ -61,24 +222,51 @@ impl Controller {
                 self.handle_action(Action::CheckSecret);
             }
             Action::OpenAddScreen => {
+                if self.state.get_editing_task_id().is_some() {
+                    self.state.set_editing_task_id(None);
+                    self.state.set_input("");
+                }
                 self.state.set_screen(Screen::Add);
-                self.handle_action(Action::ResetError);
+                self.handle_action(Action::DismissNotification);
             }
             Action::OpenGreetingsScreen => {
                 self.state.set_screen(Screen::Greetings);
             }
+            Action::OpenEditScreen | Action::OpenChangeScreen => {
+                let task = match self.state.get_task(self.state.get_line()) {
+                    Some(task) => task.clone(),
+                    None => {
+                        self.state.set_error(String::from("Please select a task"));
+                        return;
+                    }
+                };
+
+                if action == Action::OpenEditScreen {
+                    self.state.set_input(&task.title);
+                } else {
+                    self.state.set_input("");
+                }
+                self.state.set_editing_task_id(Some(task.id));
+                self.state.set_screen(Screen::Add);
+                self.handle_action(Action::DismissNotification);
+            }
             Action::CancelAddTask => {
+                if self.state.get_editing_task_id().is_some() {
+                    self.state.set_editing_task_id(None);
+                    self.state.set_input("");
+                }
                 self.state.set_screen(Screen::Main);
-                self.handle_action(Action::ResetError);
+                self.handle_action(Action::DismissNotification);
             }
             Action::InputChar(ch) => {
                 let len = self.state.input.len();
                 self.state.input.insert(len, ch);
+                self.state.set_is_overwrite_pending(false);
             }
             Action::InputMaskedChar(ch) => {
                 let len = self.state.master_key.len();
 
-                if len as i32 <= constants::MAX_MASTER_KEY_LENGTH {
+                if len <= self.state.get_config().limits.max_master_key_length {
                     self.state.master_key.insert(len, ch);
                 }
             }
@@ -87,6 +275,7 @@ impl Controller {
                 if len > 0 {
                     self.state.input.drain(len - 1..len);
                 }
+                self.state.set_is_overwrite_pending(false);
             }
             Action::RemoveMaskedChar => {
                 let len = self.state.master_key.len();
@@ -98,40 +287,55 @@ impl Controller {
                 0 => self
                     .state
                     .set_error(String::from("Please enter task title")),
-                len if len as i32 > MAX_TASK_TITLE_LENGTH => self.state.set_error(String::from(
-                    format!("Task title cannot be longer than {}", MAX_TASK_TITLE_LENGTH),
-                )),
+                len if len > self.state.get_config().limits.max_task_title_length => {
+                    let max_length = self.state.get_config().limits.max_task_title_length;
+                    self.state
+                        .set_error(format!("Task title cannot be longer than {}", max_length))
+                }
                 _ => {
-                    let data = encrypt(&self.state.input, &self.state.get_master_key());
-                    match self.client.create_task(data) {
-                        Ok(_) => {
+                    let data = encrypt(&self.state.input, self.state.get_master_key());
+                    let editing_task_id = self.state.get_editing_task_id();
+                    let result = self.call(move |client| match editing_task_id {
+                        Some(id) => client.update_task_title(id, data).map(|_| id),
+                        None => client.create_task(data),
+                    });
+                    match result {
+                        Ok(id) => {
                             self.state.set_input("");
-                            self.handle_action(Action::OpenMainScreen);
+                            self.state.set_editing_task_id(None);
+                            self.record_mutation();
+                            self.refresh_tasks(&[id]);
+                            self.state.set_screen(Screen::Main);
+                            self.handle_action(Action::DismissNotification);
                         }
-                        Err(e) => self.state.set_error(format!("{}", e)),
+                        Err(e) => self.state.set_error(e.user_message()),
                     }
                 }
             },
+            Action::SubmitMasterKey => {
+                let action = self.get_master_key_action();
+                self.handle_action(action);
+            }
             Action::AddSecret => {
-                let data = encrypt(VERY_SECRET_TEXT, &self.state.get_master_key());
-                match self.client.create_user(data) {
+                let data = encrypt(VERY_SECRET_TEXT, self.state.get_master_key());
+                match self.call(move |client| client.create_user(data)) {
                     Ok(_) => self.handle_action(Action::OpenMainScreen),
-                    Err(e) => self.state.set_error(format!("{}", e)),
+                    Err(e) => self.state.set_error(e.user_message()),
                 }
             }
             Action::CheckSecret => {
-                let user_data = self.client.get_user();
+                let user_data = self.call(|client| client.get_user());
 
                 match user_data {
                     Ok(user_vec) => {
-                        let user_result = user_vec.get(0);
+                        let user_result = user_vec.first();
                         match user_result {
                             Some(user) => {
                                 let decrypted_text =
-                                    decrypt(user.secret.as_str(), &self.state.get_master_key());
+                                    decrypt(user.secret.as_str(), self.state.get_master_key());
                                 match decrypted_text {
                                     Ok(_) => {
-                                        self.handle_action(Action::ResetError);
+                                        self.handle_action(Action::DismissNotification);
                                         self.handle_action(Action::GetTasks);
                                     }
                                     Err(_) => {
@@ -147,330 +351,3262 @@ impl Controller {
                 }
             }
             Action::RemoveTask => {
-                let index = self.state.get_line();
-                self.state.get_task_list().get(index as usize).map(|task| {
-                    // TODO: propagate this error
-                    let _ = self.client.remove_task(task.id);
-                });
-                if index == self.state.get_task_list_length() - 1 {
-                    self.handle_action(Action::MenuUp)
+                let ids = self.get_target_ids();
+                if ids.is_empty() {
+                    return;
+                }
+                let label = get_count_label("Removed", ids.len());
+                match self.change_tasks(&ids, label, |client, ids| client.remove_tasks(ids)) {
+                    Ok(_) => {
+                        self.state.clear_selection();
+                        self.refresh_tasks(&ids);
+                        // Removing a task stops its timer
+                        self.load_time_entries();
+                        let last_line = (self.state.get_task_list_length() - 1).max(0);
+                        self.go_to_line(self.state.get_line().min(last_line));
+                    }
+                    Err(e) => self.state.set_error(e.user_message()),
                 }
-                self.handle_action(Action::GetTasks);
             }
             Action::ToggleTaskStatus => {
-                let index = self.state.get_line();
-                self.state.get_task_list().get(index as usize).map(|task| {
-                    // TODO: propagate this error
-                    let _ = self.client.update_task(task.id, &task.status);
+                // A selection with any open task is completed, otherwise reopened
+                let ids = self.get_target_ids();
+                let task_list = match self.call(move |client| client.get_tasks_by_ids(&ids)) {
+                    Ok(task_list) => task_list,
+                    Err(e) => {
+                        self.state.set_error(e.user_message());
+                        return;
+                    }
+                };
+                let has_open_task = task_list.iter().any(|task| task.status != "completed");
+                let status = if has_open_task {
+                    "completed"
+                } else {
+                    "in-progress"
+                };
+                self.handle_action(Action::SetTaskStatus(String::from(status)));
+            }
+            Action::SetTaskStatus(status) => {
+                let ids = self.get_target_ids();
+                if ids.is_empty() {
+                    return;
+                }
+                let verb = if status == "completed" {
+                    "Completed"
+                } else {
+                    "Reopened"
+                };
+                let label = get_count_label(verb, ids.len());
+                let result = self.change_tasks(&ids, label, move |client, ids| {
+                    client.update_tasks_status(ids, &status)
                 });
-                self.handle_action(Action::GetTasks);
+                self.finish_bulk_change(&ids, result);
             }
-            Action::ResetError => {
-                self.state.set_error(String::from(""));
+            Action::MoveTasks(list) => {
+                let ids = self.get_target_ids();
+                if ids.is_empty() {
+                    return;
+                }
+                let label = match &list {
+                    Some(list) => format!("{} to {}", get_count_label("Moved", ids.len()), list),
+                    None => format!("{} out of their list", get_count_label("Moved", ids.len())),
+                };
+                let result = self.change_tasks(&ids, label, move |client, ids| {
+                    client.update_tasks_list(ids, list.as_deref())
+                });
+                self.finish_bulk_change(&ids, result);
             }
-            Action::ExportCSV(app_config_path) => {
-                self.handle_action(Action::GetTasks);
-                let task_list = self.state.get_task_list();
-                let acp = match app_config_path {
-                  Some(acp_option) => acp_option.join(constants::CSV_NAME),
-                  None => {
-                    let temp_acp = get_app_config_path().expect("Could not get app config path");
-                    temp_acp.join(constants::CSV_NAME)
-                  }
-              };
-
-                let write_result = write_tasks_into_csv_file(task_list, &acp);
-
-                match write_result {
-                    Ok(_) => self
-                        .state
-                        .set_error(String::from("Successfully save csv file")),
-                    Err(_) => self
-                        .state
-                        .set_error(String::from("Could not save csv file")),
+            Action::TagTasks(tag) => {
+                let ids = self.get_target_ids();
+                if ids.is_empty() {
+                    return;
                 }
+                let label = format!("{} {}", get_count_label("Tagged", ids.len()), tag);
+                let result = self.change_tasks(&ids, label, move |client, ids| {
+                    client.add_tasks_tag(ids, &tag)
+                });
+                self.finish_bulk_change(&ids, result);
             }
-            Action::Empty => {}
-        }
-    }
-
-    pub fn handle_key_stroke(&mut self, key_code: KeyCode) -> Action {
-        return match self.state.get_screen() {
-            Screen::Main => match key_code {
-                KeyCode::Char('a') => Action::OpenAddScreen,
-                KeyCode::Char('x') => Action::RemoveTask,
-                KeyCode::Char('e') => Action::ExportCSV(None),
-                KeyCode::Up => Action::MenuUp,
-                KeyCode::Down => Action::MenuDown,
-                KeyCode::Esc => Action::Exit,
-                KeyCode::Enter => Action::ToggleTaskStatus,
-                _ => Action::Empty,
-            },
-            Screen::Add => match key_code {
-                KeyCode::Esc => Action::CancelAddTask,
-                KeyCode::Enter => Action::AddTask,
-                KeyCode::Char(to_insert) => Action::InputChar(to_insert),
-                KeyCode::Backspace => Action::RemoveChar,
-                _ => Action::Empty,
+            Action::SetTaskPriority(priority) => {
+                let ids = self.get_target_ids();
+                if ids.is_empty() {
+                    return;
+                }
+                let label = get_count_label("Set the priority of", ids.len());
+                let result = self.change_tasks(&ids, label, move |client, ids| {
+                    client.update_tasks_priority(ids, priority)
+                });
+                self.finish_bulk_change(&ids, result);
+            }
+            Action::ToggleMark => {
+                if let Some(id) = self.state.get_task_id(self.state.get_line()) {
+                    self.state.toggle_selected_id(id);
+                }
+            }
+            Action::ToggleVisualMode => match self.state.get_visual_anchor() {
+                // Leaving visual mode keeps the range as marked tasks
+                Some(_) => {
+                    let ids = self.get_target_ids();
+                    let mut selected_ids = self.state.get_selected_ids().clone();
+                    selected_ids.extend(ids);
+                    self.state.set_selected_ids(selected_ids);
+                    self.state.set_visual_anchor(None);
+                }
+                None if self.state.get_task_list_length() > 0 => {
+                    let line = self.state.get_line();
+                    self.state.set_visual_anchor(Some(line));
+                }
+                None => {}
             },
-            Screen::Greetings => match key_code {
-                KeyCode::Esc => Action::Exit,
-                KeyCode::Char(to_insert) => Action::InputMaskedChar(to_insert),
-                KeyCode::Backspace => Action::RemoveMaskedChar,
-                KeyCode::Enter => {
-                    if self.state.get_is_first_time() {
-                        Action::AddSecret
-                    } else {
-                        Action::OpenMainScreen
+            Action::MarkMatching => {
+                let query = self.state.get_search_query().to_lowercase();
+                if query.is_empty() {
+                    self.state
+                        .set_error(String::from("Search first to select the matching tasks"));
+                    return;
+                }
+                let task_order = self.state.get_task_order().clone();
+                if let Err(e) = self.decrypt_titles(&task_order) {
+                    self.state.set_error(e.user_message());
+                    return;
+                }
+                let ids: Vec<i32> = self
+                    .state
+                    .get_task_order()
+                    .iter()
+                    .filter(|id| {
+                        self.title_cache
+                            .get_title(**id)
+                            .is_some_and(|title| title.to_lowercase().contains(&query))
+                    })
+                    .copied()
+                    .collect();
+                let query = self.state.get_search_query().to_string();
+                self.mark_tasks(&ids, &query);
+            }
+            // Tags and lists are read by the database, any other filter is a search
+            Action::FilterTasks(filter) => {
+                let result = if let Some(tag) = filter.strip_prefix("tag:") {
+                    let tag = tag.to_string();
+                    self.call(move |client| client.get_task_ids_with_tag(&tag))
+                } else if let Some(list) = filter.strip_prefix("list:") {
+                    let list = list.to_string();
+                    self.call(move |client| client.get_task_ids_in_list(&list))
+                } else {
+                    self.state.set_search_query(&filter);
+                    self.handle_action(Action::MarkMatching);
+                    return;
+                };
+                match result {
+                    Ok(ids) => {
+                        // Kept in list order, rows that are not listed are left out
+                        let ids: HashSet<i32> = ids.into_iter().collect();
+                        let ids: Vec<i32> = self
+                            .state
+                            .get_task_order()
+                            .iter()
+                            .filter(|id| ids.contains(id))
+                            .copied()
+                            .collect();
+                        self.mark_tasks(&ids, &filter);
                     }
+                    Err(e) => self.state.set_error(e.user_message()),
                 }
-                _ => Action::Empty,
-            },
-        };
-    }
-
-    pub fn handle_events(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        if event::poll(std::time::Duration::from_millis(16))? {
-            if let event::Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    let action = self.handle_key_stroke(key.code);
-                    self.handle_action(action);
+            }
+            Action::ClearSelection => self.state.clear_selection(),
+            Action::Undo => {
+                let undo_entry = match self.state.pop_undo_entry() {
+                    Some(undo_entry) => undo_entry,
+                    None => {
+                        self.state
+                            .set_notification(Severity::Info, String::from("Nothing to undo"));
+                        return;
+                    }
+                };
+                let task_list = undo_entry.task_list.clone();
+                if let Err(e) = self.call(move |client| client.restore_tasks(&task_list)) {
+                    self.state.set_error(e.user_message());
+                    return;
                 }
+                self.record_mutation();
+                let ids: Vec<i32> = undo_entry.task_list.iter().map(|task| task.id).collect();
+                self.refresh_tasks(&ids);
+                self.state
+                    .set_notification(Severity::Success, format!("Undid: {}", undo_entry.label));
             }
-        }
-        Ok(())
-    }
+            Action::DismissNotification => {
+                self.state.clear_notification();
+            }
+            Action::ExpireNotification => {
+                let is_expired = self
+                    .state
+                    .get_notification()
+                    .is_some_and(|notification| notification.is_expired(Instant::now()));
+                if is_expired {
+                    self.state.clear_notification();
+                }
+            }
+            Action::OpenExportScreen => {
+                let export_format = self.state.get_export_format();
+                let file_name = format!(
+                    "{}-{}.{}",
+                    constants::EXPORT_NAME,
+                    Local::now().format("%Y%m%d-%H%M%S"),
+                    export_format.extension()
+                );
+                let path = match self.state.get_config().get_export_path() {
+                    Ok(export_path) => export_path.join(file_name),
+                    Err(_) => PathBuf::from(file_name),
+                };
 
-    pub fn init_controller(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let app_config_path = get_app_config_path()?;
-        let is_first_time = !filesystem::file_exists(&app_config_path, constants::DB_NAME);
+                self.state.set_input(&path.display().to_string());
+                self.state.set_is_overwrite_pending(false);
+                self.state.set_screen(Screen::Export);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::CancelExport => {
+                self.state.set_input("");
+                self.state.set_is_overwrite_pending(false);
+                self.state.set_screen(Screen::Main);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::SwitchExportFormat => {
+                let current_format = self.state.get_export_format();
+                let next_format = current_format.next();
+                let current_extension = format!(".{}", current_format.extension());
 
-        self.state.set_is_first_time(is_first_time);
+                if let Some(stem) = self.state.get_input().strip_suffix(&current_extension) {
+                    let input = format!("{}.{}", stem, next_format.extension());
+                    self.state.set_input(&input);
+                }
+                self.state.set_export_format(next_format);
+                self.state.set_is_overwrite_pending(false);
+            }
+            Action::CompletePath => {
+                let input = complete_directory(self.state.get_input());
+                self.state.set_input(&input);
+                self.state.set_is_overwrite_pending(false);
+            }
+            Action::ConfirmExport => {
+                let path = expand_path(self.state.get_input());
+                let parent_exists = path
+                    .parent()
+                    .is_none_or(|parent| parent.as_os_str().is_empty() || parent.is_dir());
 
-        filesystem::create_config_folder(&app_config_path)?;
-        self.handle_action(Action::OpenGreetingsScreen);
-        self.client
-            .open_connection(app_config_path, constants::DB_NAME)?;
-        self.client.create_user_table()?;
-        self.client.create_todos_table()?;
-        self.handle_action(Action::Init);
-        Ok(())
-    }
+                if self.state.get_input().is_empty() {
+                    self.state
+                        .set_error(String::from("Please enter export path"));
+                } else if path.is_dir() {
+                    self.state
+                        .set_error(String::from("Export path is a directory"));
+                } else if !parent_exists {
+                    self.state
+                        .set_error(String::from("Export directory does not exist"));
+                } else if path.exists()
+                    && !self.state.get_is_overwrite_pending()
+                    && self.state.get_config().confirmations.overwrite_file
+                {
+                    self.handle_action(Action::RequestConfirmation(
+                        format!("{} already exists, overwrite it?", path.display()),
+                        Box::new(Action::OverwriteExport),
+                    ));
+                } else if self.state.get_export_format() == ExportFormat::Bundle
+                    && self.state.get_screen() == &Screen::Export
+                {
+                    self.state.set_passphrase(String::new());
+                    self.state.set_passphrase_purpose(PassphrasePurpose::Export);
+                    self.state.set_screen(Screen::Passphrase);
+                    self.handle_action(Action::DismissNotification);
+                } else {
+                    self.start_export(self.state.get_export_format(), path);
+                }
+            }
+            Action::Export(export_format, path) => self.start_export(export_format, path),
+            // Starts from the file earlier versions always imported
+            Action::OpenImportScreen => {
+                let input = match self.state.get_config().get_data_path() {
+                    Ok(data_path) => data_path.join(constants::ICS_NAME).display().to_string(),
+                    Err(_) => String::new(),
+                };
 
-    pub fn exit(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.client.close_connection()?;
-        Ok(())
-    }
+                self.state.set_input(&input);
+                self.state.set_screen(Screen::Import);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::CancelImport => {
+                self.state.set_input("");
+                self.state.set_screen(Screen::Main);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::ConfirmImport => {
+                let path = expand_path(self.state.get_input());
 
-    pub fn run<B: Backend>(
-        &mut self,
-        terminal: &mut Terminal<B>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.init_controller()?;
+                if path.is_file() {
+                    self.handle_action(Action::ImportICS(path));
+                } else {
+                    self.state
+                        .set_error(String::from("Please enter an existing ics path"));
+                }
+            }
+            Action::OpenRestoreScreen => {
+                let input = match self.state.get_config().get_export_path() {
+                    Ok(export_path) => format!("{}/", export_path.display()),
+                    Err(_) => String::new(),
+                };
 
-        while self.state.get_is_running() {
-            self.handle_events()?;
-            View::draw(terminal, &self.state)?;
-        }
-        Ok(())
-    }
-}
+                self.state.set_input(&input);
+                self.state.set_passphrase(String::new());
+                self.state.set_screen(Screen::Restore);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::CancelRestore => {
+                self.state.set_input("");
+                self.state.set_screen(Screen::Main);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::ConfirmRestore => {
+                let path = expand_path(self.state.get_input());
 
-#[cfg(test)]
-mod tests {
-    use filesystem::file_exists;
+                if path.is_file() {
+                    self.handle_action(Action::RestoreBundle(path));
+                } else {
+                    self.state
+                        .set_error(String::from("Please enter an existing bundle path"));
+                }
+            }
+            Action::RestoreBundle(path) => {
+                let task_order = self.state.get_task_order().clone();
+                let master_key = self.state.get_master_key().clone();
+                let passphrase = self.get_bundle_passphrase().to_string();
+                self.start_job(String::from("Restoring bundle"), move |client| {
+                    let result =
+                        restore_bundle(client, &path, &passphrase, &master_key, &task_order);
+                    JobOutcome::Restored(path, result)
+                });
+            }
+            Action::InputPassphraseChar(ch) => {
+                self.state.passphrase.push(ch);
+            }
+            Action::RemovePassphraseChar => {
+                self.state.passphrase.pop();
+            }
+            Action::ConfirmPassphrase => match self.state.get_passphrase_purpose() {
+                PassphrasePurpose::Export => self.handle_action(Action::ConfirmExport),
+                PassphrasePurpose::Restore => self.handle_action(Action::ConfirmRestore),
+            },
+            Action::CancelPassphrase => {
+                self.state.set_input("");
+                self.state.set_passphrase(String::new());
+                self.state.set_is_overwrite_pending(false);
+                self.state.set_screen(Screen::Main);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::ImportICS(path) => {
+                let task_order = self.state.get_task_order().clone();
+                let master_key = self.state.get_master_key().clone();
+                self.start_job(String::from("Importing tasks"), move |client| {
+                    JobOutcome::Imported(import_tasks(client, &path, &master_key, &task_order))
+                });
+            }
+            Action::CreateBackup => {
+                self.mutation_count = 0;
+                if let Some(backups_path) = &self.backups_path {
+                    let max_backups = self.state.get_config().backups.max_backups;
+                    let backups_path = backups_path.clone();
+                    self.start_job(String::from("Creating backup"), move |client| {
+                        JobOutcome::BackedUp(create_backup(client, &backups_path, max_backups))
+                    });
+                }
+            }
+            Action::OpenBackupsScreen => {
+                let backup_list = match &self.backups_path {
+                    Some(backups_path) => list_backups(backups_path),
+                    None => Ok(Vec::new()),
+                };
 
-    use super::*;
-    use std::path::PathBuf;
+                match backup_list {
+                    Ok(backup_list) => {
+                        self.state.set_backup_list(backup_list);
+                        self.state.set_backup_line(0);
+                        self.state.set_screen(Screen::Backups);
+                        self.handle_action(Action::DismissNotification);
+                    }
+                    Err(e) => self.state.set_error(e.user_message()),
+                }
+            }
+            Action::CloseBackupsScreen => {
+                self.state.set_screen(Screen::Main);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::BackupMenuDown => {
+                let current_line = self.state.get_backup_line();
+                if current_line < self.state.get_backup_list().len() as i32 - 1 {
+                    self.state.set_backup_line(current_line + 1);
+                }
+            }
+            Action::BackupMenuUp => {
+                let current_line = self.state.get_backup_line();
+                if current_line > 0 {
+                    self.state.set_backup_line(current_line - 1);
+                }
+            }
+            Action::RestoreBackup => {
+                let index = self.state.get_backup_line() as usize;
+                let backup = match self.state.get_backup_list().get(index) {
+                    Some(backup) => backup.clone(),
+                    None => {
+                        self.state.set_error(String::from("Please select a backup"));
+                        return;
+                    }
+                };
 
-    #[test]
-    fn test_action_handler() {
-        let mut path = PathBuf::new();
-        path.push("./test/controller/");
+                // Keep the current database around in case the restore was a mistake,
+                // one extra rotation makes sure the selected backup is not removed
+                if let Some(backups_path) = &self.backups_path {
+                    let max_backups = self.state.get_config().backups.max_backups;
+                    let backups_path = backups_path.clone();
+                    let result = self
+                        .call(move |client| create_backup(client, &backups_path, max_backups + 1));
+                    if let Err(e) = result {
+                        self.state.set_error(e.user_message());
+                        return;
+                    }
+                }
+                let backup_path = backup.path.clone();
+                match self.call(move |client| restore_backup(client, &backup_path)) {
+                    Ok(_) => {
+                        // Row versions start over with the restored database
+                        self.title_cache.clear();
+                        self.state.set_line(0);
+                        self.handle_action(Action::OpenMainScreen);
+                        if self.state.get_screen() == &Screen::Main {
+                            self.state.set_notification(
+                                Severity::Success,
+                                format!(
+                                    "Restored backup from {}",
+                                    backup.created_at.format("%Y-%m-%d %H:%M:%S")
+                                ),
+                            );
+                        }
+                    }
+                    Err(e) => self.state.set_error(e.user_message()),
+                }
+            }
+            Action::OpenMessagesScreen => {
+                self.state.set_history_line(0);
+                self.state.set_screen(Screen::Messages);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::CloseMessagesScreen => {
+                self.state.set_screen(Screen::Main);
+            }
+            Action::OpenStatsScreen => {
+                let today = Local::now().date_naive();
+                let since = get_heatmap_start(today);
+                match self.call(move |client| client.get_task_totals(since)) {
+                    Ok(task_totals) => {
+                        self.state.set_stats(compute_stats(task_totals, today));
+                        self.state.set_screen(Screen::Stats);
+                    }
+                    Err(e) => self.state.set_error(e.user_message()),
+                }
+            }
+            Action::CloseStatsScreen => {
+                self.state.set_screen(Screen::Main);
+            }
+            Action::ToggleTimer => {
+                let Some(task) = self.state.get_task(self.state.get_line()).cloned() else {
+                    return;
+                };
+                let running_timer = self.state.get_running_timer().cloned();
+                let is_stopping = running_timer
+                    .as_ref()
+                    .is_some_and(|time_entry| time_entry.task_id == task.id);
+                let task_id = task.id;
+                let result = self.call(move |client| {
+                    if is_stopping {
+                        client.stop_timer()
+                    } else {
+                        client.start_timer(task_id)
+                    }
+                });
+                if let Err(e) = result {
+                    self.state.set_error(e.user_message());
+                    return;
+                }
 
-        let mut controller = Controller::new();
-        assert_eq!(controller.state.get_is_running(), false);
+                self.load_time_entries();
+                let message = match running_timer {
+                    Some(time_entry) if time_entry.task_id == task.id => {
+                        let now = Local::now().naive_local();
+                        format!(
+                            "Stopped timer on {} after {}",
+                            task.title,
+                            format_elapsed(time_entry.get_duration(now))
+                        )
+                    }
+                    _ => format!("Started timer on {}", task.title),
+                };
+                self.state.set_notification(Severity::Info, message);
+            }
+            Action::ExportTimeReport(report_format, path) => {
+                let task_ids: Vec<i32> = self
+                    .state
+                    .get_time_entries()
+                    .iter()
+                    .map(|time_entry| time_entry.task_id)
+                    .collect::<HashSet<i32>>()
+                    .into_iter()
+                    .collect();
+                let task_list = match self.load_tasks(&task_ids) {
+                    Ok(task_list) => task_list,
+                    Err(e) => {
+                        self.state.set_error(e.user_message());
+                        return;
+                    }
+                };
+                let rows = build_time_report(
+                    self.state.get_time_entries(),
+                    &task_list,
+                    Local::now().naive_local(),
+                );
+                let label = format!("Exporting {} time report", report_format.extension());
+                self.start_job(label, move |_| {
+                    let result = write_time_report(&rows, report_format, &path).and_then(|_| {
+                        fs::canonicalize(&path)
+                            .map_err(|e| TuduError::io("Could not resolve export path", e))
+                    });
+                    JobOutcome::ReportExported(report_format, result)
+                });
+            }
+            Action::Tick(now) => {
+                self.handle_action(Action::ExpireNotification);
 
-        controller
-            .client
-            .open_connection(path, constants::DB_NAME)
-            .expect("Could not open connection");
+                let config = self.state.get_config().pomodoro.clone();
+                let Some(pomodoro) = self.state.get_pomodoro_mut() else {
+                    return;
+                };
+                let Some(finished_phase) = pomodoro.advance(&config, now) else {
+                    return;
+                };
+                let (task_id, next_phase) = (pomodoro.task_id, pomodoro.phase);
 
-        // Greetings
-        controller.handle_action(Action::OpenGreetingsScreen);
-        assert_eq!(controller.state.get_screen(), &Screen::Greetings);
+                if finished_phase == PomodoroPhase::Work {
+                    self.handle_action(Action::RecordPomodoro(task_id, config.work_minutes));
+                }
+                self.state.set_notification(
+                    Severity::Info,
+                    format!(
+                        "{} is over, {} started",
+                        finished_phase.label(),
+                        next_phase.label().to_lowercase()
+                    ),
+                );
+                self.state.set_is_bell_pending(config.bell);
+            }
+            Action::RecordPomodoro(task_id, minutes) => {
+                match self.call(move |client| client.record_pomodoro(task_id, minutes)) {
+                    Ok(_) => self.load_pomodoro_counts(),
+                    Err(e) => self.state.set_error(e.user_message()),
+                }
+            }
+            Action::OpenFocusScreen => {
+                let Some(task_id) = self.state.get_task_id(self.state.get_line()) else {
+                    return;
+                };
+                let config = &self.state.get_config().pomodoro;
+                let pomodoro = Pomodoro::new(task_id, config, Instant::now());
+                self.state.set_pomodoro(Some(pomodoro));
+                self.state.set_screen(Screen::Focus);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::CloseFocusScreen => {
+                if let Some(pomodoro) = self.state.get_pomodoro() {
+                    let count = pomodoro.completed_count;
+                    let plural = if count == 1 { "" } else { "s" };
+                    self.state.set_notification(
+                        Severity::Info,
+                        format!("Focus session ended after {} pomodoro{}", count, plural),
+                    );
+                }
+                self.state.set_pomodoro(None);
+                self.state.set_screen(Screen::Main);
+            }
+            Action::ToggleFocusPause => {
+                if let Some(pomodoro) = self.state.get_pomodoro_mut() {
+                    pomodoro.toggle_pause(Instant::now());
+                }
+            }
+            Action::SkipFocusPhase => {
+                let config = self.state.get_config().pomodoro.clone();
+                if let Some(pomodoro) = self.state.get_pomodoro_mut() {
+                    pomodoro.skip(&config, Instant::now());
+                }
+            }
+            Action::OpenHistoryScreen => {
+                let Some(task_id) = self.state.get_task_id(self.state.get_line()) else {
+                    return;
+                };
+                match self.call(move |client| {
+                    client.get_task_events(Some(task_id), constants::MAX_TASK_EVENTS)
+                }) {
+                    Ok(mut task_events) => {
+                        for event in task_events.iter_mut() {
+                            if let Ok(title) = decrypt(&event.title, self.state.get_master_key()) {
+                                event.title = title;
+                            }
+                        }
+                        self.state.set_task_events(task_events);
+                        self.state.set_task_events_line(0);
+                        self.state.set_screen(Screen::History);
+                    }
+                    Err(e) => self.state.set_error(e.user_message()),
+                }
+            }
+            Action::CloseHistoryScreen => {
+                self.state.set_screen(Screen::Main);
+            }
+            Action::HistoryDown => {
+                let current_line = self.state.get_task_events_line();
+                if current_line < self.state.get_task_events().len() as i32 - 1 {
+                    self.state.set_task_events_line(current_line + 1);
+                }
+            }
+            Action::HistoryUp => {
+                let current_line = self.state.get_task_events_line();
+                if current_line > 0 {
+                    self.state.set_task_events_line(current_line - 1);
+                }
+            }
+            Action::MessagesDown => {
+                let current_line = self.state.get_history_line();
+                if current_line < self.state.get_notification_history().len() as i32 - 1 {
+                    self.state.set_history_line(current_line + 1);
+                }
+            }
+            Action::MessagesUp => {
+                let current_line = self.state.get_history_line();
+                if current_line > 0 {
+                    self.state.set_history_line(current_line - 1);
+                }
+            }
+            Action::OpenSearchScreen => {
+                self.state.set_input("");
+                self.state.set_screen(Screen::Search);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::CancelSearch => {
+                self.state.set_input("");
+                self.state.set_screen(Screen::Main);
+            }
+            Action::ConfirmSearch => {
+                // An empty search repeats the previous one
+                if !self.state.get_input().is_empty() {
+                    let query = self.state.get_input().clone();
+                    self.state.set_search_query(&query);
+                }
+                self.state.set_input("");
+                self.state.set_screen(Screen::Main);
+                self.search_tasks(true);
+            }
+            Action::SearchNext => self.search_tasks(true),
+            Action::SearchPrevious => self.search_tasks(false),
+            Action::OpenCommandLine => {
+                self.state.set_input("");
+                self.state.set_command_history_line(None);
+                self.state.set_screen(Screen::Command);
+                self.handle_action(Action::DismissNotification);
+            }
+            Action::PromptCommand(command) => {
+                self.handle_action(Action::OpenCommandLine);
+                self.state.set_input(&command);
+            }
+            Action::CancelCommand => {
+                self.state.set_input("");
+                self.state.set_screen(Screen::Main);
+            }
+            Action::ExecuteCommand => {
+                let command = self.state.get_input().trim().to_string();
+                self.state.push_command_history(&command);
+                self.state.set_input("");
+                self.state.set_screen(Screen::Main);
+                match parse_command(&command) {
+                    Ok(action @ (Action::Export(..) | Action::ExportTimeReport(..))) => {
+                        let action = self.get_confirmed_action(action);
+                        self.handle_action(action);
+                    }
+                    Ok(action) => self.handle_action(action),
+                    Err(e) => self.state.set_error(e.user_message()),
+                }
+            }
+            Action::CompleteCommand => {
+                let theme_names = self.state.get_config().get_theme_names();
+                let theme_names: Vec<&str> = theme_names.iter().map(String::as_str).collect();
+                let input = complete_command(self.state.get_input(), &theme_names);
+                self.state.set_input(&input);
+            }
+            Action::CommandHistoryUp => {
+                let history_length = self.state.get_command_history().len();
+                let history_line = match self.state.get_command_history_line() {
+                    Some(line) => line.saturating_sub(1),
+                    None if history_length > 0 => history_length - 1,
+                    None => return,
+                };
+                let command = self.state.get_command_history()[history_line].clone();
+                self.state.set_command_history_line(Some(history_line));
+                self.state.set_input(&command);
+            }
+            Action::CommandHistoryDown => {
+                // Moving past the newest command goes back to an empty line
+                let history_line = match self.state.get_command_history_line() {
+                    Some(line) => line + 1,
+                    None => return,
+                };
+                match self.state.get_command_history().get(history_line).cloned() {
+                    Some(command) => {
+                        self.state.set_command_history_line(Some(history_line));
+                        self.state.set_input(&command);
+                    }
+                    None => {
+                        self.state.set_command_history_line(None);
+                        self.state.set_input("");
+                    }
+                }
+            }
+            Action::CreateTask(title) => {
+                self.state.set_editing_task_id(None);
+                self.state.set_input(&title);
+                self.handle_action(Action::AddTask);
+                self.state.set_input("");
+            }
+            Action::CompleteTask(index) => {
+                // The task can be far outside the loaded rows
+                let task = match self.state.get_task_id(index as i32) {
+                    Some(id) => self
+                        .load_tasks(&[id])
+                        .ok()
+                        .and_then(|task_list| task_list.first().cloned()),
+                    None => None,
+                };
+                let task = match task {
+                    Some(task) => task,
+                    None => {
+                        self.state
+                            .set_error(format!("There is no task {}", index + 1));
+                        return;
+                    }
+                };
+                if task.status == "completed" {
+                    self.state.set_notification(
+                        Severity::Info,
+                        format!("Task {} is already done", index + 1),
+                    );
+                    return;
+                }
 
-        controller.handle_action(Action::InputMaskedChar('S'));
-        controller.handle_action(Action::InputMaskedChar('E'));
-        controller.handle_action(Action::InputMaskedChar('C'));
-        controller.handle_action(Action::InputMaskedChar('R'));
-        controller.handle_action(Action::InputMaskedChar('E'));
-        controller.handle_action(Action::InputMaskedChar('T'));
-        controller.handle_action(Action::InputMaskedChar('T'));
+                let label = get_count_label("Completed", 1);
+                if let Err(e) = self.change_tasks(&[task.id], label, |client, ids| {
+                    client.update_tasks_status(ids, "completed")
+                }) {
+                    self.state.set_error(e.user_message());
+                    return;
+                }
+                self.refresh_tasks(&[task.id]);
+                self.go_to_line(index as i32);
+                self.state
+                    .set_notification(Severity::Success, format!("Completed {}", task.title));
+            }
+            // The page size and viewport are fixed right away, before the next draw
+            Action::Resize(width, height) => {
+                self.area = Rect::new(0, 0, width, height);
+                self.state
+                    .set_page_size(View::get_list_height(self.area) as i32);
+                self.update_list_offset();
+            }
+            Action::SwitchTheme => {
+                let theme_names = self.state.get_config().get_theme_names();
+                let next_index = theme_names
+                    .iter()
+                    .position(|name| name == self.state.get_theme_name())
+                    .map_or(0, |index| (index + 1) % theme_names.len());
+                self.handle_action(Action::SetTheme(theme_names[next_index].clone()));
+            }
+            Action::SetTheme(theme_name) => match self.state.get_config().get_theme(&theme_name) {
+                Some(theme) => {
+                    self.state.set_theme(&theme_name, theme);
+                    self.state
+                        .set_notification(Severity::Info, format!("Theme: {}", theme_name));
+                }
+                None => self.state.set_error(format!(
+                    "Unknown theme {}, use {}",
+                    theme_name,
+                    self.state.get_config().get_theme_names().join(", ")
+                )),
+            },
+            Action::SortTasks(sort_field) => {
+                self.load_generation += 1;
+                self.finish_loading_tasks();
+                match sort_field {
+                    // Every title is decrypted first, a page at a time
+                    SortField::Title => {
+                        self.handle_action(Action::LoadTaskPage(self.load_generation, 0))
+                    }
+                    sort_field => self.sort_tasks(sort_field),
+                }
+            }
+            Action::OpenHelp => {
+                let screen = *self.state.get_screen();
+                if screen == Screen::Help {
+                    return;
+                }
+                let help_line = self.state.get_keymap().get_help_offset(&screen);
+                self.state.set_previous_screen(screen);
+                self.state.set_help_line(help_line as i32);
+                self.state.set_screen(Screen::Help);
+            }
+            Action::CloseHelp => {
+                let previous_screen = *self.state.get_previous_screen();
+                self.state.set_screen(previous_screen);
+            }
+            Action::ScrollHelp(offset) => {
+                let last_line = self.state.get_keymap().get_help_length() as i32 - 1;
+                let help_line = (self.state.get_help_line() + offset).clamp(0, last_line.max(0));
+                self.state.set_help_line(help_line);
+            }
+            Action::RequestConfirmation(message, action) => {
+                let screen = *self.state.get_screen();
+                self.state
+                    .set_confirmation(Some(Confirmation::new(message, *action, screen)));
+                self.state.set_screen(Screen::Confirm);
+            }
+            Action::AcceptConfirmation => {
+                if let Some(confirmation) = self.state.take_confirmation() {
+                    self.state.set_screen(confirmation.previous_screen);
+                    self.handle_action(confirmation.action);
+                }
+            }
+            Action::RejectConfirmation => {
+                if let Some(confirmation) = self.state.take_confirmation() {
+                    self.state.set_screen(confirmation.previous_screen);
+                }
+            }
+            Action::OverwriteExport => {
+                self.state.set_is_overwrite_pending(true);
+                self.handle_action(Action::ConfirmExport);
+            }
+            Action::Empty => {}
+        }
+    }
+
+    // Moves to the next task containing the search query, wrapping around the list,
+    // titles are decrypted a page at a time until one matches
+    fn search_tasks(&mut self, is_forward: bool) {
+        let query = self.state.get_search_query().to_lowercase();
+        if query.is_empty() {
+            self.state.set_error(String::from("No previous search"));
+            return;
+        }
+
+        let task_order = self.state.get_task_order();
+        let length = task_order.len();
+        let current_line = self.state.get_line().max(0) as usize;
+        let ids: Vec<i32> = (1..=length)
+            .map(|offset| {
+                if is_forward {
+                    (current_line + offset) % length
+                } else {
+                    (current_line + length - offset % length) % length
+                }
+            })
+            .map(|line| task_order[line])
+            .collect();
+        let mut found_id = None;
+        for page in ids.chunks(TASK_PAGE_SIZE) {
+            if let Err(e) = self.decrypt_titles(page) {
+                self.state.set_error(e.user_message());
+                return;
+            }
+            found_id = page.iter().copied().find(|id| {
+                self.title_cache
+                    .get_title(*id)
+                    .is_some_and(|title| title.to_lowercase().contains(&query))
+            });
+            if found_id.is_some() {
+                break;
+            }
+        }
+
+        let found_line = found_id.and_then(|found_id| {
+            self.state
+                .get_task_order()
+                .iter()
+                .position(|id| *id == found_id)
+        });
+        match found_line {
+            Some(line) => self.go_to_line(line as i32),
+            None => self.state.set_notification(
+                Severity::Warning,
+                format!("Pattern not found: {}", self.state.get_search_query()),
+            ),
+        }
+    }
+
+    pub fn handle_key_stroke(&mut self, key: KeyEvent) -> Action {
+        let screen = self.state.get_screen();
+        let parsed_keys = self.key_parser.push(self.state.get_keymap(), screen, &key);
+        self.state.set_pending_keys(self.key_parser.get_pending());
+        let screen = self.state.get_screen();
+
+        match parsed_keys {
+            ParsedKeys::Matched(action, count) => {
+                let action = self.apply_count(action, count);
+                return self.get_bound_action(action);
+            }
+            ParsedKeys::Pending => return Action::Empty,
+            ParsedKeys::Unbound => {}
+        }
+
+        // Whatever is not bound is typed into the input of the screen
+        let is_typing = !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        match (screen, key.code) {
+            (
+                Screen::Add
+                | Screen::Export
+                | Screen::Import
+                | Screen::Restore
+                | Screen::Search
+                | Screen::Command,
+                KeyCode::Char(ch),
+            ) if is_typing => Action::InputChar(ch),
+            (Screen::Passphrase, KeyCode::Char(ch)) if is_typing => Action::InputPassphraseChar(ch),
+            (Screen::Greetings, KeyCode::Char(ch)) if is_typing => Action::InputMaskedChar(ch),
+            _ => Action::Empty,
+        }
+    }
+
+    pub fn handle_mouse_event(&mut self, mouse: MouseEvent) -> Action {
+        let screen = *self.state.get_screen();
+
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.state.get_keymap().get_action(&screen, "up").cloned(),
+            MouseEventKind::ScrollDown => {
+                self.state.get_keymap().get_action(&screen, "down").cloned()
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.key_parser.reset();
+                self.state.set_pending_keys(String::new());
+
+                let legend_area = View::get_legend_area(self.area);
+                if mouse.row == legend_area.y {
+                    let column = mouse.column.saturating_sub(legend_area.x) as usize;
+                    self.state
+                        .get_keymap()
+                        .get_legend_action(&screen, column)
+                        .cloned()
+                        .map(|action| self.get_bound_action(action))
+                } else if screen == Screen::Main {
+                    match View::get_clicked_task(self.area, &self.state, mouse.column, mouse.row) {
+                        Some((line, true)) => {
+                            self.handle_action(Action::MenuGoTo(line as usize));
+                            Some(Action::ToggleTaskStatus)
+                        }
+                        Some((line, false)) => Some(Action::MenuGoTo(line as usize)),
+                        None => None,
+                    }
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+        .unwrap_or(Action::Empty)
+    }
+
+    // Keys and legend clicks both end up here so they behave the same
+    fn get_bound_action(&self, action: Action) -> Action {
+        let action = match action {
+            Action::Exit
+                if self.state.get_screen() == &Screen::Main && self.state.has_selection() =>
+            {
+                Action::ClearSelection
+            }
+            action => action,
+        };
+        self.get_confirmed_action(action)
+    }
+
+    // Destructive keys ask first unless the config turns the question off
+    fn get_confirmed_action(&self, action: Action) -> Action {
+        let confirmations = &self.state.get_config().confirmations;
+        let line = self.state.get_line().max(0);
+        let remaining_count = (self.state.get_task_list_length() - line).max(0) as usize;
+
+        let message = match &action {
+            Action::Exit if confirmations.exit && self.state.get_screen() == &Screen::Main => {
+                Some(String::from("Quit tudu?"))
+            }
+            Action::RemoveTask if confirmations.remove_task && self.state.has_selection() => Some(
+                format!("Remove {} selected tasks?", self.get_target_ids().len()),
+            ),
+            Action::RemoveTask if confirmations.remove_task => self
+                .state
+                .get_task(line)
+                .map(|task| format!("Remove \"{}\"?", task.title)),
+            Action::Repeat(count, repeated)
+                if **repeated == Action::RemoveTask
+                    && confirmations.remove_task
+                    && remaining_count > 0 =>
+            {
+                Some(format!("Remove {} tasks?", remaining_count.min(*count)))
+            }
+            Action::Export(_, path) | Action::ExportTimeReport(_, path)
+                if path.exists() && confirmations.overwrite_file =>
+            {
+                Some(format!("{} already exists, overwrite it?", path.display()))
+            }
+            Action::RestoreBackup
+                if confirmations.restore_backup && !self.state.get_backup_list().is_empty() =>
+            {
+                Some(String::from(
+                    "Replace the tasks with this backup? The current ones are backed up first",
+                ))
+            }
+            _ => None,
+        };
+
+        match message {
+            Some(message) => Action::RequestConfirmation(message, Box::new(action)),
+            None => action,
+        }
+    }
+
+    // "5j" moves five times, "5G" goes to the fifth task
+    fn apply_count(&self, action: Action, count: Option<usize>) -> Action {
+        match (action, count) {
+            (Action::SubmitMasterKey, _) => self.get_master_key_action(),
+            (Action::MenuFirst | Action::MenuLast, Some(count)) => {
+                Action::MenuGoTo(count.saturating_sub(1))
+            }
+            (action, Some(count)) if count > 1 => Action::Repeat(count, Box::new(action)),
+            (action, _) => action,
+        }
+    }
+
+    fn get_master_key_action(&self) -> Action {
+        if self.state.get_is_first_time() {
+            Action::AddSecret
+        } else {
+            Action::OpenMainScreen
+        }
+    }
+
+    fn start_export(&mut self, export_format: ExportFormat, path: PathBuf) {
+        let task_order = self.state.get_task_order().clone();
+        let master_key = self.state.get_master_key().clone();
+        let passphrase = self.get_bundle_passphrase().to_string();
+        let label = format!("Exporting {} file", export_format.extension());
+        self.start_job(label, move |client| {
+            let result = export_tasks(
+                client,
+                &task_order,
+                &master_key,
+                &passphrase,
+                export_format,
+                &path,
+            );
+            JobOutcome::Exported(export_format, result)
+        });
+    }
+
+    // Long operations run on the database thread, their outcome comes back as an event
+    fn start_job<F>(&mut self, label: String, job: F)
+    where
+        F: FnOnce(&mut Client) -> JobOutcome + Send + 'static,
+    {
+        if let Some(loading) = self.state.get_loading() {
+            let message = format!("{} is still running, please wait", loading.label);
+            self.state.set_error(message);
+            return;
+        }
+        match self.worker.spawn(job, self.event_loop.get_sender()) {
+            Ok(_) => self
+                .state
+                .set_loading(Some(Loading::new(label, Instant::now()))),
+            Err(e) => self.state.set_error(e.user_message()),
+        }
+    }
+
+    fn handle_job_outcome(&mut self, outcome: JobOutcome) {
+        self.state.set_loading(None);
+        match outcome {
+            JobOutcome::Exported(export_format, Ok((absolute_path, skipped_count))) => {
+                if matches!(self.state.get_screen(), Screen::Export | Screen::Passphrase) {
+                    self.state.set_input("");
+                    self.state.set_passphrase(String::new());
+                    self.state.set_is_overwrite_pending(false);
+                    self.state.set_screen(Screen::Main);
+                }
+                let message = format!(
+                    "Saved {} file to {}",
+                    export_format.extension(),
+                    absolute_path.display()
+                );
+                if skipped_count == 0 {
+                    self.state.set_notification(Severity::Success, message);
+                } else {
+                    let noun = if skipped_count == 1 { "row" } else { "rows" };
+                    self.state.set_notification(
+                        Severity::Warning,
+                        format!("{}, skipped {} todos {}", message, skipped_count, noun),
+                    );
+                }
+            }
+            JobOutcome::ReportExported(report_format, Ok(absolute_path)) => {
+                self.state.set_notification(
+                    Severity::Success,
+                    format!(
+                        "Saved {} time report to {}",
+                        report_format.extension(),
+                        absolute_path.display()
+                    ),
+                );
+            }
+            JobOutcome::Imported(Ok(imported_ids)) => {
+                if self.state.get_screen() == &Screen::Import {
+                    self.state.set_input("");
+                    self.state.set_screen(Screen::Main);
+                }
+                self.record_mutation();
+                self.refresh_tasks(&imported_ids);
+                self.state.set_notification(
+                    Severity::Success,
+                    format!("Imported {} tasks from ics file", imported_ids.len()),
+                );
+            }
+            JobOutcome::Restored(path, Ok(summary)) => {
+                self.refresh_tasks(&summary.restored_ids);
+                self.load_time_entries();
+                self.load_pomodoro_counts();
+                self.state.set_input("");
+                self.state.set_passphrase(String::new());
+                self.state.set_screen(Screen::Main);
+                let message = format!(
+                    "Restored {} tasks from {}",
+                    summary.restored_ids.len(),
+                    path.display()
+                );
+                if summary.get_skipped_count() == 0 {
+                    self.state.set_notification(Severity::Success, message);
+                } else {
+                    self.state.set_notification(
+                        Severity::Warning,
+                        format!("{}, skipped {}", message, summary.format_skipped()),
+                    );
+                }
+            }
+            JobOutcome::BackedUp(Ok(_)) => {
+                if self.state.get_screen() == &Screen::Backups {
+                    self.handle_action(Action::OpenBackupsScreen);
+                }
+            }
+            JobOutcome::Restored(_, Err(TuduError::Auth(_)))
+                if self.state.get_screen() == &Screen::Restore =>
+            {
+                self.state.set_passphrase(String::new());
+                self.state
+                    .set_passphrase_purpose(PassphrasePurpose::Restore);
+                self.state.set_screen(Screen::Passphrase);
+                self.state.set_notification(
+                    Severity::Info,
+                    String::from(
+                        "Bundle is not encrypted with the master key, enter its passphrase",
+                    ),
+                );
+            }
+            JobOutcome::Exported(_, Err(e))
+            | JobOutcome::ReportExported(_, Err(e))
+            | JobOutcome::Imported(Err(e))
+            | JobOutcome::Restored(_, Err(e))
+            | JobOutcome::BackedUp(Err(e)) => self.state.set_error(e.user_message()),
+        }
+        self.run_deferred_actions();
+    }
+
+    fn run_deferred_actions(&mut self) {
+        for action in std::mem::take(&mut self.deferred_actions) {
+            self.handle_action(action);
+        }
+    }
+
+    // Quick queries run on the database thread and wait for their result, a running job
+    // would hold them and the ui up until it is done, so they are refused meanwhile
+    fn call<T, F>(&self, job: F) -> TuduResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> TuduResult<T> + Send + 'static,
+    {
+        if let Some(loading) = self.get_running_job() {
+            return Err(TuduError::validation(&format!(
+                "{} is still running, please wait",
+                loading.label
+            )));
+        }
+        self.worker.call(job)
+    }
+
+    // Sorting by title shows the spinner too but loads a page at a time between the events
+    fn get_running_job(&self) -> Option<&Loading> {
+        self.state
+            .get_loading()
+            .filter(|loading| loading.label != SORTING_TASKS_LABEL)
+    }
+
+    // Marked tasks and the visual range, or the task under the cursor when nothing is marked
+    fn get_target_ids(&self) -> Vec<i32> {
+        let mut ids: Vec<i32> = self
+            .state
+            .get_task_order()
+            .iter()
+            .enumerate()
+            .filter(|(line, _)| self.state.is_marked(*line as i32))
+            .map(|(_, id)| *id)
+            .collect();
+        if ids.is_empty() {
+            ids.extend(self.state.get_task_id(self.state.get_line()));
+        }
+        ids
+    }
+
+    // Keeps the rows as they were so a single undo reverts the whole change
+    fn change_tasks<F>(&mut self, ids: &[i32], label: String, change: F) -> TuduResult<usize>
+    where
+        F: FnOnce(&Client, &[i32]) -> TuduResult<usize> + Send + 'static,
+    {
+        let ids = ids.to_vec();
+        let (task_list, changed_count) = self.call(move |client| {
+            let task_list = client.get_tasks_by_ids(&ids)?;
+            let changed_count = change(client, &ids)?;
+            Ok((task_list, changed_count))
+        })?;
+
+        self.state.push_undo_entry(UndoEntry::new(label, task_list));
+        self.record_mutation();
+        Ok(changed_count)
+    }
+
+    fn mark_tasks(&mut self, ids: &[i32], filter: &str) {
+        let mut selected_ids = self.state.get_selected_ids().clone();
+        selected_ids.extend(ids.iter());
+        self.state.set_selected_ids(selected_ids);
+        self.state.set_notification(
+            Severity::Info,
+            format!("Selected {} tasks matching {}", ids.len(), filter),
+        );
+    }
+
+    // The changed rows are shown again and the marks are cleared once a bulk change went through
+    fn finish_bulk_change(&mut self, ids: &[i32], result: TuduResult<usize>) {
+        match result {
+            Ok(_) => {
+                self.state.clear_selection();
+                self.refresh_tasks(ids);
+            }
+            Err(e) => self.state.set_error(e.user_message()),
+        }
+    }
+
+    // Scrolls the task list only as far as needed to keep the selected line visible
+    fn update_list_offset(&mut self) {
+        let line = self.state.get_line();
+        let page_size = self.state.get_page_size();
+        let mut list_offset = self.state.get_list_offset();
+
+        if line < list_offset {
+            list_offset = line;
+        } else if line >= list_offset + page_size {
+            list_offset = line - page_size + 1;
+        }
+        let max_offset = (self.state.get_task_list_length() - page_size).max(0);
+        self.state.set_list_offset(list_offset.clamp(0, max_offset));
+        self.load_task_window();
+    }
+
+    fn go_to_line(&mut self, line: i32) {
+        self.state.set_line(line);
+        self.update_list_offset();
+    }
+
+    // Loads the rows around the viewport once a visible one is missing, the rows of the
+    // running timer and the focus session are kept for their titles
+    fn load_task_window(&mut self) {
+        // Rows that are not loaded yet stay empty until the job is done
+        if self.get_running_job().is_some() {
+            return;
+        }
+
+        let list_offset = self.state.get_list_offset();
+        let page_size = self.state.get_page_size();
+        let line = self.state.get_line();
+        let is_loaded = (list_offset..list_offset + page_size)
+            .chain([line])
+            .filter_map(|line| self.state.get_task_id(line))
+            .all(|id| self.state.get_task_by_id(id).is_some());
+        if is_loaded {
+            return;
+        }
+
+        let task_order = self.state.get_task_order();
+        let start = (list_offset - TASK_WINDOW_MARGIN).clamp(0, task_order.len() as i32) as usize;
+        let end = (list_offset + page_size + TASK_WINDOW_MARGIN)
+            .clamp(start as i32, task_order.len() as i32) as usize;
+        let mut ids: HashSet<i32> = task_order[start..end].iter().copied().collect();
+        ids.extend(self.state.get_task_id(line));
+        ids.extend(self.state.get_running_timer().map(|timer| timer.task_id));
+        ids.extend(self.state.get_pomodoro().map(|pomodoro| pomodoro.task_id));
+
+        let missing_ids: Vec<i32> = ids
+            .iter()
+            .filter(|id| self.state.get_task_by_id(**id).is_none())
+            .copied()
+            .collect();
+        let task_count = self.state.get_task_list_length();
+        let task_list = match self.load_tasks(&missing_ids) {
+            Ok(task_list) => task_list,
+            Err(e) => {
+                self.state.set_error(e.user_message());
+                return;
+            }
+        };
+        let mut loaded_tasks: Vec<Task> = self
+            .state
+            .get_loaded_tasks()
+            .values()
+            .filter(|task| ids.contains(&task.id))
+            .cloned()
+            .collect();
+        loaded_tasks.extend(task_list);
+        self.state.set_loaded_tasks(loaded_tasks);
+
+        // Rows that did not decrypt left the list, the ones after them moved up
+        if self.state.get_task_list_length() < task_count {
+            self.update_list_offset();
+        }
+    }
+
+    // Reloads the list order, titles are sorted here once the cache holds every one of them
+    fn load_task_order(&mut self, sort_field: SortField) -> TuduResult<()> {
+        let query_field = match sort_field {
+            SortField::Title => SortField::Created,
+            sort_field => sort_field,
+        };
+        let mut ids = self.call(move |client| client.get_task_ids(query_field))?;
+        if sort_field == SortField::Title {
+            self.decrypt_titles(&ids)?;
+            // Ties keep the creation order
+            let title_cache = &self.title_cache;
+            ids.retain(|id| title_cache.get_title(*id).is_some());
+            ids.sort_by_cached_key(|id| {
+                let title = title_cache.get_title(*id).map(|title| title.to_lowercase());
+                (title, *id)
+            });
+        } else {
+            ids.retain(|id| !self.title_cache.has_failed(*id));
+        }
+
+        self.state.set_task_order(ids);
+        self.state.prune_selection();
+        let last_line = (self.state.get_task_list_length() - 1).max(0);
+        self.go_to_line(self.state.get_line().min(last_line));
+        Ok(())
+    }
+
+    // The selection follows the task it was on
+    fn sort_tasks(&mut self, sort_field: SortField) {
+        let selected_id = self.state.get_task_id(self.state.get_line());
+        if let Err(e) = self.load_task_order(sort_field) {
+            self.state.set_error(e.user_message());
+            return;
+        }
+        if let Some(line) = self
+            .state
+            .get_task_order()
+            .iter()
+            .position(|id| Some(*id) == selected_id)
+        {
+            self.go_to_line(line as i32);
+        }
+        self.state.set_sort_field(sort_field);
+        self.state.set_notification(
+            Severity::Info,
+            format!("Sorted tasks by {}", sort_field.get_name()),
+        );
+    }
+
+    // Decrypts the titles that are not cached yet, a page at a time
+    fn decrypt_titles(&mut self, ids: &[i32]) -> TuduResult<()> {
+        let missing_ids: Vec<i32> = ids
+            .iter()
+            .filter(|id| {
+                self.title_cache.get_title(**id).is_none() && !self.title_cache.has_failed(**id)
+            })
+            .copied()
+            .collect();
+        for page in missing_ids.chunks(TASK_PAGE_SIZE) {
+            self.load_tasks(page)?;
+        }
+        Ok(())
+    }
+
+    fn load_tasks(&mut self, ids: &[i32]) -> TuduResult<Vec<Task>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids = ids.to_vec();
+        let task_list = self.call(move |client| client.get_tasks_by_ids(&ids))?;
+        Ok(self.decrypt_tasks(task_list))
+    }
+
+    // Rows that do not decrypt with the master key are left out of the list
+    fn decrypt_tasks(&mut self, task_list: Vec<Task>) -> Vec<Task> {
+        let (task_list, failed_count) = self
+            .title_cache
+            .decrypt_tasks(task_list, self.state.get_master_key());
+        if failed_count > 0 {
+            let mut task_order = self.state.get_task_order().clone();
+            task_order.retain(|id| !self.title_cache.has_failed(*id));
+            self.state.set_task_order(task_order);
+            self.state.prune_selection();
+            let last_line = (self.state.get_task_list_length() - 1).max(0);
+            self.state.set_line(self.state.get_line().min(last_line));
+            self.state.set_notification(
+                Severity::Warning,
+                format!(
+                    "{} tasks could not be decrypted, run tudu doctor to repair",
+                    failed_count
+                ),
+            );
+        }
+        task_list
+    }
+
+    // Reloads the latest events once another task is selected or the selected one changed
+    fn update_task_detail(&mut self) {
+        if !View::has_detail_panel(self.area) || self.get_running_job().is_some() {
+            return;
+        }
+        let detail_task = self
+            .state
+            .get_task(self.state.get_line())
+            .map(|task| (task.id, task.version));
+        if detail_task == self.state.get_detail_task() {
+            return;
+        }
+        let Some((task_id, _)) = detail_task else {
+            self.state.set_detail_events(None, Vec::new());
+            return;
+        };
+
+        match self
+            .call(move |client| client.get_task_events(Some(task_id), constants::MAX_DETAIL_EVENTS))
+        {
+            Ok(events) => self.state.set_detail_events(detail_task, events),
+            Err(e) => self.state.set_error(e.user_message()),
+        }
+    }
+
+    fn record_mutation(&mut self) {
+        self.mutation_count += 1;
+        if self.mutation_count >= self.state.get_config().backups.mutations_per_backup {
+            self.handle_action(Action::CreateBackup);
+        }
+    }
+
+    fn get_bundle_passphrase(&self) -> &str {
+        if self.state.get_passphrase().is_empty() {
+            self.state.get_master_key()
+        } else {
+            self.state.get_passphrase()
+        }
+    }
+
+    // Reloads the order and decrypts again only the rows a change touched
+    fn refresh_tasks(&mut self, ids: &[i32]) {
+        self.title_cache.remove(ids);
+        let loaded_tasks: Vec<Task> = self
+            .state
+            .get_loaded_tasks()
+            .values()
+            .filter(|task| !ids.contains(&task.id))
+            .cloned()
+            .collect();
+        self.state.set_loaded_tasks(loaded_tasks);
+        if let Err(e) = self.load_task_order(self.state.get_sort_field()) {
+            self.state.set_error(e.user_message());
+        }
+    }
+
+    fn finish_loading_tasks(&mut self) {
+        let is_loading_tasks = self
+            .state
+            .get_loading()
+            .is_some_and(|loading| loading.label == SORTING_TASKS_LABEL);
+        if is_loading_tasks {
+            self.state.set_loading(None);
+            self.run_deferred_actions();
+        }
+    }
+
+    fn load_time_entries(&mut self) {
+        match self.call(|client| client.get_time_entries()) {
+            Ok(time_entries) => self.state.set_time_entries(time_entries),
+            Err(e) => self.state.set_error(e.user_message()),
+        }
+    }
+
+    fn load_pomodoro_counts(&mut self) {
+        match self.call(|client| client.get_pomodoro_counts()) {
+            Ok(pomodoro_counts) => self.state.set_pomodoro_counts(pomodoro_counts),
+            Err(e) => self.state.set_error(e.user_message()),
+        }
+    }
+
+    // A running timer or countdown changes what is drawn every second on its own
+    fn is_clock_visible(&self) -> bool {
+        match self.state.get_screen() {
+            Screen::Focus => self
+                .state
+                .get_pomodoro()
+                .is_some_and(|pomodoro| !pomodoro.is_paused()),
+            Screen::Main | Screen::Search | Screen::Command | Screen::Confirm | Screen::Help => {
+                self.state.get_running_timer().is_some()
+            }
+            _ => false,
+        }
+    }
+
+    // Returns whether the event changed anything that is drawn
+    pub fn handle_app_event(&mut self, app_event: AppEvent) -> TuduResult<bool> {
+        match app_event {
+            AppEvent::Input(event::Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                let action = self.handle_key_stroke(key);
+                self.handle_action(action);
+                Ok(true)
+            }
+            // Mouse moves are reported too, they only matter when they map to an action
+            AppEvent::Input(event::Event::Mouse(mouse)) => {
+                let action = self.handle_mouse_event(mouse);
+                let is_changed = action != Action::Empty;
+                self.handle_action(action);
+                Ok(is_changed)
+            }
+            AppEvent::Input(_) => Ok(false),
+            AppEvent::Resize(width, height) => {
+                self.handle_action(Action::Resize(width, height));
+                Ok(true)
+            }
+            AppEvent::Tick(now) => {
+                let message = self.state.get_message().to_string();
+                self.handle_action(Action::Tick(now));
+                let is_clock_changed =
+                    self.is_clock_visible() && Local::now().timestamp() != self.last_drawn_second;
+                let is_loading = self.state.get_loading().is_some();
+                Ok(is_clock_changed || is_loading || self.state.get_message() != message)
+            }
+            AppEvent::Job(outcome) => {
+                self.handle_job_outcome(outcome);
+                Ok(true)
+            }
+            AppEvent::Action(action) => {
+                self.handle_action(action);
+                Ok(true)
+            }
+            AppEvent::Error(e) => Err(e),
+        }
+    }
+
+    pub fn init_controller(&mut self) -> TuduResult<()> {
+        let config = self.state.get_config().clone();
+        let data_path = config.get_data_path()?;
+        let is_first_time = !filesystem::file_exists(&data_path, &config.paths.database);
+
+        self.state.set_is_first_time(is_first_time);
+
+        filesystem::create_config_folder(&data_path)?;
+        self.handle_action(Action::OpenGreetingsScreen);
+        self.backups_path = Some(config.get_backups_path()?);
+        let database = config.paths.database.clone();
+        let integrity_errors = self.call(move |client| {
+            client.open_connection(data_path, &database)?;
+            client.create_user_table()?;
+            client.create_todos_table()?;
+            client.create_quarantine_table()?;
+            client.integrity_check()
+        })?;
+        // A damaged database should not be rotated over the healthy backups
+        self.is_database_damaged = !integrity_errors.is_empty();
+        if !is_first_time && !self.is_database_damaged {
+            self.handle_action(Action::CreateBackup);
+        }
+        self.handle_action(Action::Init);
+        Ok(())
+    }
+
+    pub fn exit(&mut self) -> TuduResult<()> {
+        self.worker.call(|client| client.close_connection())?;
+        Ok(())
+    }
+
+    pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> TuduResult<()> {
+        self.init_controller()?;
+
+        let area = terminal
+            .size()
+            .map_err(|e| TuduError::io("Could not get terminal size", e))?;
+        self.handle_action(Action::Resize(area.width, area.height));
+
+        self.event_loop.start_input();
+        self.event_loop.start_ticks();
+
+        let mut is_dirty = true;
+        while self.state.get_is_running() {
+            if self.state.get_is_bell_pending() {
+                self.state.set_is_bell_pending(false);
+                let mut stdout = stdout();
+                stdout
+                    .write_all(b"\x07")
+                    .and_then(|_| stdout.flush())
+                    .map_err(|e| TuduError::io("Could not ring the bell", e))?;
+            }
+            if is_dirty {
+                self.update_list_offset();
+                self.update_task_detail();
+                View::draw(terminal, &self.state)
+                    .map_err(|e| TuduError::io("Could not draw terminal", e))?;
+                self.last_drawn_second = Local::now().timestamp();
+            }
+            let app_event = self.event_loop.next()?;
+            is_dirty = self.handle_app_event(app_event)?;
+        }
+        self.event_loop.stop();
+        Ok(())
+    }
+}
+
+// Every task in list order, rows that do not decrypt are left out like in the list
+fn get_export_tasks(
+    client: &Client,
+    task_order: &[i32],
+    master_key: &str,
+) -> TuduResult<Vec<Task>> {
+    let lines: HashMap<i32, usize> = task_order
+        .iter()
+        .enumerate()
+        .map(|(line, id)| (*id, line))
+        .collect();
+    let mut task_list: Vec<Task> = client
+        .get_tasks_by_ids(task_order)?
+        .into_iter()
+        .filter_map(|task| {
+            let title = decrypt(&task.title, master_key).ok()?;
+            Some(Task { title, ..task })
+        })
+        .collect();
+    task_list.sort_by_key(|task| lines.get(&task.id).copied());
+    Ok(task_list)
+}
+
+fn export_tasks(
+    client: &mut Client,
+    task_order: &[i32],
+    master_key: &str,
+    passphrase: &str,
+    export_format: ExportFormat,
+    path: &Path,
+) -> TuduResult<(PathBuf, usize)> {
+    let mut skipped_count = 0;
+    match export_format {
+        ExportFormat::Csv => {
+            write_tasks_into_csv_file(&get_export_tasks(client, task_order, master_key)?, path)?
+        }
+        ExportFormat::Ics => {
+            write_tasks_into_ics_file(&get_export_tasks(client, task_order, master_key)?, path)?
+        }
+        ExportFormat::Markdown => write_tasks_into_markdown_file(
+            &get_export_tasks(client, task_order, master_key)?,
+            path,
+        )?,
+        ExportFormat::Bundle => {
+            let mut tables = client.dump_tables()?;
+            // Tasks that do not decrypt are left out with their rows in the other tables
+            let mut skipped_ids = HashSet::new();
+            for table in tables.iter_mut().filter(|table| table.name == "todos") {
+                let title_index = table
+                    .column_index("title")
+                    .ok_or_else(|| TuduError::validation("Missing title column"))?;
+                let id_index = table.column_index("id");
+                table.rows.retain_mut(|row| {
+                    if let Ok(title) = decrypt(&row[title_index], master_key) {
+                        row[title_index] = title;
+                        return true;
+                    }
+                    if let Some(id_index) = id_index {
+                        skipped_ids.insert(row[id_index].clone());
+                    }
+                    skipped_count += 1;
+                    false
+                });
+            }
+            for table in tables.iter_mut() {
+                if let Some(task_id_index) = table.column_index("task_id") {
+                    table
+                        .rows
+                        .retain(|row| !skipped_ids.contains(&row[task_id_index]));
+                }
+            }
+            // Events of tasks saved under an older master key keep their stored title
+            for table in tables
+                .iter_mut()
+                .filter(|table| table.name == "task_events")
+            {
+                if let Some(title_index) = table.column_index("title") {
+                    for row in table.rows.iter_mut() {
+                        if let Ok(title) = decrypt(&row[title_index], master_key) {
+                            row[title_index] = title;
+                        }
+                    }
+                }
+            }
+            write_bundle(path, passphrase, &tables)?
+        }
+    }
+    let absolute_path =
+        fs::canonicalize(path).map_err(|e| TuduError::io("Could not resolve export path", e))?;
+    Ok((absolute_path, skipped_count))
+}
+
+// Titles of the tasks in the list, to leave out the ones that are already there
+fn get_task_titles(
+    client: &Client,
+    task_order: &[i32],
+    master_key: &str,
+) -> TuduResult<HashSet<String>> {
+    Ok(client
+        .get_tasks_by_ids(task_order)?
+        .into_iter()
+        .filter_map(|task| decrypt(&task.title, master_key).ok())
+        .collect())
+}
+
+fn import_tasks(
+    client: &mut Client,
+    path: &Path,
+    master_key: &str,
+    task_order: &[i32],
+) -> TuduResult<Vec<i32>> {
+    let existing_titles = get_task_titles(client, task_order, master_key)?;
+    let mut imported_ids = Vec::new();
+    for task in read_tasks_from_ics_file(path)? {
+        if existing_titles.contains(&task.title) || task.title.is_empty() {
+            continue;
+        }
+        let title = encrypt(&task.title, master_key);
+        imported_ids.push(client.import_task(&Task { title, ..task })?);
+    }
+    Ok(imported_ids)
+}
+
+fn restore_bundle(
+    client: &mut Client,
+    path: &Path,
+    passphrase: &str,
+    master_key: &str,
+    task_order: &[i32],
+) -> TuduResult<RestoreSummary> {
+    let existing_titles = get_task_titles(client, task_order, master_key)?;
+    let tables = read_bundle(path, passphrase)?;
+    restore_tables(client, &tables, master_key, &existing_titles)
+}
+
+fn get_count_label(verb: &str, count: usize) -> String {
+    if count == 1 {
+        format!("{} 1 task", verb)
+    } else {
+        format!("{} {} tasks", verb, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use filesystem::file_exists;
+
+    use super::*;
+    use crate::config::{parse_config, Config};
+    use crate::constants::{ReportFormat, SortField};
+    use crate::filesystem::get_test_dir;
+    use crate::keymap::Keymap;
+    use crate::task::Task;
+    use crate::theme::Theme;
+    use chrono::NaiveDate;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_action_handler() {
+        let data_path = get_test_dir("controller");
+
+        let mut controller = Controller::new();
+        assert!(!controller.state.get_is_running());
+
+        let path = data_path.clone();
+        controller
+            .worker
+            .call(|client| client.open_connection(path, constants::DB_NAME))
+            .expect("Could not open connection");
+        controller
+            .worker
+            .call(|client| {
+                client.create_user_table()?;
+                client.create_todos_table()
+            })
+            .expect("Could not create tables");
+
+        // Greetings
+        controller.handle_action(Action::OpenGreetingsScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Greetings);
+
+        controller.handle_action(Action::InputMaskedChar('S'));
+        controller.handle_action(Action::InputMaskedChar('E'));
+        controller.handle_action(Action::InputMaskedChar('C'));
+        controller.handle_action(Action::InputMaskedChar('R'));
+        controller.handle_action(Action::InputMaskedChar('E'));
+        controller.handle_action(Action::InputMaskedChar('T'));
+        controller.handle_action(Action::InputMaskedChar('T'));
         assert_eq!(controller.state.get_master_key(), &String::from("SECRETT"));
 
-        controller.handle_action(Action::RemoveMaskedChar);
-        assert_eq!(controller.state.get_master_key(), &String::from("SECRET"));
+        controller.handle_action(Action::RemoveMaskedChar);
+        assert_eq!(controller.state.get_master_key(), &String::from("SECRET"));
+
+        // Add secret
+        controller.state.set_master_key(String::from("SECRET"));
+        controller.handle_action(Action::OpenMainScreen);
+        controller.handle_action(Action::Init);
+        assert!(controller.state.get_is_running());
+
+        // No item
+        controller.handle_action(Action::MenuDown);
+        assert_eq!(controller.state.get_line(), 0);
+
+        controller.handle_action(Action::GetTasks);
+        assert_eq!(controller.state.get_task_list_length(), 0);
+
+        controller.handle_action(Action::AddTask);
+        assert_eq!(controller.state.get_message(), "Please enter task title");
+
+        // 2 item
+        controller.handle_action(Action::InputChar('c'));
+        controller.handle_action(Action::AddTask);
+        controller.handle_action(Action::InputChar('c'));
+        controller.handle_action(Action::AddTask);
+        controller.handle_action(Action::GetTasks);
+        controller.handle_action(Action::MenuDown);
+        assert_eq!(controller.state.get_line(), 1);
+        controller.handle_action(Action::MenuUp);
+        assert_eq!(controller.state.get_line(), 0);
+        controller.handle_action(Action::ToggleTaskStatus);
+        assert_eq!(get_task(&controller, 0).status, "completed");
+
+        let csv_path = data_path.join("tudu.csv");
+        controller.handle_action(Action::Export(ExportFormat::Csv, csv_path.clone()));
+        finish_job(&mut controller);
+        let csv_file_exist = file_exists(&data_path, "tudu.csv");
+        assert!(csv_file_exist);
+        assert!(controller
+            .state
+            .get_message()
+            .starts_with("Saved csv file to /"));
+
+        // Export dialog
+        controller.handle_action(Action::OpenExportScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Export);
+        assert!(controller.state.get_input().ends_with(".csv"));
+        controller.handle_action(Action::SwitchExportFormat);
+        assert_eq!(controller.state.get_export_format(), ExportFormat::Ics);
+        assert!(controller.state.get_input().ends_with(".ics"));
+
+        controller.state.set_input("./test/i");
+        controller.handle_action(Action::CompletePath);
+        assert_eq!(controller.state.get_input(), "./test/ics/");
+        let ics_input = data_path.join(constants::ICS_NAME).display().to_string();
+        controller.state.set_input(&ics_input);
+        controller.handle_action(Action::ConfirmExport);
+        finish_job(&mut controller);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert!(controller.state.get_message().ends_with("/tudu.ics"));
+
+        // Exporting to the same file again asks first
+        controller.handle_action(Action::OpenExportScreen);
+        controller.state.set_input(&ics_input);
+        controller.handle_action(Action::ConfirmExport);
+        assert_eq!(controller.state.get_screen(), &Screen::Confirm);
+        assert_eq!(
+            controller
+                .state
+                .get_confirmation()
+                .map(|confirmation| confirmation.message.clone()),
+            Some(format!("{} already exists, overwrite it?", ics_input))
+        );
+        controller.handle_action(Action::AcceptConfirmation);
+        finish_job(&mut controller);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert!(controller.state.get_message().ends_with("/tudu.ics"));
+
+        controller.handle_action(Action::OpenExportScreen);
+        controller.state.set_input("./test/missing/tudu.ics");
+        controller.handle_action(Action::ConfirmExport);
+        assert_eq!(
+            controller.state.get_message(),
+            "Export directory does not exist"
+        );
+        controller.handle_action(Action::CancelExport);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert_eq!(controller.state.get_input(), "");
+        controller.state.set_export_format(ExportFormat::Csv);
+
+        // Bundle export and restore
+        let bundle_path = data_path.join("tudu.tudubak");
+        controller.state.set_passphrase(String::from("PASSPHRASE"));
+        controller.handle_action(Action::Export(ExportFormat::Bundle, bundle_path.clone()));
+        finish_job(&mut controller);
+        assert!(controller
+            .state
+            .get_message()
+            .starts_with("Saved tudubak file to /"));
+        controller.state.set_passphrase(String::new());
+
+        controller.handle_action(Action::OpenRestoreScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Restore);
+        controller
+            .state
+            .set_input(&bundle_path.display().to_string());
+        controller.handle_action(Action::ConfirmRestore);
+        finish_job(&mut controller);
+        assert_eq!(controller.state.get_screen(), &Screen::Passphrase);
+        assert_eq!(
+            controller.state.get_passphrase_purpose(),
+            PassphrasePurpose::Restore
+        );
+        for ch in "WRONG".chars() {
+            controller.handle_action(Action::InputPassphraseChar(ch));
+        }
+        controller.handle_action(Action::ConfirmPassphrase);
+        finish_job(&mut controller);
+        assert_eq!(controller.state.get_message(), "Bundle passphrase is wrong");
+        controller.state.set_passphrase(String::from("PASSPHRASE"));
+        controller.handle_action(Action::ConfirmPassphrase);
+        finish_job(&mut controller);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert!(controller
+            .state
+            .get_message()
+            .starts_with("Restored 0 tasks from"));
+        // Both tasks already exist, their rows are reported as skipped
+        assert!(controller
+            .state
+            .get_message()
+            .ends_with(", skipped 3 task_events rows, 2 todos rows"));
+        assert_eq!(
+            controller.state.get_notification().map(|n| n.severity),
+            Some(Severity::Warning)
+        );
+        assert_eq!(controller.state.get_task_list_length(), 2);
+
+        controller.handle_action(Action::OpenRestoreScreen);
+        controller.state.set_input("./test/bundle/missing.tudubak");
+        controller.handle_action(Action::ConfirmRestore);
+        assert_eq!(
+            controller.state.get_message(),
+            "Please enter an existing bundle path"
+        );
+        controller.handle_action(Action::CancelRestore);
+
+        // Backups
+        controller.backups_path = Some(data_path.join("backups"));
+        controller.handle_action(Action::OpenBackupsScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Backups);
+        assert_eq!(controller.state.get_backup_list().len(), 0);
+        controller.handle_action(Action::RestoreBackup);
+        assert_eq!(controller.state.get_message(), "Please select a backup");
+        controller.handle_action(Action::CreateBackup);
+        assert_eq!(controller.state.get_backup_list().len(), 0);
+        finish_job(&mut controller);
+        assert_eq!(controller.state.get_backup_list().len(), 1);
+        assert_eq!(controller.state.get_backup_list()[0].task_count, 2);
+        controller.handle_action(Action::BackupMenuDown);
+        assert_eq!(controller.state.get_backup_line(), 0);
+        controller.handle_action(Action::RestoreBackup);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        controller.handle_action(Action::GetTasks);
+        assert_eq!(controller.state.get_task_list_length(), 2);
+        controller.handle_action(Action::OpenBackupsScreen);
+        assert_eq!(controller.state.get_backup_list().len(), 2);
+        controller.handle_action(Action::BackupMenuDown);
+        assert_eq!(controller.state.get_backup_line(), 1);
+        controller.handle_action(Action::BackupMenuUp);
+        assert_eq!(controller.state.get_backup_line(), 0);
+        controller.handle_action(Action::CloseBackupsScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+
+        controller.handle_action(Action::OpenImportScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Import);
+        controller.state.set_input("./test/missing.ics");
+        controller.handle_action(Action::ConfirmImport);
+        assert_eq!(
+            controller.state.get_message(),
+            "Please enter an existing ics path"
+        );
+        controller
+            .state
+            .set_input(&data_path.join(constants::ICS_NAME).display().to_string());
+        controller.handle_action(Action::ConfirmImport);
+        finish_job(&mut controller);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert_eq!(controller.state.get_input(), "");
+        assert_eq!(
+            controller.state.get_message(),
+            "Imported 0 tasks from ics file"
+        );
+        assert_eq!(controller.state.get_task_list_length(), 2);
+
+        // Check remove char
+        controller.handle_action(Action::InputChar('c'));
+        controller.handle_action(Action::RemoveChar);
+        assert_eq!(controller.state.get_input(), &String::from(""));
+
+        // Check task title length
+        for _ in 0..41 {
+            controller.handle_action(Action::InputChar('c'));
+        }
+        controller.handle_action(Action::AddTask);
+        assert_eq!(
+            controller.state.get_message(),
+            &String::from("Task title cannot be longer than 40")
+        );
+
+        // Tasks added and completed from the command line
+        controller.state.set_input("");
+        controller.handle_action(Action::CreateTask(String::from("Buy milk")));
+        controller.handle_action(Action::GetTasks);
+        assert_eq!(controller.state.get_task_list_length(), 3);
+        assert_eq!(controller.state.get_input(), "");
+        controller.handle_action(Action::CompleteTask(2));
+        assert_eq!(controller.state.get_message(), "Completed Buy milk");
+        assert_eq!(controller.state.get_line(), 2);
+        assert_eq!(get_task(&controller, 2).status, "completed");
+        controller.handle_action(Action::Undo);
+        assert_eq!(controller.state.get_message(), "Undid: Completed 1 task");
+        assert_eq!(get_task(&controller, 2).status, "in-progress");
+        controller.handle_action(Action::RemoveTask);
+
+        // Remote all items
+        controller.handle_action(Action::RemoveTask);
+        controller.handle_action(Action::RemoveTask);
+        assert_eq!(controller.state.get_line(), 0);
+
+        // Add task
+        controller.state.set_error(String::from("ERROR"));
+        controller.handle_action(Action::OpenAddScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Add);
+        assert_eq!(controller.state.get_message(), "");
+
+        // Cancel add task
+        controller.handle_action(Action::CancelAddTask);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+
+        controller.state.set_error(String::from(""));
+        controller.state.set_master_key(String::from("MASTER_KEY"));
+        controller.handle_action(Action::AddSecret);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert_eq!(controller.state.get_message(), "");
+
+        controller.state.set_master_key(String::from(""));
+        controller.handle_action(Action::AddSecret);
+        assert_eq!(
+            controller.state.get_message(),
+            &String::from("Password is wrong")
+        );
+
+        controller
+            .worker
+            .call(|client| client.remove_user())
+            .expect("Could not remove user");
+        controller.handle_action(Action::Empty);
+        controller.handle_action(Action::Exit);
+    }
+
+    #[test]
+    fn test_key_stroke_handler() {
+        let mut controller = Controller::new();
+
+        // Main screen
+        let mut action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('a')));
+        assert_eq!(action, Action::OpenAddScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('x')));
+        assert_eq!(action, Action::RemoveTask);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Up));
+        assert_eq!(action, Action::MenuUp);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Down));
+        assert_eq!(action, Action::MenuDown);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(
+            action,
+            Action::RequestConfirmation(String::from("Quit tudu?"), Box::new(Action::Exit))
+        );
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::ToggleTaskStatus);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('e')));
+        assert_eq!(action, Action::OpenExportScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('i')));
+        assert_eq!(action, Action::OpenImportScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('z')));
+        assert_eq!(action, Action::Empty);
+
+        // Add screen
+        controller.handle_action(Action::OpenAddScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::CancelAddTask);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::AddTask);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('s')));
+        assert_eq!(action, Action::InputChar('s'));
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(action, Action::RemoveChar);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Home));
+        assert_eq!(action, Action::Empty);
+
+        // Export screen
+        controller.handle_action(Action::OpenExportScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::CancelExport);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::ConfirmExport);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(action, Action::CompletePath);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Down));
+        assert_eq!(action, Action::SwitchExportFormat);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('s')));
+        assert_eq!(action, Action::InputChar('s'));
+
+        // Restore screen
+        controller.handle_action(Action::OpenRestoreScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::CancelRestore);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::ConfirmRestore);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Tab));
+        assert_eq!(action, Action::CompletePath);
+
+        // Passphrase screen
+        controller.state.set_screen(Screen::Passphrase);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::CancelPassphrase);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::ConfirmPassphrase);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('s')));
+        assert_eq!(action, Action::InputPassphraseChar('s'));
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(action, Action::RemovePassphraseChar);
+
+        // Backups screen
+        controller.state.set_screen(Screen::Backups);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::CloseBackupsScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::RestoreBackup);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Up));
+        assert_eq!(action, Action::BackupMenuUp);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Down));
+        assert_eq!(action, Action::BackupMenuDown);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!(action, Action::CreateBackup);
+
+        // Configured keybindings, modifiers are part of the key
+        let config =
+            parse_config("[keybindings.main]\nadd = \"ctrl+n\"").expect("Could not parse config");
+        controller.state.set_config(config);
+        controller.state.set_screen(Screen::Main);
+        action =
+            controller.handle_key_stroke(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        assert_eq!(action, Action::OpenAddScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('a')));
+        assert_eq!(action, Action::Empty);
+        controller.state.set_screen(Screen::Add);
+        action =
+            controller.handle_key_stroke(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL));
+        assert_eq!(action, Action::Empty);
+        action =
+            controller.handle_key_stroke(KeyEvent::new(KeyCode::Char('N'), KeyModifiers::SHIFT));
+        assert_eq!(action, Action::InputChar('N'));
+        controller.state.set_config(Config::default());
+
+        // Messages screen
+        controller.state.set_screen(Screen::Main);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('m')));
+        assert_eq!(action, Action::OpenMessagesScreen);
+        controller.state.set_screen(Screen::Messages);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::CloseMessagesScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Up));
+        assert_eq!(action, Action::MessagesUp);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Down));
+        assert_eq!(action, Action::MessagesDown);
+
+        // Greetings screen
+        controller.handle_action(Action::OpenGreetingsScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::Exit);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('s')));
+        assert_eq!(action, Action::InputMaskedChar('s'));
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Backspace));
+        assert_eq!(action, Action::RemoveMaskedChar);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::OpenMainScreen);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Home));
+        assert_eq!(action, Action::Empty);
+
+        controller.state.set_is_first_time(true);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Enter));
+        assert_eq!(action, Action::AddSecret);
+    }
+
+    #[test]
+    fn test_common_error_tests() {
+        let mut controller = Controller::new();
+
+        controller.handle_action(Action::GetTasks);
+        assert_ne!(controller.state.get_message(), "");
+
+        controller.state.set_input("TEST TASK TITLE");
+        controller.handle_action(Action::AddTask);
+        assert_ne!(controller.state.get_message(), "");
+
+        controller.handle_action(Action::CheckSecret);
+        assert_eq!(controller.state.get_message(), "Could not get user");
+    }
+
+    #[test]
+    fn test_undecryptable_tasks() {
+        let mut controller = Controller::new();
+        controller
+            .worker
+            .call(|client| client.open_connection(PathBuf::new(), ":memory:"))
+            .expect("Could not open connection");
+        controller
+            .worker
+            .call(|client| client.create_todos_table())
+            .expect("Could not create todos table");
+        controller.state.set_master_key(String::from("SECRET"));
+
+        controller
+            .worker
+            .call(|client| client.create_task(encrypt("Readable", "SECRET")))
+            .expect("Could not create task");
+        controller
+            .worker
+            .call(|client| client.create_task(encrypt("Unreadable", "OTHER")))
+            .expect("Could not create task");
+
+        controller.handle_action(Action::GetTasks);
+        assert_eq!(controller.state.get_task_list_length(), 1);
+        assert_eq!(
+            controller.state.get_message(),
+            "1 tasks could not be decrypted, run tudu doctor to repair"
+        );
+        assert_eq!(
+            controller
+                .state
+                .get_notification()
+                .map(|notification| notification.severity),
+            Some(Severity::Warning)
+        );
+
+        // The bundle leaves the unreadable task out instead of failing
+        let bundle_path = get_test_dir("controller_undecryptable").join("tudu.tudubak");
+        controller.handle_action(Action::Export(ExportFormat::Bundle, bundle_path.clone()));
+        finish_job(&mut controller);
+        assert!(controller
+            .state
+            .get_message()
+            .ends_with("tudu.tudubak, skipped 1 todos row"));
+        let tables = read_bundle(&bundle_path, "SECRET").expect("Could not read bundle");
+        let todos = tables
+            .iter()
+            .find(|table| table.name == "todos")
+            .expect("Bundle has no todos");
+        assert_eq!(todos.rows.len(), 1);
+        assert!(tables
+            .iter()
+            .filter(|table| table.name == "task_events")
+            .all(|table| table.rows.len() == 1));
+    }
+
+    #[test]
+    fn test_task_window() {
+        let mut controller = get_memory_controller(&[]);
+        let task_count = TASK_PAGE_SIZE + 3;
+        controller
+            .worker
+            .call(move |client| {
+                for index in 0..task_count {
+                    client.create_task(encrypt(&format!("Task {}", index), "KEY"))?;
+                }
+                Ok(())
+            })
+            .expect("Could not create tasks");
+
+        // Every task is listed, only the rows around the viewport are decrypted
+        controller.handle_action(Action::GetTasks);
+        let window_size = (controller.state.get_page_size() + TASK_WINDOW_MARGIN) as usize;
+        assert_eq!(controller.state.get_task_list_length(), task_count as i32);
+        assert_eq!(controller.state.get_loaded_tasks().len(), window_size);
+        assert_eq!(controller.title_cache.len(), window_size);
+        controller.handle_action(Action::MenuLast);
+        assert_eq!(get_task(&controller, 502).title, "Task 502");
+        assert!(controller.state.get_task(0).is_none());
+
+        // Search decrypts a page at a time until a title matches
+        controller.state.set_search_query("task 250");
+        controller.handle_action(Action::SearchNext);
+        assert_eq!(controller.state.get_line(), 250);
+        assert_eq!(controller.title_cache.len(), task_count);
+
+        // Sorting by title goes through the pages, a reload drops the page still queued
+        controller.handle_action(Action::SortTasks(SortField::Title));
+        assert!(controller.state.get_loading().is_some());
+        let stale_page = controller
+            .event_loop
+            .next()
+            .expect("Could not get next page");
+        assert!(matches!(
+            stale_page,
+            AppEvent::Action(Action::LoadTaskPage(_, id)) if id == 500
+        ));
+        controller.handle_action(Action::GetTasks);
+        assert!(controller.state.get_loading().is_none());
+        controller
+            .handle_app_event(stale_page)
+            .expect("Could not handle stale page");
+        assert_eq!(controller.state.get_sort_field(), SortField::Created);
+        assert_eq!(get_task(&controller, 250).title, "Task 250");
+
+        controller.handle_action(Action::SortTasks(SortField::Title));
+        let next_page = controller
+            .event_loop
+            .next()
+            .expect("Could not get next page");
+        controller
+            .handle_app_event(next_page)
+            .expect("Could not load next page");
+        assert!(controller.state.get_loading().is_none());
+        assert_eq!(controller.state.get_sort_field(), SortField::Title);
+        assert_eq!(
+            get_task(&controller, controller.state.get_line()).title,
+            "Task 250"
+        );
+        controller.handle_action(Action::MenuFirst);
+        let titles: Vec<String> = (0..4)
+            .map(|line| get_task(&controller, line).title)
+            .collect();
+        assert_eq!(titles, ["Task 0", "Task 1", "Task 10", "Task 100"]);
+
+        // A change bumps the version so the row is decrypted again
+        controller.handle_action(Action::MenuDown);
+        controller.handle_action(Action::ToggleTaskStatus);
+        assert_eq!(get_task(&controller, 1).title, "Task 1");
+        assert_eq!(get_task(&controller, 1).version, 2);
+        controller.handle_action(Action::RemoveTask);
+        assert_eq!(controller.title_cache.len(), task_count - 1);
+        assert_eq!(get_task(&controller, 1).title, "Task 10");
+    }
+
+    #[test]
+    fn test_notifications() {
+        let mut controller = Controller::new();
+
+        controller.state.set_error(String::from("first"));
+        controller
+            .state
+            .set_notification(Severity::Success, String::from("second"));
+
+        // Fresh notifications survive the tick
+        controller.handle_action(Action::ExpireNotification);
+        assert_eq!(controller.state.get_message(), "second");
+
+        if let Some(notification) = controller.state.notification.as_mut() {
+            notification.shown_at -= Severity::Success.timeout();
+        }
+        controller.handle_action(Action::ExpireNotification);
+        assert_eq!(controller.state.get_message(), "");
+
+        controller.handle_action(Action::OpenMessagesScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Messages);
+        assert_eq!(controller.state.get_notification_history().len(), 2);
+        assert_eq!(
+            controller.state.get_notification_history()[0].message,
+            "second"
+        );
+
+        controller.handle_action(Action::MessagesDown);
+        controller.handle_action(Action::MessagesDown);
+        assert_eq!(controller.state.get_history_line(), 1);
+        controller.handle_action(Action::MessagesUp);
+        assert_eq!(controller.state.get_history_line(), 0);
+
+        controller.handle_action(Action::CloseMessagesScreen);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+    }
+
+    #[test]
+    fn test_vim_navigation() {
+        let mut controller = get_memory_controller(&["milk", "bread", "Milkshake", "eggs"]);
+        let config = parse_config("keymap_preset = \"vim\"").expect("Could not parse config");
+        controller.state.set_config(config);
+
+        let press = |controller: &mut Controller, keys: &str| {
+            for ch in keys.chars() {
+                let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char(ch)));
+                controller.handle_action(action);
+            }
+        };
+
+        press(&mut controller, "2j");
+        assert_eq!(controller.state.get_line(), 2);
+        press(&mut controller, "gg");
+        assert_eq!(controller.state.get_line(), 0);
+        press(&mut controller, "G");
+        assert_eq!(controller.state.get_line(), 3);
+        press(&mut controller, "2G");
+        assert_eq!(controller.state.get_line(), 1);
+        press(&mut controller, "9k");
+        assert_eq!(controller.state.get_line(), 0);
+
+        press(&mut controller, "3");
+        assert_eq!(controller.state.get_pending_keys(), "3");
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::Empty);
+        assert_eq!(controller.state.get_pending_keys(), "");
+
+        // Search is case insensitive and wraps around
+        press(&mut controller, "/milk");
+        assert_eq!(controller.state.get_screen(), &Screen::Search);
+        controller.handle_action(Action::ConfirmSearch);
+        assert_eq!(controller.state.get_line(), 2);
+        press(&mut controller, "n");
+        assert_eq!(controller.state.get_line(), 0);
+        press(&mut controller, "N");
+        assert_eq!(controller.state.get_line(), 2);
+        press(&mut controller, "/tea");
+        controller.handle_action(Action::ConfirmSearch);
+        assert_eq!(controller.state.get_line(), 2);
+        assert_eq!(controller.state.get_message(), "Pattern not found: tea");
+
+        // Command line
+        press(&mut controller, ":4");
+        assert_eq!(controller.state.get_screen(), &Screen::Command);
+        controller.handle_action(Action::ExecuteCommand);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert_eq!(controller.state.get_line(), 3);
+        press(&mut controller, ":nope");
+        controller.handle_action(Action::ExecuteCommand);
+        assert_eq!(controller.state.get_message(), "Not a command: nope");
+
+        // Editing fills the add screen with the title, changing clears it
+        press(&mut controller, "i");
+        assert_eq!(controller.state.get_screen(), &Screen::Add);
+        assert_eq!(controller.state.get_input(), "eggs");
+        assert_eq!(controller.state.get_editing_task_id(), Some(4));
+        controller.handle_action(Action::CancelAddTask);
+        assert_eq!(controller.state.get_editing_task_id(), None);
+        assert_eq!(controller.state.get_input(), "");
+        press(&mut controller, "cw");
+        assert_eq!(controller.state.get_screen(), &Screen::Add);
+        assert_eq!(controller.state.get_input(), "");
+        assert_eq!(controller.state.get_editing_task_id(), Some(4));
+        controller.handle_action(Action::OpenAddScreen);
+        assert_eq!(controller.state.get_editing_task_id(), None);
+    }
+
+    #[test]
+    fn test_command_line() {
+        let mut controller = get_memory_controller(&["milk", "Bread"]);
+        controller.handle_action(Action::ToggleTaskStatus);
 
-        // Add secret
-        controller.state.set_master_key(String::from("SECRET"));
-        controller.handle_action(Action::OpenMainScreen);
-        controller.handle_action(Action::Init);
-        assert_eq!(controller.state.get_is_running(), true);
+        let run = |controller: &mut Controller, command: &str| {
+            controller.handle_action(Action::OpenCommandLine);
+            controller.state.set_input(command);
+            controller.handle_action(Action::ExecuteCommand);
+        };
 
-        // No item
-        controller.handle_action(Action::MenuDown);
-        assert_eq!(controller.state.get_line(), 0);
+        // The selection stays on the same task after sorting
+        run(&mut controller, "sort title");
+        assert_eq!(controller.state.get_sort_field(), SortField::Title);
+        assert_eq!(get_task(&controller, 0).title, "Bread");
+        assert_eq!(controller.state.get_line(), 1);
+        assert_eq!(controller.state.get_message(), "Sorted tasks by title");
 
-        controller.handle_action(Action::GetTasks);
-        let tasks = controller.state.get_task_list();
-        assert_eq!(tasks.len(), 0);
+        run(&mut controller, "done 2");
+        assert_eq!(controller.state.get_message(), "Task 2 is already done");
+        run(&mut controller, "done 5");
+        assert_eq!(controller.state.get_message(), "There is no task 5");
+        run(&mut controller, "archive work");
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert_eq!(controller.state.get_message(), "Not a command: archive");
 
-        controller.handle_action(Action::AddTask);
-        assert_eq!(controller.state.get_error(), "Please enter task title");
+        // History and completion
+        controller.handle_action(Action::OpenCommandLine);
+        controller.handle_action(Action::CommandHistoryUp);
+        assert_eq!(controller.state.get_input(), "archive work");
+        controller.handle_action(Action::CommandHistoryUp);
+        controller.handle_action(Action::CommandHistoryUp);
+        controller.handle_action(Action::CommandHistoryUp);
+        assert_eq!(controller.state.get_input(), "sort title");
+        controller.handle_action(Action::CommandHistoryDown);
+        assert_eq!(controller.state.get_input(), "done 2");
+        controller.handle_action(Action::CommandHistoryDown);
+        controller.handle_action(Action::CommandHistoryDown);
+        controller.handle_action(Action::CommandHistoryDown);
+        assert_eq!(controller.state.get_input(), "");
 
-        // 2 item
-        controller.handle_action(Action::InputChar('c'));
-        controller.handle_action(Action::AddTask);
-        controller.handle_action(Action::InputChar('c'));
-        controller.handle_action(Action::AddTask);
-        controller.handle_action(Action::GetTasks);
-        controller.handle_action(Action::MenuDown);
-        assert_eq!(controller.state.get_line(), 1);
-        controller.handle_action(Action::MenuUp);
-        assert_eq!(controller.state.get_line(), 0);
-        controller.handle_action(Action::ToggleTaskStatus);
+        controller.state.set_input("so");
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Tab));
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_input(), "sort ");
+        controller.handle_action(Action::CancelCommand);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+    }
+
+    #[test]
+    fn test_help_overlay() {
+        let mut controller = Controller::new();
+        let keymap = Keymap::default();
+
+        controller.state.set_screen(Screen::Backups);
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('?')));
+        assert_eq!(action, Action::OpenHelp);
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Help);
         assert_eq!(
-            controller
-                .state
-                .get_task_list()
-                .get(0)
-                .expect("nope")
-                .status,
-            "completed"
+            controller.state.get_help_line(),
+            keymap.get_help_offset(&Screen::Backups) as i32
         );
 
-        let mut csv_path = PathBuf::new();
-        csv_path.push("./test/csv/");
-        controller.handle_action(Action::ExportCSV(Some(csv_path.clone())));
-        let csv_file_exist = file_exists(&csv_path, constants::CSV_NAME);
-        assert_eq!(csv_file_exist, true);
+        controller.handle_action(Action::ScrollHelp(1));
+        assert_eq!(
+            controller.state.get_help_line(),
+            keymap.get_help_offset(&Screen::Backups) as i32 + 1
+        );
+        controller.handle_action(Action::ScrollHelp(-1000));
+        assert_eq!(controller.state.get_help_line(), 0);
+        controller.handle_action(Action::ScrollHelp(1000));
+        assert_eq!(
+            controller.state.get_help_line(),
+            keymap.get_help_length() as i32 - 1
+        );
 
-        // Check remove char
-        controller.handle_action(Action::InputChar('c'));
-        controller.handle_action(Action::RemoveChar);
-        assert_eq!(controller.state.get_input(), &String::from(""));
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('?')));
+        assert_eq!(action, Action::CloseHelp);
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Backups);
 
-        // Check task title length
-        for _ in 0..41 {
-            controller.handle_action(Action::InputChar('c'));
-        }
-        controller.handle_action(Action::AddTask);
+        // `?` is typed on input screens, F1 still opens help
+        controller.state.set_screen(Screen::Add);
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('?')));
+        assert_eq!(action, Action::InputChar('?'));
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::F(1)));
+        controller.handle_action(action);
+        controller.handle_action(Action::OpenHelp);
+        assert_eq!(controller.state.get_previous_screen(), &Screen::Add);
+        controller.handle_action(Action::CloseHelp);
+        assert_eq!(controller.state.get_screen(), &Screen::Add);
+    }
+
+    #[test]
+    fn test_confirmations() {
+        let mut controller = Controller::new();
+        controller.state.set_task_list(vec![
+            Task {
+                id: 1,
+                title: String::from("milk"),
+                status: String::from("in-progress"),
+                ..Default::default()
+            },
+            Task {
+                id: 2,
+                title: String::from("bread"),
+                status: String::from("in-progress"),
+                ..Default::default()
+            },
+        ]);
+
+        let mut action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('x')));
         assert_eq!(
-            controller.state.get_error(),
-            &String::from("Task title cannot be longer than 40")
+            action,
+            Action::RequestConfirmation(
+                String::from("Remove \"milk\"?"),
+                Box::new(Action::RemoveTask)
+            )
         );
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Confirm);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('n')));
+        assert_eq!(action, Action::RejectConfirmation);
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert_eq!(controller.state.get_confirmation(), None);
+        assert_eq!(controller.state.get_task_list_length(), 2);
 
-        // Remote all items
-        controller.handle_action(Action::RemoveTask);
-        controller.handle_action(Action::RemoveTask);
+        controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('5')));
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('x')));
+        assert!(matches!(
+            action,
+            Action::RequestConfirmation(ref message, _) if message == "Remove 2 tasks?"
+        ));
+
+        controller.state.set_is_running(true);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        controller.handle_action(action);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('y')));
+        assert_eq!(action, Action::AcceptConfirmation);
+        controller.handle_action(action);
+        assert!(!controller.state.get_is_running());
+
+        // Confirmations can be turned off one by one
+        let config = parse_config("[confirmations]\nexit = false\nremove_task = false")
+            .expect("Could not parse config");
+        controller.state.set_config(config);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(action, Action::Exit);
+        action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('x')));
+        assert_eq!(action, Action::RemoveTask);
+    }
+
+    #[test]
+    fn test_list_viewport() {
+        let mut controller = Controller::new();
+        controller.state.set_task_list(
+            (0..30)
+                .map(|id| Task {
+                    id,
+                    title: format!("task {}", id),
+                    status: String::from("in-progress"),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        controller.state.set_page_size(10);
+
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::PageDown));
+        controller.handle_action(action);
+        controller.update_list_offset();
+        assert_eq!(controller.state.get_line(), 10);
+        assert_eq!(controller.state.get_list_offset(), 1);
+
+        controller.handle_action(Action::MenuLast);
+        controller.update_list_offset();
+        assert_eq!(controller.state.get_line(), 29);
+        assert_eq!(controller.state.get_list_offset(), 20);
+
+        // Moving inside the visible page does not scroll
+        controller.handle_action(Action::MenuUp);
+        controller.update_list_offset();
+        assert_eq!(controller.state.get_list_offset(), 20);
+
+        controller.handle_action(Action::MenuPageUp);
+        controller.update_list_offset();
+        assert_eq!(controller.state.get_line(), 18);
+        assert_eq!(controller.state.get_list_offset(), 18);
+
+        controller.handle_action(Action::MenuPageUp);
+        controller.handle_action(Action::MenuPageUp);
+        controller.update_list_offset();
         assert_eq!(controller.state.get_line(), 0);
+        assert_eq!(controller.state.get_list_offset(), 0);
 
-        // Add task
-        controller.state.set_error(String::from("ERROR"));
-        controller.handle_action(Action::OpenAddScreen);
-        assert_eq!(controller.state.get_screen(), &Screen::Add);
-        assert_eq!(controller.state.get_error(), &String::from(""));
+        // A shorter list pulls the offset back
+        controller.state.set_list_offset(25);
+        controller.update_list_offset();
+        assert_eq!(controller.state.get_list_offset(), 0);
 
-        // Cancel add task
-        controller.handle_action(Action::CancelAddTask);
-        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        // A shorter terminal scrolls the list so the selection stays visible
+        controller.handle_action(Action::MenuGoTo(15));
+        controller.handle_action(Action::Resize(120, 10));
+        assert_eq!(controller.state.get_page_size(), 5);
+        assert_eq!(controller.state.get_list_offset(), 11);
+        assert_eq!(controller.area, Rect::new(0, 0, 120, 10));
+    }
 
-        controller.state.set_error(String::from(""));
-        controller.state.set_master_key(String::from("MASTER_KEY"));
+    // Tests that change tasks get their own database so they can run in parallel
+    // Long operations report back through the event loop, tests wait for them here
+    fn finish_job(controller: &mut Controller) {
+        if controller.state.get_loading().is_some() {
+            let app_event = controller
+                .event_loop
+                .next()
+                .expect("Could not get job outcome");
+            controller
+                .handle_app_event(app_event)
+                .expect("Could not handle job outcome");
+        }
+    }
+
+    fn get_task(controller: &Controller, line: i32) -> Task {
+        controller
+            .state
+            .get_task(line)
+            .cloned()
+            .expect("Could not get task")
+    }
+
+    fn get_memory_controller(titles: &[&str]) -> Controller {
+        let mut controller = Controller::new();
+        controller
+            .worker
+            .call(|client| {
+                client.open_connection(PathBuf::new(), ":memory:")?;
+                client.create_user_table()?;
+                client.create_todos_table()
+            })
+            .expect("Could not open connection");
+        controller.state.set_master_key(String::from("KEY"));
+        controller.handle_action(Action::Resize(80, 24));
         controller.handle_action(Action::AddSecret);
+        for title in titles {
+            controller.handle_action(Action::CreateTask(String::from(*title)));
+        }
+        controller
+    }
+
+    #[test]
+    fn test_bulk_operations() {
+        let mut controller = get_memory_controller(&["apples", "bread", "bananas", "eggs"]);
+        assert_eq!(controller.state.get_task_list_length(), 4);
+
+        let press = |controller: &mut Controller, code: KeyCode| {
+            let action = controller.handle_key_stroke(KeyEvent::from(code));
+            controller.handle_action(action);
+        };
+
+        // Marked tasks are completed together
+        press(&mut controller, KeyCode::Char(' '));
+        press(&mut controller, KeyCode::Down);
+        press(&mut controller, KeyCode::Down);
+        press(&mut controller, KeyCode::Char(' '));
+        assert_eq!(controller.state.get_selected_ids().len(), 2);
+        press(&mut controller, KeyCode::Enter);
+        let statuses: Vec<String> = (0..4)
+            .map(|line| get_task(&controller, line).status)
+            .collect();
+        assert_eq!(
+            statuses,
+            vec!["completed", "in-progress", "completed", "in-progress"]
+        );
+        assert!(!controller.state.has_selection());
+
+        // A visual range is removed after one confirmation
+        press(&mut controller, KeyCode::Up);
+        press(&mut controller, KeyCode::Char('v'));
+        press(&mut controller, KeyCode::Down);
+        press(&mut controller, KeyCode::Down);
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('x')));
+        assert!(matches!(
+            action,
+            Action::RequestConfirmation(ref message, _) if message == "Remove 3 selected tasks?"
+        ));
+        controller.handle_action(action);
+        press(&mut controller, KeyCode::Char('y'));
+        assert_eq!(controller.state.get_task_list_length(), 1);
+        assert_eq!(controller.state.get_line(), 0);
+
+        // Each bulk change is undone in one step
+        press(&mut controller, KeyCode::Char('u'));
+        assert_eq!(controller.state.get_message(), "Undid: Removed 3 tasks");
+        assert_eq!(controller.state.get_task_list_length(), 4);
+        assert_eq!(get_task(&controller, 2).status, "completed");
+        press(&mut controller, KeyCode::Char('u'));
+        assert_eq!(controller.state.get_message(), "Undid: Completed 2 tasks");
+        assert!((0..4).all(|line| get_task(&controller, line).status == "in-progress"));
+        press(&mut controller, KeyCode::Char('u'));
+        assert_eq!(controller.state.get_message(), "Nothing to undo");
+
+        // Tasks matching the search are marked, esc clears the marks
+        controller.state.set_search_query("an");
+        press(&mut controller, KeyCode::Char('*'));
+        assert_eq!(
+            controller.state.get_message(),
+            "Selected 1 tasks matching an"
+        );
+        controller.state.set_search_query("a");
+        press(&mut controller, KeyCode::Char('*'));
+        assert_eq!(controller.state.get_selected_ids().len(), 3);
+        press(&mut controller, KeyCode::Esc);
+        assert!(!controller.state.has_selection());
         assert_eq!(controller.state.get_screen(), &Screen::Main);
-        assert_eq!(controller.state.get_error(), &String::from(""));
 
-        controller.state.set_master_key(String::from(""));
-        controller.handle_action(Action::AddSecret);
+        // Marked tasks are moved, tagged and given a priority together
+        controller.go_to_line(0);
+        press(&mut controller, KeyCode::Char(' '));
+        press(&mut controller, KeyCode::Down);
+        press(&mut controller, KeyCode::Char(' '));
+        press(&mut controller, KeyCode::Char('M'));
+        assert_eq!(controller.state.get_screen(), &Screen::Command);
+        assert_eq!(controller.state.get_input(), "move ");
+        controller.state.set_input("move work");
+        controller.handle_action(Action::ExecuteCommand);
+        assert!(!controller.state.has_selection());
+        assert_eq!(get_task(&controller, 0).list.as_deref(), Some("work"));
+        assert_eq!(get_task(&controller, 1).list.as_deref(), Some("work"));
+        assert_eq!(get_task(&controller, 2).list, None);
+
+        press(&mut controller, KeyCode::Char('v'));
+        press(&mut controller, KeyCode::Down);
+        controller.handle_action(Action::TagTasks(String::from("urgent")));
+        controller.go_to_line(2);
+        controller.handle_action(Action::TagTasks(String::from("urgent")));
+        assert_eq!(get_task(&controller, 1).tags, vec![String::from("urgent")]);
+        assert_eq!(get_task(&controller, 2).tags, vec![String::from("urgent")]);
+        controller.handle_action(Action::TagTasks(String::from("two words")));
         assert_eq!(
-            controller.state.get_error(),
-            &String::from("Password is wrong")
+            controller.state.get_message(),
+            "Tags cannot be empty or contain commas or spaces"
+        );
+
+        controller.go_to_line(0);
+        press(&mut controller, KeyCode::Char('v'));
+        press(&mut controller, KeyCode::Down);
+        controller.handle_action(Action::SetTaskPriority(Some(1)));
+        assert_eq!(get_task(&controller, 0).priority, Some(1));
+        assert_eq!(get_task(&controller, 1).priority, Some(1));
+        press(&mut controller, KeyCode::Char('u'));
+        assert_eq!(
+            controller.state.get_message(),
+            "Undid: Set the priority of 2 tasks"
+        );
+        assert_eq!(get_task(&controller, 0).priority, None);
+        assert_eq!(get_task(&controller, 1).tags, vec![String::from("urgent")]);
+
+        // Filters from the command line mark what they match
+        let execute = |controller: &mut Controller, command: &str| {
+            controller.handle_action(Action::OpenCommandLine);
+            controller.state.set_input(command);
+            controller.handle_action(Action::ExecuteCommand);
+        };
+        execute(&mut controller, "filter tag:urgent");
+        assert_eq!(
+            controller.state.get_message(),
+            "Selected 2 tasks matching tag:urgent"
+        );
+        assert!(controller.state.is_marked(1) && controller.state.is_marked(2));
+        controller.handle_action(Action::ClearSelection);
+        execute(&mut controller, "list work");
+        assert_eq!(
+            controller.state.get_message(),
+            "Selected 2 tasks matching list:work"
+        );
+        controller.handle_action(Action::ClearSelection);
+        execute(&mut controller, "filter egg");
+        assert_eq!(
+            controller.state.get_message(),
+            "Selected 1 tasks matching egg"
         );
+        assert!(controller.state.is_marked(3));
 
+        // Tasks with a due date come first
         controller
-            .client
-            .remove_user()
-            .expect("Could not remove user");
-        controller.handle_action(Action::Empty);
-        controller.handle_action(Action::Exit);
+            .worker
+            .call(|client| {
+                client.restore_tasks(&[Task {
+                    due: NaiveDate::from_ymd_opt(2024, 10, 19)
+                        .and_then(|date| date.and_hms_opt(9, 0, 0)),
+                    ..client.get_tasks_by_ids(&[4])?[0].clone()
+                }])
+            })
+            .expect("Could not set due date");
+        execute(&mut controller, "sort due");
+        assert_eq!(controller.state.get_sort_field(), SortField::Due);
+        assert_eq!(controller.state.get_task_id(0), Some(4));
+        assert_eq!(controller.state.get_task_id(1), Some(1));
     }
 
     #[test]
-    fn test_key_stroke_handler() {
+    fn test_theme_switching() {
         let mut controller = Controller::new();
+        let config = parse_config("theme = \"monochrome\"\n[themes.paper]\ntext = \"black\"")
+            .expect("Could not parse config");
+        controller.state.set_config(config);
+        assert_eq!(controller.state.get_theme(), &Theme::monochrome());
 
-        // Main screen
-        let mut action = controller.handle_key_stroke(KeyCode::Char('a'));
-        assert_eq!(action, Action::OpenAddScreen);
-        action = controller.handle_key_stroke(KeyCode::Char('x'));
-        assert_eq!(action, Action::RemoveTask);
-        action = controller.handle_key_stroke(KeyCode::Up);
-        assert_eq!(action, Action::MenuUp);
-        action = controller.handle_key_stroke(KeyCode::Down);
-        assert_eq!(action, Action::MenuDown);
-        action = controller.handle_key_stroke(KeyCode::Esc);
-        assert_eq!(action, Action::Exit);
-        action = controller.handle_key_stroke(KeyCode::Enter);
-        assert_eq!(action, Action::ToggleTaskStatus);
-        action = controller.handle_key_stroke(KeyCode::Char('z'));
-        assert_eq!(action, Action::Empty);
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('t')));
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_theme_name(), "paper");
+        assert_eq!(controller.state.get_message(), "Theme: paper");
+        controller.handle_action(Action::SwitchTheme);
+        assert_eq!(controller.state.get_theme_name(), "dark");
 
-        // Add screen
-        controller.handle_action(Action::OpenAddScreen);
-        action = controller.handle_key_stroke(KeyCode::Esc);
-        assert_eq!(action, Action::CancelAddTask);
-        action = controller.handle_key_stroke(KeyCode::Enter);
-        assert_eq!(action, Action::AddTask);
-        action = controller.handle_key_stroke(KeyCode::Char('s'));
-        assert_eq!(action, Action::InputChar('s'));
-        action = controller.handle_key_stroke(KeyCode::Backspace);
-        assert_eq!(action, Action::RemoveChar);
-        action = controller.handle_key_stroke(KeyCode::Home);
-        assert_eq!(action, Action::Empty);
+        controller.handle_action(Action::SetTheme(String::from("high-contrast")));
+        assert_eq!(controller.state.get_theme(), &Theme::high_contrast());
+        controller.handle_action(Action::SetTheme(String::from("neon")));
+        assert_eq!(controller.state.get_theme_name(), "high-contrast");
+        assert_eq!(
+            controller.state.get_message(),
+            "Unknown theme neon, use dark, light, high-contrast, monochrome, paper"
+        );
+    }
 
-        // Greetings screen
-        controller.handle_action(Action::OpenGreetingsScreen);
-        action = controller.handle_key_stroke(KeyCode::Esc);
-        assert_eq!(action, Action::Exit);
-        action = controller.handle_key_stroke(KeyCode::Char('s'));
-        assert_eq!(action, Action::InputMaskedChar('s'));
-        action = controller.handle_key_stroke(KeyCode::Backspace);
-        assert_eq!(action, Action::RemoveMaskedChar);
-        action = controller.handle_key_stroke(KeyCode::Enter);
-        assert_eq!(action, Action::OpenMainScreen);
-        action = controller.handle_key_stroke(KeyCode::Home);
-        assert_eq!(action, Action::Empty);
+    #[test]
+    fn test_stats_screen() {
+        let mut controller = get_memory_controller(&["apples", "bread", "eggs"]);
+        controller.handle_action(Action::ToggleTaskStatus);
+        let task = get_task(&controller, 0);
+        assert!(task.completed_at.is_some() && task.completed_at >= task.created_at);
 
-        controller.state.set_is_first_time(true);
-        action = controller.handle_key_stroke(KeyCode::Enter);
-        assert_eq!(action, Action::AddSecret);
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('s')));
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Stats);
+        let stats = controller.state.get_stats();
+        assert_eq!((stats.done_count, stats.open_count), (1, 2));
+        assert_eq!(stats.untracked_count, 0);
+        assert_eq!(
+            stats.completed_per_day.last().map(|(_, count)| *count),
+            Some(1)
+        );
+
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
     }
 
     #[test]
-    fn test_common_error_tests() {
-        let mut controller = Controller::new();
+    fn test_history_screen() {
+        let mut controller = get_memory_controller(&["apples", "bread"]);
+        controller.handle_action(Action::ToggleTaskStatus);
+        controller.handle_action(Action::OpenEditScreen);
+        controller.state.set_input("green apples");
+        controller.handle_action(Action::AddTask);
 
-        controller.handle_action(Action::GetTasks);
-        assert_ne!(controller.state.get_error(), "");
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('h')));
+        assert_eq!(action, Action::OpenHistoryScreen);
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::History);
+        let descriptions: Vec<&str> = controller
+            .state
+            .get_task_events()
+            .iter()
+            .map(|event| event.describe())
+            .collect();
+        assert_eq!(descriptions, vec!["Renamed", "Completed", "Created"]);
+        assert_eq!(controller.state.get_task_events()[0].title, "green apples");
+        assert_eq!(controller.state.get_task_events()[2].title, "apples");
 
-        controller.state.set_input("TEST TASK TITLE");
-        controller.handle_action(Action::AddTask);
-        assert_ne!(controller.state.get_error(), "");
+        controller.handle_action(Action::HistoryDown);
+        controller.handle_action(Action::HistoryDown);
+        controller.handle_action(Action::HistoryDown);
+        assert_eq!(controller.state.get_task_events_line(), 2);
+        controller.handle_action(Action::HistoryUp);
+        assert_eq!(controller.state.get_task_events_line(), 1);
 
-        controller.handle_action(Action::CheckSecret);
-        assert_eq!(controller.state.get_error(), "Could not get user");
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Esc));
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+
+        // The detail panel shows the latest events of the selected task
+        let detail_descriptions = |controller: &Controller| -> Vec<String> {
+            controller
+                .state
+                .get_detail_events()
+                .iter()
+                .map(|event| String::from(event.describe()))
+                .collect()
+        };
+        controller.update_task_detail();
+        assert!(detail_descriptions(&controller).is_empty());
+        controller.handle_action(Action::Resize(120, 40));
+        controller.update_task_detail();
+        assert_eq!(
+            detail_descriptions(&controller),
+            vec!["Renamed", "Completed", "Created"]
+        );
+        controller.handle_action(Action::MenuDown);
+        controller.update_task_detail();
+        assert_eq!(detail_descriptions(&controller), vec!["Created"]);
+        controller.handle_action(Action::ToggleTaskStatus);
+        controller.update_task_detail();
+        assert_eq!(
+            detail_descriptions(&controller),
+            vec!["Completed", "Created"]
+        );
+    }
+
+    #[test]
+    fn test_time_tracking() {
+        let mut controller = get_memory_controller(&["apples", "bread"]);
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('T')));
+        assert_eq!(action, Action::ToggleTimer);
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_message(), "Started timer on apples");
+        let apples_id = get_task(&controller, 0).id;
+        assert_eq!(
+            controller
+                .state
+                .get_running_timer()
+                .map(|timer| timer.task_id),
+            Some(apples_id)
+        );
+
+        // Starting another timer stops the running one
+        controller.handle_action(Action::MenuDown);
+        controller.handle_action(Action::ToggleTimer);
+        let bread_id = get_task(&controller, 1).id;
+        assert_eq!(controller.state.get_time_entries().len(), 2);
+        assert_eq!(
+            controller
+                .state
+                .get_running_timer()
+                .map(|timer| timer.task_id),
+            Some(bread_id)
+        );
+
+        controller.handle_action(Action::ToggleTimer);
+        assert!(controller.state.get_running_timer().is_none());
+        assert!(controller
+            .state
+            .get_message()
+            .starts_with("Stopped timer on bread after 0:00:"));
+
+        controller.handle_action(Action::ToggleTimer);
+        controller.handle_action(Action::RemoveTask);
+        assert!(controller.state.get_running_timer().is_none());
+
+        let report_path = get_test_dir("time-tracking");
+        let path = report_path.join("controller.csv");
+        controller.handle_action(Action::ExportTimeReport(ReportFormat::Csv, path.clone()));
+        finish_job(&mut controller);
+        assert!(controller
+            .state
+            .get_message()
+            .starts_with("Saved csv time report to"));
+        assert!(file_exists(&report_path, "controller.csv"));
+    }
+
+    #[test]
+    fn test_focus_screen() {
+        let mut controller = get_memory_controller(&["apples", "bread"]);
+        let press = |controller: &mut Controller, code: KeyCode| {
+            let action = controller.handle_key_stroke(KeyEvent::from(code));
+            controller.handle_action(action);
+        };
+        let action = controller.handle_key_stroke(KeyEvent::from(KeyCode::Char('f')));
+        assert_eq!(action, Action::OpenFocusScreen);
+        controller.handle_action(action);
+        assert_eq!(controller.state.get_screen(), &Screen::Focus);
+        let task_id = get_task(&controller, 0).id;
+
+        let start = Instant::now();
+        controller.handle_action(Action::Tick(start));
+        assert!(!controller.state.get_is_bell_pending());
+
+        let minutes = |count: u64| std::time::Duration::from_secs(count * 60);
+        controller.handle_action(Action::Tick(start + minutes(26)));
+        assert!(controller.state.get_is_bell_pending());
+        assert_eq!(controller.state.get_pomodoro_count(task_id), 1);
+        assert_eq!(
+            controller.state.get_message(),
+            "Work is over, short break started"
+        );
+        let pomodoro = controller
+            .state
+            .get_pomodoro()
+            .expect("No pomodoro running");
+        assert_eq!(pomodoro.phase, PomodoroPhase::ShortBreak);
+
+        // A paused break does not run out
+        controller.state.set_is_bell_pending(false);
+        press(&mut controller, KeyCode::Char(' '));
+        controller.handle_action(Action::Tick(start + minutes(60)));
+        assert!(!controller.state.get_is_bell_pending());
+        press(&mut controller, KeyCode::Char(' '));
+        press(&mut controller, KeyCode::Char('n'));
+        let pomodoro = controller
+            .state
+            .get_pomodoro()
+            .expect("No pomodoro running");
+        assert_eq!(pomodoro.phase, PomodoroPhase::Work);
+
+        press(&mut controller, KeyCode::Esc);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        assert!(controller.state.get_pomodoro().is_none());
+        assert_eq!(
+            controller.state.get_message(),
+            "Focus session ended after 1 pomodoro"
+        );
+    }
+
+    #[test]
+    fn test_app_events() {
+        let mut controller = get_memory_controller(&["apples", "bread"]);
+        let now = Instant::now();
+        let is_dirty = |controller: &mut Controller, app_event: AppEvent| {
+            controller
+                .handle_app_event(app_event)
+                .expect("Could not handle event")
+        };
+
+        assert!(!is_dirty(&mut controller, AppEvent::Tick(now)));
+        let moved = MouseEvent {
+            kind: MouseEventKind::Moved,
+            column: 5,
+            row: 5,
+            modifiers: KeyModifiers::NONE,
+        };
+        assert!(!is_dirty(
+            &mut controller,
+            AppEvent::Input(event::Event::Mouse(moved))
+        ));
+        let key = KeyEvent::from(KeyCode::Down);
+        assert!(is_dirty(
+            &mut controller,
+            AppEvent::Input(event::Event::Key(key))
+        ));
+        assert_eq!(controller.state.get_line(), 1);
+        assert!(is_dirty(&mut controller, AppEvent::Resize(120, 40)));
+
+        // Results sent back from other threads run like any action
+        assert!(is_dirty(
+            &mut controller,
+            AppEvent::Action(Action::ToggleTaskStatus)
+        ));
+        assert_eq!(get_task(&controller, 1).status, "completed");
+
+        // A running timer keeps the list changing, once per second
+        controller.handle_action(Action::ToggleTimer);
+        assert!(is_dirty(&mut controller, AppEvent::Tick(now)));
+
+        let error = AppEvent::Error(TuduError::validation("Could not read event"));
+        assert!(controller.handle_app_event(error).is_err());
+    }
+
+    #[test]
+    fn test_background_jobs() {
+        let mut controller = get_memory_controller(&["apples", "bread"]);
+        let path = get_test_dir("jobs").join("jobs.csv");
+
+        controller.handle_action(Action::ExportTimeReport(ReportFormat::Csv, path.clone()));
+        assert!(controller
+            .state
+            .get_loading()
+            .is_some_and(|loading| loading.label == "Exporting csv time report"));
+        // The list keeps working while the job runs, another long one has to wait
+        controller.handle_action(Action::MenuDown);
+        assert_eq!(controller.state.get_line(), 1);
+        controller.handle_action(Action::Export(ExportFormat::Csv, path.clone()));
+        assert_eq!(
+            controller.state.get_message(),
+            "Exporting csv time report is still running, please wait"
+        );
+        // Queries are refused instead of freezing the ui, a finished pomodoro waits for the job
+        controller.handle_action(Action::FilterTasks(String::from("tag:work")));
+        assert_eq!(
+            controller.state.get_message(),
+            "Exporting csv time report is still running, please wait"
+        );
+        let task_id = get_task(&controller, 0).id;
+        controller.handle_action(Action::RecordPomodoro(task_id, 25));
+        assert_eq!(controller.state.get_pomodoro_count(task_id), 0);
+
+        finish_job(&mut controller);
+        assert!(controller.state.get_loading().is_none());
+        assert_eq!(controller.state.get_pomodoro_count(task_id), 1);
+        assert!(controller
+            .state
+            .get_message()
+            .starts_with("Saved csv time report to"));
+
+        // Commands ask before overwriting a file, like the export screen
+        let command = format!("report csv {}", path.display());
+        controller.handle_action(Action::OpenCommandLine);
+        controller.state.set_input(&command);
+        controller.handle_action(Action::ExecuteCommand);
+        assert_eq!(controller.state.get_screen(), &Screen::Confirm);
+        assert_eq!(
+            controller
+                .state
+                .get_confirmation()
+                .map(|c| c.message.as_str()),
+            Some(format!("{} already exists, overwrite it?", path.display()).as_str())
+        );
+        controller.handle_action(Action::RejectConfirmation);
+        assert!(controller.state.get_loading().is_none());
+        let command = format!("export csv {}", path.display());
+        controller.handle_action(Action::OpenCommandLine);
+        controller.state.set_input(&command);
+        controller.handle_action(Action::ExecuteCommand);
+        controller.handle_action(Action::AcceptConfirmation);
+        assert_eq!(controller.state.get_screen(), &Screen::Main);
+        finish_job(&mut controller);
+        assert!(controller
+            .state
+            .get_message()
+            .starts_with("Saved csv file to"));
+
+        controller.handle_action(Action::ImportICS(PathBuf::from("./test/missing/tudu.ics")));
+        finish_job(&mut controller);
+        assert_eq!(
+            controller.state.get_notification().map(|n| n.severity),
+            Some(Severity::Error)
+        );
+        assert_eq!(controller.state.get_task_list_length(), 2);
+    }
+
+    #[test]
+    fn test_mouse_events() {
+        let mut controller = get_memory_controller(&["apples", "bread", "eggs"]);
+        let mouse = |kind, column, row| MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        };
+        let click = |column, row| mouse(MouseEventKind::Down(MouseButton::Left), column, row);
+
+        // Tasks start below the top border of the list
+        let action = controller.handle_mouse_event(click(20, 2));
+        assert_eq!(action, Action::MenuGoTo(1));
+        controller.handle_action(action);
+        assert_eq!(controller.handle_mouse_event(click(20, 4)), Action::Empty);
+        assert_eq!(controller.handle_mouse_event(click(0, 2)), Action::Empty);
+
+        let action = controller.handle_mouse_event(click(3, 3));
+        assert_eq!(controller.state.get_line(), 2);
+        assert_eq!(action, Action::ToggleTaskStatus);
+        controller.handle_action(action);
+        assert_eq!(get_task(&controller, 2).status, "completed");
+
+        let action = controller.handle_mouse_event(mouse(MouseEventKind::ScrollUp, 20, 2));
+        assert_eq!(action, Action::MenuUp);
+
+        // The legend starts with "esc: Exit, ?/f1: Help"
+        let action = controller.handle_mouse_event(click(2, 21));
+        assert!(matches!(
+            action,
+            Action::RequestConfirmation(ref message, _) if message == "Quit tudu?"
+        ));
+        assert_eq!(controller.handle_mouse_event(click(9, 21)), Action::Empty);
+        let action = controller.handle_mouse_event(click(11, 21));
+        assert_eq!(action, Action::OpenHelp);
+        controller.handle_action(action);
+
+        let action = controller.handle_mouse_event(mouse(MouseEventKind::ScrollDown, 20, 2));
+        assert_eq!(action, Action::ScrollHelp(1));
+        assert_eq!(controller.handle_mouse_event(click(20, 2)), Action::Empty);
     }
 
     // #[test]
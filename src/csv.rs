@@ -1,32 +1,32 @@
-use std::{error::Error, path::PathBuf};
+use std::path::Path;
 
 use csv::Writer;
 
+use crate::error::{TuduError, TuduResult};
 use crate::task::Task;
 
-pub fn write_tasks_into_csv_file(
-    task_list: &Vec<Task>,
-    path: &PathBuf,
-) -> Result<(), Box<dyn Error>> {
-    let mut writer = Writer::from_path(path)?;
-    let mut index = 0;
+pub fn write_tasks_into_csv_file(task_list: &[Task], path: &Path) -> TuduResult<()> {
+    let map_error = |e| TuduError::export("Could not save csv file", e);
+    let mut writer = Writer::from_path(path).map_err(map_error)?;
 
-    for task in task_list.iter() {
+    for (index, task) in task_list.iter().enumerate() {
         let title = task.title.as_str();
         let status = task.status.as_str();
         let record_index = format!("{}", index);
         let record = &[record_index.as_str(), title, status];
 
-        writer.write_record(record)?;
-        index += 1;
+        writer.write_record(record).map_err(map_error)?;
     }
-    writer.flush()?;
+    writer
+        .flush()
+        .map_err(|e| TuduError::export("Could not save csv file", e))?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::filesystem::get_test_dir;
 
     #[test]
     fn test_write_file() {
@@ -34,12 +34,12 @@ mod tests {
             id: 0,
             title: String::from("Title"),
             status: String::from("completed"),
+            ..Default::default()
         };
 
         let task_list = vec![task];
 
-        let mut path = PathBuf::new();
-        path.push("./test/csv/tudu.csv");
+        let path = get_test_dir("csv").join("tudu.csv");
 
         let result = write_tasks_into_csv_file(&task_list, &path);
         assert!(result.is_ok());
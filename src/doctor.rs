@@ -0,0 +1,124 @@
+use crate::client::Client;
+use crate::encdec::decrypt;
+use crate::error::{TuduError, TuduResult};
+use crate::task::Task;
+
+pub const DECRYPTION_FAILED_REASON: &str = "Could not decrypt title with the master key";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct DoctorReport {
+    pub integrity_errors: Vec<String>,
+    pub undecryptable_tasks: Vec<Task>,
+    pub task_count: usize,
+}
+
+impl DoctorReport {
+    pub fn is_healthy(&self) -> bool {
+        self.integrity_errors.is_empty() && self.undecryptable_tasks.is_empty()
+    }
+}
+
+pub fn verify_master_key(client: &Client, master_key: &str) -> TuduResult<bool> {
+    let users = client.get_user()?;
+    match users.first() {
+        Some(user) => Ok(decrypt(&user.secret, master_key).is_ok()),
+        None => Err(TuduError::auth("Could not get user")),
+    }
+}
+
+pub fn diagnose(client: &Client, master_key: &str) -> TuduResult<DoctorReport> {
+    let integrity_errors = client.integrity_check()?;
+    let tasks = client.get_tasks()?;
+    let task_count = tasks.len();
+    let undecryptable_tasks = tasks
+        .into_iter()
+        .filter(|task| decrypt(&task.title, master_key).is_err())
+        .collect();
+
+    Ok(DoctorReport {
+        integrity_errors,
+        undecryptable_tasks,
+        task_count,
+    })
+}
+
+pub fn quarantine_tasks(client: &Client, tasks: &[Task]) -> TuduResult<usize> {
+    client.create_quarantine_table()?;
+    for task in tasks.iter() {
+        client.quarantine_task(task, DECRYPTION_FAILED_REASON)?;
+    }
+    Ok(tasks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encdec::encrypt;
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_diagnose_and_quarantine() {
+        let client = Client {
+            connection: Some(Connection::open_in_memory().expect("Could not open connection")),
+        };
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+        client
+            .create_user_table()
+            .expect("Could not create user table");
+
+        client
+            .create_user(encrypt("secret", "KEY"))
+            .expect("Could not create user");
+        client
+            .create_task(encrypt("Readable task", "KEY"))
+            .expect("Could not create task");
+        client
+            .create_task(encrypt("Other key task", "OTHER_KEY"))
+            .expect("Could not create task");
+        client
+            .create_task(String::from("not encrypted"))
+            .expect("Could not create task");
+
+        assert!(verify_master_key(&client, "KEY").expect("Could not verify"));
+        assert!(!verify_master_key(&client, "WRONG").expect("Could not verify"));
+
+        let report = diagnose(&client, "KEY").expect("Could not diagnose");
+        assert_eq!(report.task_count, 3);
+        assert!(report.integrity_errors.is_empty());
+        assert_eq!(report.undecryptable_tasks.len(), 2);
+        assert!(!report.is_healthy());
+
+        let quarantined =
+            quarantine_tasks(&client, &report.undecryptable_tasks).expect("Could not quarantine");
+        assert_eq!(quarantined, 2);
+
+        let report = diagnose(&client, "KEY").expect("Could not diagnose");
+        assert_eq!(report.task_count, 1);
+        assert!(report.is_healthy());
+
+        let tables = client.dump_tables().expect("Could not dump tables");
+        let quarantine = tables
+            .iter()
+            .find(|table| table.name == "quarantine")
+            .expect("Could not find quarantine table");
+        assert_eq!(quarantine.rows.len(), 2);
+
+        client
+            .rebuild_database()
+            .expect("Could not rebuild database");
+    }
+
+    #[test]
+    fn test_verify_without_user() {
+        let client = Client {
+            connection: Some(Connection::open_in_memory().expect("Could not open connection")),
+        };
+        client
+            .create_user_table()
+            .expect("Could not create user table");
+
+        assert!(verify_master_key(&client, "KEY").is_err());
+    }
+}
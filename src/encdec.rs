@@ -2,18 +2,26 @@ use hex::{decode, encode};
 use magic_crypt::new_magic_crypt;
 use magic_crypt::MagicCryptTrait;
 
+use crate::error::{TuduError, TuduResult};
+
 pub fn encrypt(data: &str, key: &str) -> String {
     let mc = new_magic_crypt!(key, 256);
     let encrypted = mc.encrypt_str_to_bytes(data);
     encode(encrypted)
 }
 
-pub fn decrypt(encrypted_data: &str, key: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let trimmed_data = &encrypted_data[1..encrypted_data.len() - 1];
+pub fn decrypt(encrypted_data: &str, key: &str) -> TuduResult<String> {
+    let trimmed_data = encrypted_data
+        .get(1..encrypted_data.len().saturating_sub(1))
+        .unwrap_or_default();
     let mc = new_magic_crypt!(key, 256);
-    let encrypted_bytes = decode(trimmed_data)?;
-    let decrypted_bytes = mc.decrypt_bytes_to_bytes(&encrypted_bytes)?;
-    Ok(String::from_utf8(decrypted_bytes)?)
+    let encrypted_bytes = decode(trimmed_data)
+        .map_err(|e| TuduError::crypto("Encrypted data is not valid hex", e))?;
+    let decrypted_bytes = mc
+        .decrypt_bytes_to_bytes(&encrypted_bytes)
+        .map_err(|e| TuduError::crypto("Could not decrypt data", e))?;
+    String::from_utf8(decrypted_bytes)
+        .map_err(|e| TuduError::crypto("Decrypted data is not valid text", e))
 }
 
 #[cfg(test)]
@@ -51,5 +59,6 @@ mod tests {
         let decrypted_data = decrypt(encrypted_data, key);
 
         assert!(decrypted_data.is_err());
+        assert!(decrypt("", key).is_err());
     }
 }
@@ -0,0 +1,168 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+pub type Source = Box<dyn Error + Send + Sync + 'static>;
+pub type TuduResult<T> = Result<T, TuduError>;
+
+#[derive(Debug)]
+pub enum TuduError {
+    Storage {
+        context: String,
+        source: Option<Source>,
+    },
+    Crypto {
+        context: String,
+        source: Option<Source>,
+    },
+    Auth(String),
+    Io {
+        context: String,
+        source: Option<Source>,
+    },
+    Validation(String),
+    Export {
+        context: String,
+        source: Option<Source>,
+    },
+}
+
+impl TuduError {
+    pub fn storage<E: Into<Source>>(context: &str, source: E) -> Self {
+        TuduError::Storage {
+            context: String::from(context),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn crypto<E: Into<Source>>(context: &str, source: E) -> Self {
+        TuduError::Crypto {
+            context: String::from(context),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn io<E: Into<Source>>(context: &str, source: E) -> Self {
+        TuduError::Io {
+            context: String::from(context),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn export<E: Into<Source>>(context: &str, source: E) -> Self {
+        TuduError::Export {
+            context: String::from(context),
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn auth(message: &str) -> Self {
+        TuduError::Auth(String::from(message))
+    }
+
+    pub fn validation(message: &str) -> Self {
+        TuduError::Validation(String::from(message))
+    }
+
+    // Message shown in the ui and printed by the cli, includes the whole source chain
+    pub fn user_message(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = self.source();
+        while let Some(error) = source {
+            message.push_str(&format!(", e: {}", error));
+            source = error.source();
+        }
+        message
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            TuduError::Validation(_) => 2,
+            TuduError::Storage { .. } => 3,
+            TuduError::Crypto { .. } => 4,
+            TuduError::Auth(_) => 5,
+            TuduError::Io { .. } => 6,
+            TuduError::Export { .. } => 7,
+        }
+    }
+}
+
+impl fmt::Display for TuduError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuduError::Storage { context, .. }
+            | TuduError::Crypto { context, .. }
+            | TuduError::Io { context, .. }
+            | TuduError::Export { context, .. } => write!(f, "{}", context),
+            TuduError::Auth(message) | TuduError::Validation(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for TuduError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TuduError::Storage { source, .. }
+            | TuduError::Crypto { source, .. }
+            | TuduError::Io { source, .. }
+            | TuduError::Export { source, .. } => source
+                .as_ref()
+                .map(|source| source.as_ref() as &(dyn Error + 'static)),
+            TuduError::Auth(_) | TuduError::Validation(_) => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for TuduError {
+    fn from(error: rusqlite::Error) -> Self {
+        TuduError::storage("Database error", error)
+    }
+}
+
+impl From<io::Error> for TuduError {
+    fn from(error: io::Error) -> Self {
+        TuduError::io("File error", error)
+    }
+}
+
+impl From<csv::Error> for TuduError {
+    fn from(error: csv::Error) -> Self {
+        TuduError::export("Could not write csv", error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_message_chain() {
+        let io_error = io::Error::new(io::ErrorKind::NotFound, "missing file");
+        let error = TuduError::export("Could not save csv file", io_error);
+
+        assert_eq!(error.to_string(), "Could not save csv file");
+        assert_eq!(
+            error.user_message(),
+            "Could not save csv file, e: missing file"
+        );
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(TuduError::validation("bad input").exit_code(), 2);
+        assert_eq!(TuduError::auth("Password is wrong").exit_code(), 5);
+        assert_eq!(
+            TuduError::from(rusqlite::Error::InvalidQuery).exit_code(),
+            3
+        );
+        assert_eq!(
+            TuduError::from(io::Error::other("disk full")).exit_code(),
+            6
+        );
+        assert_eq!(
+            TuduError::auth("Password is wrong").user_message(),
+            "Password is wrong"
+        );
+    }
+}
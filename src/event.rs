@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event};
+
+use crate::constants::{Action, TICK_RATE};
+use crate::error::{TuduError, TuduResult};
+use crate::worker::JobOutcome;
+
+// How long the input thread waits for a key before checking if it should stop
+const INPUT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Debug)]
+pub enum AppEvent {
+    Input(Event),
+    Resize(u16, u16),
+    Tick(Instant),
+    // Work finished off the ui thread comes back as the action to run with its result
+    Action(Action),
+    Job(JobOutcome),
+    Error(TuduError),
+}
+
+// Every source of events feeds one channel, the ui thread sleeps until something arrives
+pub struct EventLoop {
+    sender: Sender<AppEvent>,
+    receiver: Receiver<AppEvent>,
+    is_running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Default for EventLoop {
+    fn default() -> Self {
+        EventLoop::new()
+    }
+}
+
+impl EventLoop {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        EventLoop {
+            sender,
+            receiver,
+            is_running: Arc::new(AtomicBool::new(true)),
+            threads: Vec::new(),
+        }
+    }
+
+    pub fn get_sender(&self) -> Sender<AppEvent> {
+        self.sender.clone()
+    }
+
+    pub fn start_input(&mut self) {
+        let sender = self.get_sender();
+        let is_running = Arc::clone(&self.is_running);
+        self.threads.push(thread::spawn(move || {
+            while is_running.load(Ordering::Relaxed) {
+                let app_event = match event::poll(INPUT_POLL_TIMEOUT) {
+                    Ok(false) => continue,
+                    Ok(true) => match event::read() {
+                        Ok(Event::Resize(width, height)) => AppEvent::Resize(width, height),
+                        Ok(event) => AppEvent::Input(event),
+                        Err(e) => AppEvent::Error(TuduError::io("Could not read event", e)),
+                    },
+                    Err(e) => AppEvent::Error(TuduError::io("Could not poll events", e)),
+                };
+                let is_error = matches!(app_event, AppEvent::Error(_));
+                if sender.send(app_event).is_err() || is_error {
+                    break;
+                }
+            }
+        }));
+    }
+
+    pub fn start_ticks(&mut self) {
+        let sender = self.get_sender();
+        let is_running = Arc::clone(&self.is_running);
+        self.threads.push(thread::spawn(move || {
+            while is_running.load(Ordering::Relaxed) {
+                thread::sleep(TICK_RATE);
+                if sender.send(AppEvent::Tick(Instant::now())).is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+
+    pub fn next(&self) -> TuduResult<AppEvent> {
+        self.receiver
+            .recv()
+            .map_err(|e| TuduError::io("Event channel closed", e))
+    }
+
+    pub fn stop(&mut self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for EventLoop {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_loop() {
+        let mut event_loop = EventLoop::new();
+        event_loop
+            .get_sender()
+            .send(AppEvent::Action(Action::Undo))
+            .expect("Could not send event");
+        assert!(matches!(
+            event_loop.next(),
+            Ok(AppEvent::Action(Action::Undo))
+        ));
+
+        let started_at = Instant::now();
+        event_loop.start_ticks();
+        match event_loop.next() {
+            Ok(AppEvent::Tick(now)) => assert!(now >= started_at + TICK_RATE),
+            other => panic!("Expected a tick, got {:?}", other),
+        }
+        event_loop.stop();
+        assert!(event_loop.threads.is_empty());
+    }
+}
@@ -1,34 +1,88 @@
 use std::fs;
-use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
-
-use dirs;
+use std::path::{Path, PathBuf};
 
 use crate::constants;
+use crate::error::{TuduError, TuduResult};
 
-pub fn get_app_config_path() -> Result<PathBuf, Error> {
+pub fn get_app_config_path() -> TuduResult<PathBuf> {
     dirs::config_dir()
         .map(|config_directory| {
-            let mut path = PathBuf::from(config_directory);
+            let mut path = config_directory;
             path.push(constants::APP_PATH);
             path
         })
-        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Could not get config directory path"))
+        .ok_or_else(|| TuduError::Io {
+            context: String::from("Could not get config directory path"),
+            source: None,
+        })
 }
 
-pub fn create_config_folder(app_config_path: &PathBuf) -> Result<(), Error> {
-    match fs::create_dir_all(&app_config_path) {
-        Ok(()) => Ok(()),
-        Err(_) => Err(Error::new(
-            ErrorKind::Other,
-            "Could not create config folder",
-        )),
-    }
+pub fn create_config_folder(app_config_path: &Path) -> TuduResult<()> {
+    fs::create_dir_all(app_config_path)
+        .map_err(|e| TuduError::io("Could not create config folder", e))
 }
 
-pub fn file_exists(app_config_path: &PathBuf, file_name: &str) -> bool {
+pub fn file_exists(app_config_path: &Path, file_name: &str) -> bool {
     let absolute_path = app_config_path.join(file_name);
-    fs::metadata(&absolute_path).is_ok()
+    fs::metadata(absolute_path).is_ok()
+}
+
+pub fn expand_path(input: &str) -> PathBuf {
+    match input.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => dirs::home_dir()
+            .map(|home| home.join(rest.trim_start_matches('/')))
+            .unwrap_or_else(|| PathBuf::from(input)),
+        _ => PathBuf::from(input),
+    }
+}
+
+pub fn complete_directory(input: &str) -> String {
+    let (parent, prefix) = match input.rfind('/') {
+        Some(index) => (&input[..index + 1], &input[index + 1..]),
+        None => ("", input),
+    };
+    let search_path = if parent.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_path(parent)
+    };
+
+    let mut matches: Vec<String> = match fs::read_dir(search_path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name.starts_with(prefix))
+            .collect(),
+        Err(_) => return String::from(input),
+    };
+    matches.sort();
+
+    match matches.as_slice() {
+        [] => String::from(input),
+        [single] => format!("{}{}/", parent, single),
+        [first, rest @ ..] => {
+            let common_length = rest.iter().fold(first.len(), |length, name| {
+                first
+                    .chars()
+                    .zip(name.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum::<usize>()
+                    .min(length)
+            });
+            format!("{}{}", parent, &first[..common_length])
+        }
+    }
+}
+
+// An empty folder under the system temp directory, tests write there instead of the repository
+#[cfg(test)]
+pub fn get_test_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tudu-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).expect("Could not create test folder");
+    path
 }
 
 #[cfg(test)]
@@ -43,8 +97,8 @@ mod tests {
         let correct_result = file_exists(&path, "tudu.db");
         let wrong_result = file_exists(&path, "tudu1.db");
 
-        assert_eq!(correct_result, true);
-        assert_eq!(wrong_result, false);
+        assert!(correct_result);
+        assert!(!wrong_result);
     }
 
     #[test]
@@ -61,4 +115,23 @@ mod tests {
         let result = create_config_folder(&path);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_expand_path() {
+        let home = dirs::home_dir().expect("Could not get home directory");
+
+        assert_eq!(expand_path("~/out.csv"), home.join("out.csv"));
+        assert_eq!(expand_path("./out.csv"), PathBuf::from("./out.csv"));
+        assert_eq!(expand_path("~user/out.csv"), PathBuf::from("~user/out.csv"));
+    }
+
+    #[test]
+    fn test_complete_directory() {
+        assert_eq!(complete_directory("./test/fil"), "./test/filesystem/");
+        assert_eq!(complete_directory("./test/c"), "./test/c");
+        assert_eq!(complete_directory("./test/co"), "./test/con");
+        assert_eq!(complete_directory("./test/cont"), "./test/controller/");
+        assert_eq!(complete_directory("./test/nothing"), "./test/nothing");
+        assert_eq!(complete_directory("./missing/x"), "./missing/x");
+    }
 }
@@ -0,0 +1,104 @@
+use chrono::NaiveDateTime;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskEventKind {
+    Created,
+    TitleChanged,
+    StatusChanged,
+    // The list, tags or priority of the task
+    DetailsChanged,
+    Deleted,
+    Restored,
+}
+
+impl TaskEventKind {
+    // Stored in the kind column of the task_events table
+    pub fn get_name(&self) -> &str {
+        match self {
+            TaskEventKind::Created => "created",
+            TaskEventKind::TitleChanged => "title_changed",
+            TaskEventKind::StatusChanged => "status_changed",
+            TaskEventKind::DetailsChanged => "details_changed",
+            TaskEventKind::Deleted => "deleted",
+            TaskEventKind::Restored => "restored",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            TaskEventKind::Created,
+            TaskEventKind::TitleChanged,
+            TaskEventKind::StatusChanged,
+            TaskEventKind::DetailsChanged,
+            TaskEventKind::Deleted,
+            TaskEventKind::Restored,
+        ]
+        .into_iter()
+        .find(|kind| kind.get_name() == name)
+    }
+}
+
+// The title and status are the task as it was right after the change,
+// or right before it for a deletion
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskEvent {
+    pub id: i32,
+    pub task_id: i32,
+    pub kind: TaskEventKind,
+    pub title: String,
+    pub status: String,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+impl TaskEvent {
+    pub fn describe(&self) -> &str {
+        match self.kind {
+            TaskEventKind::Created => "Created",
+            TaskEventKind::TitleChanged => "Renamed",
+            TaskEventKind::StatusChanged if self.status == "completed" => "Completed",
+            TaskEventKind::StatusChanged => "Reopened",
+            TaskEventKind::DetailsChanged => "Updated",
+            TaskEventKind::Deleted => "Deleted",
+            TaskEventKind::Restored => "Restored",
+        }
+    }
+
+    pub fn format_line(&self) -> String {
+        let created_at = self.created_at.map_or(String::from("-"), |created_at| {
+            created_at.format("%Y-%m-%d %H:%M:%S").to_string()
+        });
+        format!("{}  {:<10}{}", created_at, self.describe(), self.title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_event() {
+        assert_eq!(
+            TaskEventKind::from_name("status_changed"),
+            Some(TaskEventKind::StatusChanged)
+        );
+        assert_eq!(TaskEventKind::from_name("archived"), None);
+
+        let mut event = TaskEvent {
+            id: 1,
+            task_id: 3,
+            kind: TaskEventKind::StatusChanged,
+            title: String::from("Buy milk"),
+            status: String::from("completed"),
+            created_at: NaiveDateTime::parse_from_str("2024-10-19 15:30:00", "%Y-%m-%d %H:%M:%S")
+                .ok(),
+        };
+        assert_eq!(
+            event.format_line(),
+            "2024-10-19 15:30:00  Completed Buy milk"
+        );
+
+        event.status = String::from("in-progress");
+        event.created_at = None;
+        assert_eq!(event.format_line(), "-  Reopened  Buy milk");
+    }
+}
@@ -0,0 +1,459 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::error::{TuduError, TuduResult};
+use crate::task::Task;
+
+const PRODID: &str = "-//itsjustaplant//tudu//EN";
+const UID_PREFIX: &str = "tudu-task-";
+const UID_DOMAIN: &str = "@tudu";
+const MAX_LINE_OCTETS: usize = 75;
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+const DATE_FORMAT: &str = "%Y%m%d";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VTodo {
+    pub uid: String,
+    pub summary: String,
+    pub status: String,
+    pub due: Option<String>,
+    pub priority: Option<u8>,
+    pub completed: Option<String>,
+    pub created: Option<String>,
+    pub last_modified: Option<String>,
+    pub rrule: Option<String>,
+}
+
+impl From<&Task> for VTodo {
+    fn from(task: &Task) -> Self {
+        let status = if task.status == "completed" {
+            "COMPLETED"
+        } else {
+            "NEEDS-ACTION"
+        };
+
+        VTodo {
+            uid: format!("{}{}{}", UID_PREFIX, task.id, UID_DOMAIN),
+            summary: task.title.clone(),
+            status: String::from(status),
+            completed: task.completed_at.map(format_date_time),
+            created: task.created_at.map(format_date_time),
+            last_modified: task.updated_at.map(format_date_time),
+            due: task.due.map(format_date_time),
+            priority: task.priority,
+            rrule: task.recurrence.clone(),
+        }
+    }
+}
+
+impl VTodo {
+    pub fn to_task(&self) -> Task {
+        let id = self
+            .uid
+            .strip_prefix(UID_PREFIX)
+            .and_then(|rest| rest.strip_suffix(UID_DOMAIN))
+            .and_then(|id| id.parse().ok())
+            .unwrap_or(0);
+        let status = if self.status == "COMPLETED" {
+            "completed"
+        } else {
+            "in-progress"
+        };
+
+        Task {
+            id,
+            title: self.summary.clone(),
+            status: String::from(status),
+            created_at: self.created.as_deref().and_then(parse_date_time),
+            updated_at: self.last_modified.as_deref().and_then(parse_date_time),
+            completed_at: self.completed.as_deref().and_then(parse_date_time),
+            due: self.due.as_deref().and_then(parse_date_time),
+            // 0 means the priority is undefined
+            priority: self.priority.filter(|priority| (1..=9).contains(priority)),
+            recurrence: self.rrule.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+pub fn write_tasks_into_ics_file(task_list: &[Task], path: &Path) -> TuduResult<()> {
+    let dtstamp = format!("{}Z", Utc::now().format(DATE_TIME_FORMAT));
+    fs::write(path, build_calendar(task_list, &dtstamp))
+        .map_err(|e| TuduError::export("Could not save ics file", e))
+}
+
+pub fn read_tasks_from_ics_file(path: &Path) -> TuduResult<Vec<Task>> {
+    let content =
+        fs::read_to_string(path).map_err(|e| TuduError::io("Could not read ics file", e))?;
+    let todos = parse_calendar(&content)?;
+    Ok(todos.iter().map(VTodo::to_task).collect())
+}
+
+pub fn build_calendar(task_list: &[Task], dtstamp: &str) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!("PRODID:{}", PRODID),
+    ];
+
+    for task in task_list.iter() {
+        let todo = VTodo::from(task);
+
+        lines.push(String::from("BEGIN:VTODO"));
+        lines.push(format!("UID:{}", todo.uid));
+        lines.push(format!("DTSTAMP:{}", dtstamp));
+        lines.push(format!("SUMMARY:{}", escape_text(&todo.summary)));
+        lines.push(format!("STATUS:{}", todo.status));
+        if let Some(created) = &todo.created {
+            lines.push(format!("CREATED:{}", created));
+        }
+        if let Some(last_modified) = &todo.last_modified {
+            lines.push(format!("LAST-MODIFIED:{}", last_modified));
+        }
+        if let Some(due) = &todo.due {
+            lines.push(format!("DUE:{}", due));
+        }
+        if let Some(priority) = todo.priority {
+            lines.push(format!("PRIORITY:{}", priority));
+        }
+        if let Some(completed) = &todo.completed {
+            lines.push(format!("COMPLETED:{}", completed));
+        }
+        if let Some(rrule) = &todo.rrule {
+            lines.push(format!("RRULE:{}", rrule));
+        }
+        lines.push(String::from("END:VTODO"));
+    }
+    lines.push(String::from("END:VCALENDAR"));
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+pub fn parse_calendar(content: &str) -> TuduResult<Vec<VTodo>> {
+    let mut todos = Vec::new();
+    let mut current: Option<VTodo> = None;
+
+    for line in unfold_lines(content) {
+        let (name, value) = match split_property(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value == "VTODO" => current = Some(VTodo::default()),
+            ("END", Some(_)) if value == "VTODO" => {
+                if let Some(todo) = current.take() {
+                    todos.push(todo);
+                }
+            }
+            ("UID", Some(todo)) => todo.uid = value,
+            ("SUMMARY", Some(todo)) => todo.summary = unescape_text(&value),
+            ("STATUS", Some(todo)) => todo.status = value.to_uppercase(),
+            ("DUE", Some(todo)) => todo.due = Some(value),
+            ("PRIORITY", Some(todo)) => todo.priority = value.parse().ok(),
+            ("COMPLETED", Some(todo)) => todo.completed = Some(value),
+            ("CREATED", Some(todo)) => todo.created = Some(value),
+            ("LAST-MODIFIED", Some(todo)) => todo.last_modified = Some(value),
+            ("RRULE", Some(todo)) => todo.rrule = Some(value),
+            _ => {}
+        }
+    }
+
+    if current.is_some() {
+        return Err(TuduError::validation("Unterminated VTODO component"));
+    }
+    Ok(todos)
+}
+
+// Tasks keep local time, the calendar gets UTC unless the local time does not exist
+fn format_date_time(timestamp: NaiveDateTime) -> String {
+    match Local.from_local_datetime(&timestamp).earliest() {
+        Some(local) => format!("{}Z", local.with_timezone(&Utc).format(DATE_TIME_FORMAT)),
+        None => timestamp.format(DATE_TIME_FORMAT).to_string(),
+    }
+}
+
+// UTC values are turned into local time, floating ones and dates are taken as local
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let timestamp = NaiveDateTime::parse_from_str(utc, DATE_TIME_FORMAT).ok()?;
+        return Some(
+            Utc.from_utc_datetime(&timestamp)
+                .with_timezone(&Local)
+                .naive_local(),
+        );
+    }
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// Lines longer than 75 octets are split with CRLF followed by a single space,
+// never in the middle of a UTF-8 sequence
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut line_octets = 0;
+
+    for ch in line.chars() {
+        let ch_octets = ch.len_utf8();
+        if line_octets + ch_octets > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        folded.push(ch);
+        line_octets += ch_octets;
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn unfold_lines(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for raw_line in content.split('\n') {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push(String::from(line)),
+        }
+    }
+    lines
+}
+
+// Returns the upper-cased property name without parameters and the raw value
+fn split_property(line: &str) -> Option<(String, String)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(index, ch)| match ch {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(index),
+        _ => None,
+    })?;
+
+    let name = line[..colon].split(';').next().unwrap_or("");
+    Some((name.to_uppercase(), String::from(&line[colon + 1..])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::get_test_dir;
+    use std::path::PathBuf;
+
+    fn sample_tasks() -> Vec<Task> {
+        vec![
+            Task {
+                id: 1,
+                title: String::from("Buy milk, eggs; bread"),
+                status: String::from("in-progress"),
+                ..Default::default()
+            },
+            Task {
+                id: 2,
+                title: String::from(
+                    "Write a very long task title that will definitely need folding ✓✓✓",
+                ),
+                status: String::from("completed"),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_build_calendar() {
+        let expected = fs::read_to_string("./test/ics/export.ics").expect("Could not read file");
+        let calendar = build_calendar(&sample_tasks(), "20240101T090000Z");
+
+        assert_eq!(calendar, expected);
+        assert!(calendar.split("\r\n").all(|line| line.len() <= 75));
+    }
+
+    #[test]
+    fn test_read_file() {
+        let mut path = PathBuf::new();
+        path.push("./test/ics/import.ics");
+
+        let tasks = read_tasks_from_ics_file(&path).expect("Could not read ics file");
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].title, "Call the plumber, then fix the sink; today");
+        assert_eq!(tasks[0].status, "in-progress");
+        assert_eq!(tasks[1].id, 7);
+        assert_eq!(tasks[1].status, "completed");
+        let completed_at = Utc
+            .with_ymd_and_hms(2024, 1, 2, 10, 0, 0)
+            .single()
+            .map(|timestamp| timestamp.with_timezone(&Local).naive_local());
+        assert_eq!(tasks[1].completed_at, completed_at);
+        assert_eq!(tasks[0].created_at, None);
+        let due = Utc
+            .with_ymd_and_hms(2024, 1, 15, 17, 0, 0)
+            .single()
+            .map(|timestamp| timestamp.with_timezone(&Local).naive_local());
+        assert_eq!(tasks[0].due, due);
+        assert_eq!(tasks[0].priority, Some(1));
+        assert_eq!(
+            tasks[2].recurrence,
+            Some(String::from("FREQ=WEEKLY;BYDAY=FR"))
+        );
+        assert_eq!(
+            tasks[2].title,
+            "Prepare the quarterly report for the whole team and send it to everyone before Friday"
+        );
+
+        let content = fs::read_to_string(&path).expect("Could not read file");
+        let todos = parse_calendar(&content).expect("Could not parse calendar");
+        assert_eq!(todos[0].due, Some(String::from("20240115T170000Z")));
+        assert_eq!(todos[0].priority, Some(1));
+        assert_eq!(todos[1].completed, Some(String::from("20240102T100000Z")));
+        assert_eq!(todos[2].rrule, Some(String::from("FREQ=WEEKLY;BYDAY=FR")));
+    }
+
+    #[test]
+    fn test_write_file() {
+        let path = get_test_dir("ics").join("tudu.ics");
+
+        let result = write_tasks_into_ics_file(&sample_tasks(), &path);
+        assert!(result.is_ok());
+
+        let tasks = read_tasks_from_ics_file(&path).expect("Could not read ics file");
+        assert_eq!(tasks, sample_tasks());
+    }
+
+    #[test]
+    fn test_timestamps_round_trip() {
+        let timestamp = |day: u32, hour: u32| {
+            NaiveDate::from_ymd_opt(2024, 3, day).and_then(|date| date.and_hms_opt(hour, 15, 30))
+        };
+        let task_list = vec![
+            Task {
+                id: 3,
+                title: String::from("Water the plants"),
+                status: String::from("completed"),
+                created_at: timestamp(4, 9),
+                updated_at: timestamp(5, 18),
+                completed_at: timestamp(5, 18),
+                ..Default::default()
+            },
+            Task {
+                id: 4,
+                title: String::from("Book the trip"),
+                status: String::from("in-progress"),
+                created_at: timestamp(6, 7),
+                updated_at: timestamp(6, 7),
+                ..Default::default()
+            },
+        ];
+
+        let calendar = build_calendar(&task_list, "20240101T090000Z");
+        assert_eq!(calendar.matches("\r\nCREATED:").count(), 2);
+        assert_eq!(calendar.matches("\r\nLAST-MODIFIED:").count(), 2);
+        assert_eq!(calendar.matches("\r\nCOMPLETED:").count(), 1);
+
+        let todos = parse_calendar(&calendar).expect("Could not parse calendar");
+        let tasks: Vec<Task> = todos.iter().map(VTodo::to_task).collect();
+        assert_eq!(tasks, task_list);
+
+        // Floating times and dates are read as local time
+        let todo = VTodo {
+            created: Some(String::from("20240304T091530")),
+            last_modified: Some(String::from("20240306")),
+            ..VTodo::default()
+        };
+        let task = todo.to_task();
+        assert_eq!(task.created_at, timestamp(4, 9));
+        assert_eq!(
+            task.updated_at,
+            NaiveDate::from_ymd_opt(2024, 3, 6).and_then(|date| date.and_hms_opt(0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_planning_round_trip() {
+        let content = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:tudu-task-5@tudu\r\n\
+                       SUMMARY:Pay the rent\r\nSTATUS:NEEDS-ACTION\r\nDUE:20240301T090000Z\r\n\
+                       PRIORITY:2\r\nRRULE:FREQ=MONTHLY;BYMONTHDAY=1\r\nEND:VTODO\r\n\
+                       END:VCALENDAR\r\n";
+        let path = get_test_dir("ics_planning").join("tudu.ics");
+        fs::write(&path, content).expect("Could not write file");
+
+        let tasks = read_tasks_from_ics_file(&path).expect("Could not read ics file");
+        assert!(tasks[0].due.is_some());
+        assert_eq!(tasks[0].priority, Some(2));
+        write_tasks_into_ics_file(&tasks, &path).expect("Could not write ics file");
+
+        let content = fs::read_to_string(&path).expect("Could not read file");
+        let todos = parse_calendar(&content).expect("Could not parse calendar");
+        assert_eq!(todos[0].due, Some(String::from("20240301T090000Z")));
+        assert_eq!(todos[0].priority, Some(2));
+        assert_eq!(
+            todos[0].rrule,
+            Some(String::from("FREQ=MONTHLY;BYMONTHDAY=1"))
+        );
+        assert_eq!(todos[0].to_task(), tasks[0]);
+
+        // An undefined priority is not kept
+        let todo = VTodo {
+            priority: Some(0),
+            ..VTodo::default()
+        };
+        assert_eq!(todo.to_task().priority, None);
+    }
+
+    #[test]
+    fn test_escaping() {
+        let text = "a\\b;c,d\ne";
+        assert_eq!(escape_text(text), "a\\\\b\\;c\\,d\\ne");
+        assert_eq!(unescape_text(&escape_text(text)), text);
+    }
+
+    #[test]
+    fn test_unterminated_component() {
+        let result = parse_calendar("BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nSUMMARY:x\r\n");
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,1085 @@
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::config::{KeyBindingsConfig, KeymapPreset};
+use crate::constants::{Action, Screen};
+use crate::error::{TuduError, TuduResult};
+
+// Screens where plain characters are typed into an input field
+const TEXT_INPUT_SCREENS: [Screen; 8] = [
+    Screen::Add,
+    Screen::Export,
+    Screen::Import,
+    Screen::Restore,
+    Screen::Passphrase,
+    Screen::Greetings,
+    Screen::Search,
+    Screen::Command,
+];
+const MAX_COUNT: usize = 9999;
+const HELP_PAGE: i32 = 10;
+const LEGEND_SEPARATOR: &str = ", ";
+
+// Several keys pressed one after another, written as "g g" in the config
+pub type KeySequence = Vec<KeyBinding>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // The case of a character already tells whether shift was held
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        KeyBinding { code, modifiers }
+    }
+
+    pub fn parse(text: &str) -> TuduResult<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // "+" alone or "ctrl++" bind the plus key itself
+        if text.ends_with("++") || text == "+" {
+            parts.pop();
+            parts.pop();
+            parts.push("+");
+        }
+        let key = parts.pop().unwrap_or_default();
+
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+                "alt" => modifiers |= KeyModifiers::ALT,
+                "shift" => modifiers |= KeyModifiers::SHIFT,
+                _ => {
+                    return Err(TuduError::validation(&format!(
+                        "Unknown modifier {} in {}",
+                        modifier, text
+                    )))
+                }
+            }
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            name if name.len() > 1 && name.starts_with('f') => match name[1..].parse() {
+                Ok(number @ 1..=12) => KeyCode::F(number),
+                _ => return Err(TuduError::validation(&format!("Unknown key {}", text))),
+            },
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                        KeyCode::Char(ch.to_ascii_uppercase())
+                    }
+                    (Some(ch), None) => KeyCode::Char(ch),
+                    _ => return Err(TuduError::validation(&format!("Unknown key {}", text))),
+                }
+            }
+        };
+
+        Ok(KeyBinding::new(code, modifiers))
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        *self == KeyBinding::new(key.code, key.modifiers)
+    }
+
+    fn is_plain_char(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "backtab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "del"),
+            KeyCode::Insert => write!(f, "ins"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pgup"),
+            KeyCode::PageDown => write!(f, "pgdn"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::F(number) => write!(f, "f{}", number),
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(ch) => write!(f, "{}", ch),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+pub fn parse_sequence(text: &str) -> TuduResult<KeySequence> {
+    let sequence = text
+        .split_whitespace()
+        .map(KeyBinding::parse)
+        .collect::<TuduResult<KeySequence>>()?;
+    if sequence.is_empty() {
+        return Err(TuduError::validation("Key cannot be empty"));
+    }
+    Ok(sequence)
+}
+
+pub fn format_sequence(sequence: &[KeyBinding]) -> String {
+    let keys: Vec<String> = sequence.iter().map(KeyBinding::to_string).collect();
+    if keys.iter().all(|key| key.chars().count() == 1) {
+        keys.join("")
+    } else {
+        keys.join(" ")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyEntry {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub keys: Vec<KeySequence>,
+    pub action: Action,
+    pub is_in_legend: bool,
+}
+
+impl KeyEntry {
+    pub fn get_keys_text(&self) -> String {
+        self.keys
+            .iter()
+            .map(|sequence| format_sequence(sequence))
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScreenKeymap {
+    pub screen: Screen,
+    pub entries: Vec<KeyEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SequenceMatch {
+    Exact(Action),
+    Prefix,
+    Unbound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    pub screens: Vec<ScreenKeymap>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            screens: get_default_screens(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn from_config(preset: KeymapPreset, keybindings: &KeyBindingsConfig) -> TuduResult<Self> {
+        let mut keymap = Keymap::default();
+        let mut problems: Vec<String> = Vec::new();
+
+        if preset == KeymapPreset::Vim {
+            for (screen_name, entry_name, keys) in VIM_OVERRIDES.iter() {
+                let keys: Vec<String> = keys.iter().map(|key| String::from(*key)).collect();
+                keymap.set_keys(screen_name, entry_name, &keys, &mut problems);
+            }
+        }
+
+        for (screen_name, overrides) in keybindings.iter() {
+            if !keymap
+                .screens
+                .iter()
+                .any(|screen_keymap| screen_keymap.screen.get_name() == screen_name)
+            {
+                problems.push(format!("keybindings.{} is not a screen", screen_name));
+                continue;
+            }
+            for (entry_name, key_list) in overrides.iter() {
+                let keys: Vec<String> = key_list.get_keys().into_iter().map(String::from).collect();
+                keymap.set_keys(screen_name, entry_name, &keys, &mut problems);
+            }
+        }
+
+        problems.extend(keymap.get_conflicts());
+        if problems.is_empty() {
+            Ok(keymap)
+        } else {
+            Err(TuduError::validation(&problems.join(", ")))
+        }
+    }
+
+    fn set_keys(
+        &mut self,
+        screen_name: &str,
+        entry_name: &str,
+        keys: &[String],
+        problems: &mut Vec<String>,
+    ) {
+        let path = format!("keybindings.{}.{}", screen_name, entry_name);
+        let entry = self
+            .screens
+            .iter_mut()
+            .filter(|screen_keymap| screen_keymap.screen.get_name() == screen_name)
+            .flat_map(|screen_keymap| screen_keymap.entries.iter_mut())
+            .find(|entry| entry.name == entry_name);
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                problems.push(format!("{} is not an action", path));
+                return;
+            }
+        };
+
+        let mut sequences = Vec::new();
+        for key in keys.iter() {
+            match parse_sequence(key) {
+                Ok(sequence) => sequences.push(sequence),
+                Err(e) => problems.push(format!("{}: {}", path, e.user_message())),
+            }
+        }
+        entry.keys = sequences;
+    }
+
+    pub fn get_entries(&self, screen: &Screen) -> &[KeyEntry] {
+        self.screens
+            .iter()
+            .find(|screen_keymap| &screen_keymap.screen == screen)
+            .map_or(&[], |screen_keymap| screen_keymap.entries.as_slice())
+    }
+
+    pub fn find_sequence(&self, screen: &Screen, keys: &[KeyBinding]) -> SequenceMatch {
+        let entries = self.get_entries(screen);
+        let sequences = || {
+            entries
+                .iter()
+                .flat_map(|entry| entry.keys.iter().map(move |sequence| (entry, sequence)))
+        };
+
+        if let Some((entry, _)) = sequences().find(|(_, sequence)| sequence.as_slice() == keys) {
+            return SequenceMatch::Exact(entry.action.clone());
+        }
+        if sequences().any(|(_, sequence)| sequence.starts_with(keys)) {
+            return SequenceMatch::Prefix;
+        }
+        SequenceMatch::Unbound
+    }
+
+    // Every entry including the hidden ones, grouped by the config table of its screen
+    pub fn get_help_sections(&self) -> Vec<(Screen, Vec<String>)> {
+        self.screens
+            .iter()
+            .map(|screen_keymap| {
+                let lines = screen_keymap
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        let keys = if entry.keys.is_empty() {
+                            String::from("unbound")
+                        } else {
+                            entry.get_keys_text()
+                        };
+                        format!("  {:<16}{:<18}{}", keys, entry.name, entry.label)
+                    })
+                    .collect();
+                (screen_keymap.screen, lines)
+            })
+            .collect()
+    }
+
+    // Line of the help text where the section of the screen starts
+    pub fn get_help_offset(&self, screen: &Screen) -> usize {
+        self.get_help_sections()
+            .iter()
+            .take_while(|(section_screen, _)| section_screen != screen)
+            .map(|(_, lines)| lines.len() + 2)
+            .sum()
+    }
+
+    pub fn get_help_length(&self) -> usize {
+        self.get_help_sections()
+            .iter()
+            .map(|(_, lines)| lines.len() + 2)
+            .sum()
+    }
+
+    pub fn get_action(&self, screen: &Screen, name: &str) -> Option<&Action> {
+        self.get_entries(screen)
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| &entry.action)
+    }
+
+    fn get_legend_items(&self, screen: &Screen) -> Vec<(String, &Action)> {
+        self.get_entries(screen)
+            .iter()
+            .filter(|entry| entry.is_in_legend && !entry.keys.is_empty())
+            .map(|entry| {
+                let text = format!("{}: {}", entry.get_keys_text(), entry.label);
+                (text, &entry.action)
+            })
+            .collect()
+    }
+
+    pub fn get_legend(&self, screen: &Screen) -> String {
+        self.get_legend_items(screen)
+            .into_iter()
+            .map(|(text, _)| text)
+            .collect::<Vec<String>>()
+            .join(LEGEND_SEPARATOR)
+    }
+
+    // The column is counted from where the legend starts, separators match nothing
+    pub fn get_legend_action(&self, screen: &Screen, column: usize) -> Option<&Action> {
+        let mut start = 0;
+        for (text, action) in self.get_legend_items(screen) {
+            let end = start + text.chars().count();
+            if column < end {
+                return (column >= start).then_some(action);
+            }
+            start = end + LEGEND_SEPARATOR.len();
+        }
+        None
+    }
+
+    fn get_conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+
+        for screen_keymap in self.screens.iter() {
+            let screen_name = screen_keymap.screen.get_name();
+            let is_text_input = TEXT_INPUT_SCREENS.contains(&screen_keymap.screen);
+            let mut seen: Vec<(&KeySequence, &str)> = Vec::new();
+
+            for entry in screen_keymap.entries.iter() {
+                for sequence in entry.keys.iter() {
+                    if is_text_input && sequence.first().is_some_and(KeyBinding::is_plain_char) {
+                        conflicts.push(format!(
+                            "keybindings.{}.{}: {} is needed for typing",
+                            screen_name,
+                            entry.name,
+                            format_sequence(sequence)
+                        ));
+                    }
+                    for (other_sequence, other) in seen.iter() {
+                        if *other_sequence == sequence {
+                            conflicts.push(format!(
+                                "keybindings.{}: {} and {} both use {}",
+                                screen_name,
+                                other,
+                                entry.name,
+                                format_sequence(sequence)
+                            ));
+                        } else if other_sequence.starts_with(sequence)
+                            || sequence.starts_with(other_sequence)
+                        {
+                            conflicts.push(format!(
+                                "keybindings.{}: {} ({}) and {} ({}) start the same way",
+                                screen_name,
+                                other,
+                                format_sequence(other_sequence),
+                                entry.name,
+                                format_sequence(sequence)
+                            ));
+                        }
+                    }
+                    seen.push((sequence, entry.name));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedKeys {
+    Pending,
+    Matched(Action, Option<usize>),
+    Unbound,
+}
+
+// Collects counts and multi-key sequences like "5j" or "dd" until they resolve to an action
+#[derive(Debug, Default)]
+pub struct KeySequenceParser {
+    count: Option<usize>,
+    keys: KeySequence,
+}
+
+impl KeySequenceParser {
+    pub fn push(&mut self, keymap: &Keymap, screen: &Screen, key: &KeyEvent) -> ParsedKeys {
+        let binding = KeyBinding::new(key.code, key.modifiers);
+        let is_pending = self.count.is_some() || !self.keys.is_empty();
+
+        if is_pending && binding.code == KeyCode::Esc {
+            self.reset();
+            return ParsedKeys::Pending;
+        }
+
+        if let (KeyCode::Char(digit @ '0'..='9'), true) =
+            (binding.code, binding.modifiers.is_empty())
+        {
+            let is_count_allowed = self.keys.is_empty()
+                && !TEXT_INPUT_SCREENS.contains(screen)
+                && (self.count.is_some() || digit != '0')
+                && keymap.find_sequence(screen, &[binding]) == SequenceMatch::Unbound;
+            if is_count_allowed {
+                let value = digit.to_digit(10).unwrap_or_default() as usize;
+                let count = self.count.unwrap_or_default() * 10 + value;
+                self.count = Some(count.min(MAX_COUNT));
+                return ParsedKeys::Pending;
+            }
+        }
+
+        self.keys.push(binding);
+        match keymap.find_sequence(screen, &self.keys) {
+            SequenceMatch::Exact(action) => {
+                let count = self.count;
+                self.reset();
+                ParsedKeys::Matched(action, count)
+            }
+            SequenceMatch::Prefix => ParsedKeys::Pending,
+            // A broken sequence is dropped, a lone unbound key is left to the screen
+            SequenceMatch::Unbound => {
+                self.reset();
+                if is_pending {
+                    ParsedKeys::Pending
+                } else {
+                    ParsedKeys::Unbound
+                }
+            }
+        }
+    }
+
+    pub fn get_pending(&self) -> String {
+        let count = self
+            .count
+            .map(|count| count.to_string())
+            .unwrap_or_default();
+        format!("{}{}", count, format_sequence(&self.keys))
+    }
+
+    pub fn reset(&mut self) {
+        self.count = None;
+        self.keys.clear();
+    }
+}
+
+// (screen, action, keys) replacing the default keys when the vim preset is active
+const VIM_OVERRIDES: [(&str, &str, &[&str]); 31] = [
+    ("main", "exit", &["q"]),
+    ("main", "add", &["o", "a"]),
+    ("main", "remove", &["d d", "x"]),
+    ("main", "up", &["k", "up"]),
+    ("main", "down", &["j", "down"]),
+    ("main", "first", &["g g", "home"]),
+    ("main", "last", &["G", "end"]),
+    ("main", "page_up", &["ctrl+b", "pgup"]),
+    ("main", "page_down", &["ctrl+f", "pgdn"]),
+    ("main", "edit", &["i"]),
+    ("main", "change", &["c w", "c c"]),
+    ("main", "import", &["I"]),
+    ("main", "search", &["/"]),
+    ("main", "search_next", &["n"]),
+    ("main", "search_previous", &["N"]),
+    ("main", "command", &[":"]),
+    ("backups", "back", &["esc", "q"]),
+    ("backups", "up", &["k", "up"]),
+    ("backups", "down", &["j", "down"]),
+    ("backups", "new_backup", &["o"]),
+    ("messages", "back", &["esc", "q"]),
+    ("messages", "up", &["k", "up"]),
+    ("messages", "down", &["j", "down"]),
+    ("stats", "back", &["esc", "q"]),
+    ("history", "back", &["esc", "q"]),
+    ("history", "up", &["k", "up"]),
+    ("history", "down", &["j", "down"]),
+    ("focus", "back", &["esc", "q"]),
+    ("help", "close", &["esc", "?", "q"]),
+    ("help", "up", &["k", "up"]),
+    ("help", "down", &["j", "down"]),
+];
+
+fn entry(name: &'static str, label: &'static str, keys: &[&str], action: Action) -> KeyEntry {
+    KeyEntry {
+        name,
+        label,
+        keys: keys
+            .iter()
+            .filter_map(|key| parse_sequence(key).ok())
+            .collect(),
+        action,
+        is_in_legend: true,
+    }
+}
+
+fn hidden_entry(
+    name: &'static str,
+    label: &'static str,
+    keys: &[&str],
+    action: Action,
+) -> KeyEntry {
+    KeyEntry {
+        is_in_legend: false,
+        ..entry(name, label, keys, action)
+    }
+}
+
+fn get_input_entries(
+    cancel: Action,
+    confirm_label: &'static str,
+    confirm: Action,
+) -> Vec<KeyEntry> {
+    vec![
+        entry("cancel", "Cancel", &["esc"], cancel),
+        entry("confirm", confirm_label, &["enter"], confirm),
+        hidden_entry(
+            "delete_char",
+            "Delete character",
+            &["backspace"],
+            Action::RemoveChar,
+        ),
+    ]
+}
+
+fn get_default_screens() -> Vec<ScreenKeymap> {
+    let mut screens = vec![
+        ScreenKeymap {
+            screen: Screen::Main,
+            entries: vec![
+                entry("exit", "Exit", &["esc"], Action::Exit),
+                entry("add", "Add", &["a"], Action::OpenAddScreen),
+                entry("remove", "Remove", &["x"], Action::RemoveTask),
+                entry(
+                    "toggle",
+                    "Check/Uncheck",
+                    &["enter"],
+                    Action::ToggleTaskStatus,
+                ),
+                entry("up", "Up", &["up"], Action::MenuUp),
+                entry("down", "Down", &["down"], Action::MenuDown),
+                entry("mark", "Mark", &["space"], Action::ToggleMark),
+                hidden_entry("visual", "Mark range", &["v"], Action::ToggleVisualMode),
+                hidden_entry(
+                    "mark_matching",
+                    "Mark matches",
+                    &["*"],
+                    Action::MarkMatching,
+                ),
+                entry("undo", "Undo", &["u"], Action::Undo),
+                hidden_entry(
+                    "move",
+                    "Move to list",
+                    &["M"],
+                    Action::PromptCommand(String::from("move ")),
+                ),
+                hidden_entry(
+                    "tag",
+                    "Tag",
+                    &["#"],
+                    Action::PromptCommand(String::from("tag ")),
+                ),
+                hidden_entry(
+                    "priority",
+                    "Set priority",
+                    &["P"],
+                    Action::PromptCommand(String::from("priority ")),
+                ),
+                hidden_entry("first", "First task", &["home"], Action::MenuFirst),
+                hidden_entry("last", "Last task", &["end"], Action::MenuLast),
+                hidden_entry("page_up", "Page up", &["pgup"], Action::MenuPageUp),
+                hidden_entry("page_down", "Page down", &["pgdn"], Action::MenuPageDown),
+                entry("edit", "Edit", &["ctrl+e"], Action::OpenEditScreen),
+                hidden_entry("change", "Change title", &[], Action::OpenChangeScreen),
+                entry("export", "Export", &["e"], Action::OpenExportScreen),
+                entry("import", "Import ics", &["i"], Action::OpenImportScreen),
+                entry("restore", "Restore", &["r"], Action::OpenRestoreScreen),
+                entry("backups", "Backups", &["b"], Action::OpenBackupsScreen),
+                entry("messages", "Messages", &["m"], Action::OpenMessagesScreen),
+                hidden_entry("theme", "Next theme", &["t"], Action::SwitchTheme),
+                hidden_entry("stats", "Statistics", &["s"], Action::OpenStatsScreen),
+                hidden_entry("history", "Task history", &["h"], Action::OpenHistoryScreen),
+                hidden_entry("timer", "Start/Stop timer", &["T"], Action::ToggleTimer),
+                hidden_entry("focus", "Focus timer", &["f"], Action::OpenFocusScreen),
+                entry("search", "Search", &["/"], Action::OpenSearchScreen),
+                hidden_entry("search_next", "Next match", &["n"], Action::SearchNext),
+                hidden_entry(
+                    "search_previous",
+                    "Previous match",
+                    &["N"],
+                    Action::SearchPrevious,
+                ),
+                entry("command", "Command", &[":"], Action::OpenCommandLine),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Add,
+            entries: vec![
+                entry("cancel", "Cancel", &["esc"], Action::CancelAddTask),
+                entry("save", "Save", &["enter"], Action::AddTask),
+                hidden_entry(
+                    "delete_char",
+                    "Delete character",
+                    &["backspace"],
+                    Action::RemoveChar,
+                ),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Greetings,
+            entries: vec![
+                entry("exit", "Cancel", &["esc"], Action::Exit),
+                entry("confirm", "Enter", &["enter"], Action::SubmitMasterKey),
+                hidden_entry(
+                    "delete_char",
+                    "Delete character",
+                    &["backspace"],
+                    Action::RemoveMaskedChar,
+                ),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Export,
+            entries: vec![
+                entry("cancel", "Cancel", &["esc"], Action::CancelExport),
+                entry("export", "Export", &["enter"], Action::ConfirmExport),
+                entry("complete", "Complete", &["tab"], Action::CompletePath),
+                entry(
+                    "switch_format",
+                    "Format",
+                    &["up", "down"],
+                    Action::SwitchExportFormat,
+                ),
+                hidden_entry(
+                    "delete_char",
+                    "Delete character",
+                    &["backspace"],
+                    Action::RemoveChar,
+                ),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Import,
+            entries: vec![
+                entry("cancel", "Cancel", &["esc"], Action::CancelImport),
+                entry("import", "Import", &["enter"], Action::ConfirmImport),
+                entry("complete", "Complete", &["tab"], Action::CompletePath),
+                hidden_entry(
+                    "delete_char",
+                    "Delete character",
+                    &["backspace"],
+                    Action::RemoveChar,
+                ),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Restore,
+            entries: vec![
+                entry("cancel", "Cancel", &["esc"], Action::CancelRestore),
+                entry("restore", "Restore", &["enter"], Action::ConfirmRestore),
+                entry("complete", "Complete", &["tab"], Action::CompletePath),
+                hidden_entry(
+                    "delete_char",
+                    "Delete character",
+                    &["backspace"],
+                    Action::RemoveChar,
+                ),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Passphrase,
+            entries: vec![
+                entry("cancel", "Cancel", &["esc"], Action::CancelPassphrase),
+                entry("confirm", "Enter", &["enter"], Action::ConfirmPassphrase),
+                hidden_entry(
+                    "delete_char",
+                    "Delete character",
+                    &["backspace"],
+                    Action::RemovePassphraseChar,
+                ),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Backups,
+            entries: vec![
+                entry("back", "Back", &["esc"], Action::CloseBackupsScreen),
+                entry("restore", "Restore", &["enter"], Action::RestoreBackup),
+                entry("new_backup", "New backup", &["n"], Action::CreateBackup),
+                entry("up", "Up", &["up"], Action::BackupMenuUp),
+                entry("down", "Down", &["down"], Action::BackupMenuDown),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Messages,
+            entries: vec![
+                entry("back", "Back", &["esc"], Action::CloseMessagesScreen),
+                entry("up", "Up", &["up"], Action::MessagesUp),
+                entry("down", "Down", &["down"], Action::MessagesDown),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Stats,
+            entries: vec![entry("back", "Back", &["esc"], Action::CloseStatsScreen)],
+        },
+        ScreenKeymap {
+            screen: Screen::History,
+            entries: vec![
+                entry("back", "Back", &["esc"], Action::CloseHistoryScreen),
+                entry("up", "Up", &["up"], Action::HistoryUp),
+                entry("down", "Down", &["down"], Action::HistoryDown),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Focus,
+            entries: vec![
+                entry("back", "Stop", &["esc"], Action::CloseFocusScreen),
+                entry(
+                    "pause",
+                    "Pause/Resume",
+                    &["space"],
+                    Action::ToggleFocusPause,
+                ),
+                entry("skip", "Skip", &["n"], Action::SkipFocusPhase),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Search,
+            entries: get_input_entries(Action::CancelSearch, "Search", Action::ConfirmSearch),
+        },
+        ScreenKeymap {
+            screen: Screen::Command,
+            entries: [
+                get_input_entries(Action::CancelCommand, "Run", Action::ExecuteCommand),
+                vec![
+                    entry("complete", "Complete", &["tab"], Action::CompleteCommand),
+                    entry("history_up", "Previous", &["up"], Action::CommandHistoryUp),
+                    entry(
+                        "history_down",
+                        "Next",
+                        &["down"],
+                        Action::CommandHistoryDown,
+                    ),
+                ],
+            ]
+            .concat(),
+        },
+        ScreenKeymap {
+            screen: Screen::Confirm,
+            entries: vec![
+                entry(
+                    "confirm",
+                    "Yes",
+                    &["y", "enter"],
+                    Action::AcceptConfirmation,
+                ),
+                entry("cancel", "No", &["n", "esc"], Action::RejectConfirmation),
+            ],
+        },
+        ScreenKeymap {
+            screen: Screen::Help,
+            entries: vec![
+                entry("close", "Close", &["esc", "?"], Action::CloseHelp),
+                entry("up", "Up", &["up"], Action::ScrollHelp(-1)),
+                entry("down", "Down", &["down"], Action::ScrollHelp(1)),
+                hidden_entry(
+                    "page_up",
+                    "Page up",
+                    &["pgup"],
+                    Action::ScrollHelp(-HELP_PAGE),
+                ),
+                hidden_entry(
+                    "page_down",
+                    "Page down",
+                    &["pgdn"],
+                    Action::ScrollHelp(HELP_PAGE),
+                ),
+            ],
+        },
+    ];
+
+    // Help is reachable from every screen, `?` only where it is not typed
+    for screen_keymap in screens.iter_mut() {
+        if screen_keymap.screen == Screen::Help {
+            continue;
+        }
+        let help = if TEXT_INPUT_SCREENS.contains(&screen_keymap.screen) {
+            hidden_entry("help", "Help", &["f1"], Action::OpenHelp)
+        } else {
+            entry("help", "Help", &["?", "f1"], Action::OpenHelp)
+        };
+        screen_keymap.entries.insert(1, help);
+    }
+    screens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeyList;
+    use std::collections::BTreeMap;
+
+    fn press(parser: &mut KeySequenceParser, keymap: &Keymap, keys: &str) -> ParsedKeys {
+        let mut result = ParsedKeys::Unbound;
+        for ch in keys.chars() {
+            let modifiers = if ch.is_ascii_uppercase() {
+                KeyModifiers::SHIFT
+            } else {
+                KeyModifiers::NONE
+            };
+            result = parser.push(
+                keymap,
+                &Screen::Main,
+                &KeyEvent::new(KeyCode::Char(ch), modifiers),
+            );
+        }
+        result
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let binding = KeyBinding::parse("ctrl+a").expect("Could not parse key");
+        assert_eq!(binding.code, KeyCode::Char('a'));
+        assert_eq!(binding.modifiers, KeyModifiers::CONTROL);
+        assert_eq!(binding.to_string(), "ctrl+a");
+
+        let binding = KeyBinding::parse("shift+g").expect("Could not parse key");
+        assert_eq!(
+            binding,
+            KeyBinding::parse("G").expect("Could not parse key")
+        );
+        assert!(binding.matches(&KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+
+        assert_eq!(
+            KeyBinding::parse("pgdn").expect("Could not parse key").code,
+            KeyCode::PageDown
+        );
+        assert_eq!(
+            KeyBinding::parse("f5").expect("Could not parse key").code,
+            KeyCode::F(5)
+        );
+        assert_eq!(
+            KeyBinding::parse("ctrl++")
+                .expect("Could not parse key")
+                .code,
+            KeyCode::Char('+')
+        );
+        assert!(KeyBinding::parse("hyper+a").is_err());
+        assert!(KeyBinding::parse("nothing").is_err());
+        assert!(KeyBinding::parse("f13").is_err());
+
+        let sequence = parse_sequence("g g").expect("Could not parse sequence");
+        assert_eq!(sequence.len(), 2);
+        assert_eq!(format_sequence(&sequence), "gg");
+        assert!(parse_sequence(" ").is_err());
+    }
+
+    #[test]
+    fn test_default_keymap() {
+        let keymap = Keymap::default();
+        assert!(keymap.get_conflicts().is_empty());
+        assert_eq!(keymap.get_entries(&Screen::Main).len(), 34);
+        assert!(keymap
+            .get_entries(&Screen::Main)
+            .iter()
+            .all(|entry| entry.name == "change" || !entry.keys.is_empty()));
+
+        let add = KeyBinding::parse("a").expect("Could not parse key");
+        assert_eq!(
+            keymap.find_sequence(&Screen::Main, &[add]),
+            SequenceMatch::Exact(Action::OpenAddScreen)
+        );
+        let ctrl_a = KeyBinding::parse("ctrl+a").expect("Could not parse key");
+        assert_eq!(
+            keymap.find_sequence(&Screen::Main, &[ctrl_a]),
+            SequenceMatch::Unbound
+        );
+
+        assert_eq!(
+            keymap.get_legend(&Screen::Export),
+            "esc: Cancel, enter: Export, tab: Complete, ↑/↓: Format"
+        );
+        assert!(keymap
+            .get_legend(&Screen::Backups)
+            .starts_with("esc: Back, ?/f1: Help, "));
+    }
+
+    #[test]
+    fn test_legend_action() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.get_action(&Screen::Help, "down"),
+            Some(&Action::ScrollHelp(1))
+        );
+        assert_eq!(keymap.get_action(&Screen::Confirm, "down"), None);
+
+        // "esc: Cancel, enter: Export, tab: Complete, ↑/↓: Format"
+        let action = |column| keymap.get_legend_action(&Screen::Export, column);
+        assert_eq!(action(0), Some(&Action::CancelExport));
+        assert_eq!(action(10), Some(&Action::CancelExport));
+        assert_eq!(action(11), None);
+        assert_eq!(action(13), Some(&Action::ConfirmExport));
+        assert_eq!(action(42), None);
+        assert_eq!(action(43), Some(&Action::SwitchExportFormat));
+        assert_eq!(action(53), Some(&Action::SwitchExportFormat));
+        assert_eq!(action(54), None);
+    }
+
+    #[test]
+    fn test_help_sections() {
+        let keymap = Keymap::default();
+        let sections = keymap.get_help_sections();
+        assert_eq!(sections.len(), keymap.screens.len());
+
+        let (screen, lines) = &sections[0];
+        assert_eq!(screen, &Screen::Main);
+        assert!(lines.iter().any(|line| line.starts_with("  home ")
+            && line.contains("first")
+            && line.ends_with("First task")));
+        assert!(lines.iter().any(|line| line.starts_with("  unbound ")));
+
+        assert_eq!(keymap.get_help_offset(&Screen::Main), 0);
+        assert_eq!(keymap.get_help_offset(&Screen::Add), lines.len() + 2);
+        assert!(keymap.get_help_offset(&Screen::Help) < keymap.get_help_length());
+    }
+
+    #[test]
+    fn test_from_config() {
+        let mut main = BTreeMap::new();
+        main.insert(String::from("add"), KeyList::One(String::from("ctrl+n")));
+        main.insert(
+            String::from("down"),
+            KeyList::Many(vec![String::from("down"), String::from("J")]),
+        );
+        let mut keybindings = BTreeMap::new();
+        keybindings.insert(String::from("main"), main);
+
+        let keymap = Keymap::from_config(KeymapPreset::Default, &keybindings)
+            .expect("Could not build keymap");
+        let ctrl_n = KeyBinding::parse("ctrl+n").expect("Could not parse key");
+        assert_eq!(
+            keymap.find_sequence(&Screen::Main, &[ctrl_n]),
+            SequenceMatch::Exact(Action::OpenAddScreen)
+        );
+        let add = KeyBinding::parse("a").expect("Could not parse key");
+        assert_eq!(
+            keymap.find_sequence(&Screen::Main, &[add]),
+            SequenceMatch::Unbound
+        );
+
+        let vim = Keymap::from_config(KeymapPreset::Vim, &keybindings)
+            .expect("Could not build vim keymap");
+        assert_eq!(
+            vim.find_sequence(&Screen::Main, &[ctrl_n]),
+            SequenceMatch::Exact(Action::OpenAddScreen)
+        );
+        let d = KeyBinding::parse("d").expect("Could not parse key");
+        assert_eq!(
+            vim.find_sequence(&Screen::Main, &[d]),
+            SequenceMatch::Prefix
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut main = BTreeMap::new();
+        main.insert(String::from("add"), KeyList::One(String::from("x")));
+        main.insert(String::from("first"), KeyList::One(String::from("g")));
+        main.insert(String::from("last"), KeyList::One(String::from("g g")));
+        main.insert(String::from("unknown"), KeyList::One(String::from("u")));
+        let mut add = BTreeMap::new();
+        add.insert(String::from("cancel"), KeyList::One(String::from("q")));
+        let mut keybindings = BTreeMap::new();
+        keybindings.insert(String::from("main"), main);
+        keybindings.insert(String::from("add"), add);
+        keybindings.insert(String::from("nowhere"), BTreeMap::new());
+
+        let message = Keymap::from_config(KeymapPreset::Default, &keybindings)
+            .expect_err("Keymap should conflict")
+            .user_message();
+        assert!(message.contains("keybindings.main: add and remove both use x"));
+        assert!(message.contains("keybindings.main: first (g) and last (gg) start the same way"));
+        assert!(message.contains("keybindings.main.unknown is not an action"));
+        assert!(message.contains("keybindings.add.cancel: q is needed for typing"));
+        assert!(message.contains("keybindings.nowhere is not a screen"));
+    }
+
+    #[test]
+    fn test_sequence_parser() {
+        let keymap = Keymap::from_config(KeymapPreset::Vim, &BTreeMap::new())
+            .expect("Could not build vim keymap");
+        assert!(keymap.get_conflicts().is_empty());
+        let mut parser = KeySequenceParser::default();
+
+        assert_eq!(
+            press(&mut parser, &keymap, "j"),
+            ParsedKeys::Matched(Action::MenuDown, None)
+        );
+        assert_eq!(press(&mut parser, &keymap, "1"), ParsedKeys::Pending);
+        assert_eq!(press(&mut parser, &keymap, "2d"), ParsedKeys::Pending);
+        assert_eq!(parser.get_pending(), "12d");
+        assert_eq!(
+            press(&mut parser, &keymap, "d"),
+            ParsedKeys::Matched(Action::RemoveTask, Some(12))
+        );
+        assert_eq!(
+            press(&mut parser, &keymap, "gg"),
+            ParsedKeys::Matched(Action::MenuFirst, None)
+        );
+        assert_eq!(
+            press(&mut parser, &keymap, "G"),
+            ParsedKeys::Matched(Action::MenuLast, None)
+        );
+        assert_eq!(
+            press(&mut parser, &keymap, "cw"),
+            ParsedKeys::Matched(Action::OpenChangeScreen, None)
+        );
+
+        // Broken sequences are dropped, unknown single keys are left to the screen
+        assert_eq!(press(&mut parser, &keymap, "dz"), ParsedKeys::Pending);
+        assert_eq!(parser.get_pending(), "");
+        assert_eq!(press(&mut parser, &keymap, "z"), ParsedKeys::Unbound);
+        assert_eq!(press(&mut parser, &keymap, "0"), ParsedKeys::Unbound);
+
+        assert_eq!(press(&mut parser, &keymap, "5"), ParsedKeys::Pending);
+        let result = parser.push(&keymap, &Screen::Main, &KeyEvent::from(KeyCode::Esc));
+        assert_eq!(result, ParsedKeys::Pending);
+        assert_eq!(parser.get_pending(), "");
+    }
+}
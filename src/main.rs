@@ -1,33 +1,94 @@
+pub mod backup;
+#[cfg(test)]
+mod bench;
+pub mod bundle;
+pub mod cache;
+pub mod cli;
 pub mod client;
+pub mod command;
+pub mod config;
+pub mod confirmation;
 pub mod constants;
 pub mod controller;
 pub mod csv;
+pub mod doctor;
 pub mod encdec;
+pub mod error;
+pub mod event;
 pub mod filesystem;
+pub mod history;
+pub mod ics;
+pub mod keymap;
+pub mod markdown;
+pub mod notification;
+pub mod pomodoro;
+pub mod restore;
 pub mod state;
+pub mod stats;
 pub mod task;
+pub mod theme;
+pub mod timer;
+pub mod undo;
 pub mod user;
 pub mod view;
+pub mod worker;
 
 use std::io::stdout;
+use std::path::Path;
+use std::process::ExitCode;
 
 use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
 use ratatui::prelude::{CrosstermBackend, Terminal};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+use error::{TuduError, TuduResult};
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e.user_message());
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> TuduResult<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (config_path, args) = cli::split_config_arg(&args)?;
+
+    if args.is_empty() {
+        run_app(config_path.as_deref())
+    } else {
+        let config = config::load_config(config_path.as_deref())?;
+        cli::run_command(&args, &config)
+    }
+}
+
+fn run_app(config_path: Option<&Path>) -> TuduResult<()> {
+    let map_error = |e| TuduError::io("Could not set up terminal", e);
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout())).map_err(map_error)?;
     let mut controller = controller::Controller::new();
 
-    stdout().execute(EnterAlternateScreen)?;
-    enable_raw_mode()?;
-    terminal.clear()?;
+    // An invalid config should not lock anyone out, fall back to defaults and say why
+    match config::load_config(config_path) {
+        Ok(config) => controller.state.set_config(config),
+        Err(e) => controller.state.set_error(e.user_message()),
+    }
+
+    stdout().execute(EnterAlternateScreen).map_err(map_error)?;
+    stdout().execute(EnableMouseCapture).map_err(map_error)?;
+    enable_raw_mode().map_err(map_error)?;
+    terminal.clear().map_err(map_error)?;
 
-    controller.run(&mut terminal)?;
+    // The terminal is restored before the error is printed
+    let result = controller.run(&mut terminal);
 
-    stdout().execute(LeaveAlternateScreen)?;
-    disable_raw_mode()?;
-    Ok(())
+    stdout().execute(DisableMouseCapture).map_err(map_error)?;
+    stdout().execute(LeaveAlternateScreen).map_err(map_error)?;
+    disable_raw_mode().map_err(map_error)?;
+    result
 }
@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::Path;
+
+use crate::error::{TuduError, TuduResult};
+use crate::task::Task;
+
+// Tasks become a checklist that renders on most markdown viewers
+pub fn write_tasks_into_markdown_file(task_list: &[Task], path: &Path) -> TuduResult<()> {
+    let mut content = String::from("# tudu\n\n");
+    for task in task_list.iter() {
+        let checkbox = if task.status == "completed" { 'x' } else { ' ' };
+        content.push_str(&format!("- [{}] {}\n", checkbox, task.title));
+    }
+
+    fs::write(path, content).map_err(|e| TuduError::export("Could not save md file", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::get_test_dir;
+
+    #[test]
+    fn test_write_file() {
+        let task_list = vec![
+            Task {
+                id: 0,
+                title: String::from("Title"),
+                status: String::from("completed"),
+                ..Default::default()
+            },
+            Task {
+                id: 1,
+                title: String::from("Other"),
+                status: String::from("in-progress"),
+                ..Default::default()
+            },
+        ];
+
+        let path = get_test_dir("markdown").join("tudu.md");
+        write_tasks_into_markdown_file(&task_list, &path).expect("Could not write md file");
+
+        let content = fs::read_to_string(&path).expect("Could not read md file");
+        assert_eq!(content, "# tudu\n\n- [x] Title\n- [ ] Other\n");
+    }
+}
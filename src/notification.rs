@@ -0,0 +1,44 @@
+use std::time::Instant;
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::constants::Severity;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub message: String,
+    pub severity: Severity,
+    pub created_at: NaiveDateTime,
+    pub shown_at: Instant,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, message: String) -> Self {
+        Notification {
+            message,
+            severity,
+            created_at: Local::now().naive_local(),
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.shown_at) >= self.severity.timeout()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry() {
+        let success = Notification::new(Severity::Success, String::from("Saved"));
+        let error = Notification::new(Severity::Error, String::from("Could not save"));
+        let later = success.shown_at + Severity::Success.timeout();
+
+        assert!(!success.is_expired(success.shown_at));
+        assert!(success.is_expired(later));
+        assert!(!error.is_expired(later));
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDateTime;
+
+use crate::config::PomodoroConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PomodoroPhase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl PomodoroPhase {
+    pub fn label(&self) -> &str {
+        match self {
+            PomodoroPhase::Work => "Work",
+            PomodoroPhase::ShortBreak => "Short break",
+            PomodoroPhase::LongBreak => "Long break",
+        }
+    }
+
+    pub fn get_length(&self, config: &PomodoroConfig) -> Duration {
+        let minutes = match self {
+            PomodoroPhase::Work => config.work_minutes,
+            PomodoroPhase::ShortBreak => config.short_break_minutes,
+            PomodoroPhase::LongBreak => config.long_break_minutes,
+        };
+        Duration::from_secs(minutes * 60)
+    }
+}
+
+// A finished work phase as stored in the pomodoros table
+#[derive(Debug, Clone, PartialEq)]
+pub struct PomodoroRecord {
+    pub task_id: i32,
+    pub minutes: u64,
+    pub completed_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pomodoro {
+    pub task_id: i32,
+    pub phase: PomodoroPhase,
+    pub phase_length: Duration,
+    pub phase_ends_at: Instant,
+    // The end of the phase moves forward by the paused time on resume
+    pub paused_at: Option<Instant>,
+    pub completed_count: u32,
+}
+
+impl Pomodoro {
+    pub fn new(task_id: i32, config: &PomodoroConfig, now: Instant) -> Self {
+        let phase_length = PomodoroPhase::Work.get_length(config);
+        Pomodoro {
+            task_id,
+            phase: PomodoroPhase::Work,
+            phase_length,
+            phase_ends_at: now + phase_length,
+            paused_at: None,
+            completed_count: 0,
+        }
+    }
+
+    pub fn get_remaining(&self, now: Instant) -> Duration {
+        self.phase_ends_at
+            .saturating_duration_since(self.paused_at.unwrap_or(now))
+    }
+
+    pub fn get_progress(&self, now: Instant) -> f64 {
+        if self.phase_length.is_zero() {
+            return 1.0;
+        }
+        let remaining = self.get_remaining(now).as_secs_f64();
+        (1.0 - remaining / self.phase_length.as_secs_f64()).clamp(0.0, 1.0)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    pub fn toggle_pause(&mut self, now: Instant) {
+        match self.paused_at.take() {
+            Some(paused_at) => self.phase_ends_at += now.saturating_duration_since(paused_at),
+            None => self.paused_at = Some(now),
+        }
+    }
+
+    // Called on every tick, returns the phase that just ran out
+    pub fn advance(&mut self, config: &PomodoroConfig, now: Instant) -> Option<PomodoroPhase> {
+        if self.is_paused() || now < self.phase_ends_at {
+            return None;
+        }
+
+        let finished_phase = self.phase;
+        let next_phase = match finished_phase {
+            PomodoroPhase::Work => {
+                self.completed_count += 1;
+                if self.completed_count % config.long_break_every == 0 {
+                    PomodoroPhase::LongBreak
+                } else {
+                    PomodoroPhase::ShortBreak
+                }
+            }
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => PomodoroPhase::Work,
+        };
+        self.start_phase(next_phase, config, now);
+        Some(finished_phase)
+    }
+
+    // A skipped work phase does not count as a pomodoro
+    pub fn skip(&mut self, config: &PomodoroConfig, now: Instant) {
+        let next_phase = match self.phase {
+            PomodoroPhase::Work => PomodoroPhase::ShortBreak,
+            PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => PomodoroPhase::Work,
+        };
+        self.start_phase(next_phase, config, now);
+    }
+
+    fn start_phase(&mut self, phase: PomodoroPhase, config: &PomodoroConfig, now: Instant) {
+        self.phase = phase;
+        self.phase_length = phase.get_length(config);
+        self.phase_ends_at = now + self.phase_length;
+        self.paused_at = None;
+    }
+}
+
+pub fn format_countdown(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pomodoro_cycle() {
+        let config = PomodoroConfig {
+            long_break_every: 2,
+            ..Default::default()
+        };
+        let minutes = |count: u64| Duration::from_secs(count * 60);
+        let start = Instant::now();
+        let mut pomodoro = Pomodoro::new(3, &config, start);
+
+        assert_eq!(pomodoro.advance(&config, start + minutes(10)), None);
+        assert_eq!(pomodoro.get_remaining(start + minutes(10)), minutes(15));
+        assert_eq!(pomodoro.get_progress(start + minutes(10)), 0.4);
+
+        // Ten paused minutes push the end of the phase back
+        pomodoro.toggle_pause(start + minutes(10));
+        assert_eq!(pomodoro.get_remaining(start + minutes(15)), minutes(15));
+        assert_eq!(pomodoro.advance(&config, start + minutes(30)), None);
+        pomodoro.toggle_pause(start + minutes(20));
+        assert_eq!(pomodoro.advance(&config, start + minutes(30)), None);
+
+        let now = start + minutes(35);
+        assert_eq!(pomodoro.advance(&config, now), Some(PomodoroPhase::Work));
+        assert_eq!(pomodoro.phase, PomodoroPhase::ShortBreak);
+        assert_eq!(pomodoro.completed_count, 1);
+        assert_eq!(pomodoro.get_remaining(now), minutes(5));
+
+        pomodoro.skip(&config, now);
+        assert_eq!(pomodoro.phase, PomodoroPhase::Work);
+        let now = now + minutes(25);
+        assert_eq!(pomodoro.advance(&config, now), Some(PomodoroPhase::Work));
+        assert_eq!(pomodoro.phase, PomodoroPhase::LongBreak);
+        assert_eq!(pomodoro.get_remaining(now), minutes(15));
+
+        pomodoro.skip(&config, now);
+        pomodoro.skip(&config, now);
+        assert_eq!(pomodoro.phase, PomodoroPhase::ShortBreak);
+        assert_eq!(pomodoro.completed_count, 2);
+        assert_eq!(
+            format_countdown(minutes(4) + Duration::from_secs(5)),
+            "04:05"
+        );
+    }
+}
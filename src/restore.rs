@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::NaiveDateTime;
+
+use crate::bundle::{BundleError, TableDump};
+use crate::client::{Client, TIMESTAMP_FORMAT};
+use crate::encdec::{decrypt, encrypt};
+use crate::error::TuduResult;
+use crate::history::{TaskEvent, TaskEventKind};
+use crate::pomodoro::PomodoroRecord;
+use crate::task::Task;
+use crate::timer::TimeEntry;
+
+// The secret belongs to the database the bundle was made from
+const IGNORED_TABLES: [&str; 1] = ["user"];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RestoreSummary {
+    pub restored_ids: Vec<i32>,
+    // Rows left out by table, tasks that already exist and rows that could not be read
+    pub skipped_counts: BTreeMap<String, usize>,
+}
+
+impl RestoreSummary {
+    pub fn get_skipped_count(&self) -> usize {
+        self.skipped_counts.values().sum()
+    }
+
+    pub fn format_skipped(&self) -> String {
+        self.skipped_counts
+            .iter()
+            .map(|(table, count)| {
+                let noun = if *count == 1 { "row" } else { "rows" };
+                format!("{} {} {}", count, table, noun)
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    fn skip(&mut self, table: &str) {
+        *self.skipped_counts.entry(String::from(table)).or_insert(0) += 1;
+    }
+}
+
+// Tasks get new ids, the rows that point at them follow through the old ids
+pub fn restore_tables(
+    client: &Client,
+    tables: &[TableDump],
+    master_key: &str,
+    existing_titles: &HashSet<String>,
+) -> TuduResult<RestoreSummary> {
+    let todos = tables
+        .iter()
+        .find(|table| table.name == "todos")
+        .ok_or(BundleError::Corrupted)?;
+    if todos.column_index("title").is_none() || todos.column_index("status").is_none() {
+        return Err(BundleError::Corrupted.into());
+    }
+
+    let mut summary = RestoreSummary::default();
+    let mut task_list = Vec::new();
+    let mut task_ids: HashSet<i32> = HashSet::new();
+    for row in todos.rows.iter() {
+        let task = match read_task(todos, row) {
+            Some(task)
+                if !existing_titles.contains(&task.title) && !task_ids.contains(&task.id) =>
+            {
+                task
+            }
+            _ => {
+                summary.skip(&todos.name);
+                continue;
+            }
+        };
+        task_ids.insert(task.id);
+        task_list.push(Task {
+            title: encrypt(&task.title, master_key),
+            ..task
+        });
+    }
+
+    let mut task_events = Vec::new();
+    let mut time_entries = Vec::new();
+    let mut pomodoros = Vec::new();
+    for table in tables.iter() {
+        if table.name == todos.name || IGNORED_TABLES.contains(&table.name.as_str()) {
+            continue;
+        }
+
+        // Rows of tasks that were not restored and of unknown tables are skipped too
+        for row in table.rows.iter() {
+            let task_id = get_cell(table, row, "task_id")
+                .and_then(|value| value.parse().ok())
+                .filter(|id: &i32| task_ids.contains(id));
+            let pushed = match (table.name.as_str(), task_id) {
+                ("task_events", Some(task_id)) => read_task_event(table, row, task_id, master_key)
+                    .map(|event| task_events.push(event)),
+                ("time_entries", Some(task_id)) => read_time_entry(table, row, task_id)
+                    .map(|time_entry| time_entries.push(time_entry)),
+                ("pomodoros", Some(task_id)) => {
+                    read_pomodoro(table, row, task_id).map(|pomodoro| pomodoros.push(pomodoro))
+                }
+                _ => None,
+            };
+            if pushed.is_none() {
+                summary.skip(&table.name);
+            }
+        }
+    }
+    summary.restored_ids =
+        client.import_rows(&task_list, &task_events, &time_entries, &pomodoros)?;
+
+    Ok(summary)
+}
+
+// Empty cells and columns the bundle does not have both read as missing
+fn get_cell<'a>(table: &TableDump, row: &'a [String], column: &str) -> Option<&'a str> {
+    table
+        .column_index(column)
+        .and_then(|index| row.get(index))
+        .map(String::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT).ok()
+}
+
+// A missing timestamp is fine, one that does not parse is not
+fn read_optional_timestamp(
+    table: &TableDump,
+    row: &[String],
+    column: &str,
+) -> Option<Option<NaiveDateTime>> {
+    match get_cell(table, row, column) {
+        Some(value) => parse_timestamp(value).map(Some),
+        None => Some(None),
+    }
+}
+
+fn read_task(table: &TableDump, row: &[String]) -> Option<Task> {
+    let title = get_cell(table, row, "title")?;
+    let status = get_cell(table, row, "status")
+        .filter(|status| *status == "completed" || *status == "in-progress")?;
+    Some(Task {
+        id: get_cell(table, row, "id").and_then(|id| id.parse().ok())?,
+        title: String::from(title),
+        status: String::from(status),
+        created_at: read_optional_timestamp(table, row, "created_at")?,
+        updated_at: read_optional_timestamp(table, row, "updated_at")?,
+        completed_at: read_optional_timestamp(table, row, "completed_at")?,
+        due: read_optional_timestamp(table, row, "due")?,
+        priority: get_cell(table, row, "priority").and_then(|value| value.parse().ok()),
+        recurrence: get_cell(table, row, "recurrence").map(String::from),
+        list: get_cell(table, row, "list").map(String::from),
+        tags: get_cell(table, row, "tags")
+            .map(|tags| {
+                tags.split(',')
+                    .filter(|tag| !tag.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+// Event titles are stored encrypted, bundles carry them decrypted when they could be
+fn read_task_event(
+    table: &TableDump,
+    row: &[String],
+    task_id: i32,
+    master_key: &str,
+) -> Option<TaskEvent> {
+    let kind = get_cell(table, row, "kind").and_then(TaskEventKind::from_name)?;
+    let title = get_cell(table, row, "title").unwrap_or_default();
+    let title = match decrypt(title, master_key) {
+        Ok(_) => String::from(title),
+        Err(_) => format!("{:?}", encrypt(title, master_key)),
+    };
+    Some(TaskEvent {
+        id: 0,
+        task_id,
+        kind,
+        title,
+        status: String::from(get_cell(table, row, "status").unwrap_or_default()),
+        created_at: read_optional_timestamp(table, row, "created_at")?,
+    })
+}
+
+// A timer that was still running in the bundle would run next to the current one
+fn read_time_entry(table: &TableDump, row: &[String], task_id: i32) -> Option<TimeEntry> {
+    Some(TimeEntry {
+        id: 0,
+        task_id,
+        started_at: get_cell(table, row, "started_at").and_then(parse_timestamp)?,
+        stopped_at: Some(get_cell(table, row, "stopped_at").and_then(parse_timestamp)?),
+    })
+}
+
+fn read_pomodoro(table: &TableDump, row: &[String], task_id: i32) -> Option<PomodoroRecord> {
+    Some(PomodoroRecord {
+        task_id,
+        minutes: get_cell(table, row, "minutes").and_then(|value| value.parse().ok())?,
+        completed_at: get_cell(table, row, "completed_at").and_then(parse_timestamp)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn get_table(name: &str, columns: &[&str], rows: &[&[&str]]) -> TableDump {
+        TableDump {
+            name: String::from(name),
+            columns: columns.iter().map(|column| String::from(*column)).collect(),
+            rows: rows
+                .iter()
+                .map(|row| row.iter().map(|cell| String::from(*cell)).collect())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_restore_tables() {
+        let mut client = Client::default();
+        client
+            .open_connection(PathBuf::new(), ":memory:")
+            .expect("Could not open connection");
+        client
+            .create_todos_table()
+            .expect("Could not create todos table");
+
+        let tables = vec![
+            get_table(
+                "todos",
+                &[
+                    "id",
+                    "title",
+                    "status",
+                    "created_at",
+                    "updated_at",
+                    "completed_at",
+                ],
+                &[
+                    &[
+                        "1",
+                        "apples",
+                        "completed",
+                        "2024-10-18 09:00:00",
+                        "2024-10-19 10:00:00",
+                        "2024-10-19 10:00:00",
+                    ],
+                    &["2", "bread", "in-progress", "2024-10-18 09:30:00", "", ""],
+                    &["3", "existing", "in-progress", "", "", ""],
+                    &["4", "lost", "archived", "", "", ""],
+                    &["5", "late", "in-progress", "yesterday", "", ""],
+                ],
+            ),
+            get_table(
+                "task_events",
+                &["id", "task_id", "kind", "title", "status", "created_at"],
+                &[
+                    &[
+                        "1",
+                        "1",
+                        "created",
+                        "apples",
+                        "in-progress",
+                        "2024-10-18 09:00:00",
+                    ],
+                    &[
+                        "2",
+                        "1",
+                        "status_changed",
+                        "apples",
+                        "completed",
+                        "2024-10-19 10:00:00",
+                    ],
+                    &[
+                        "3",
+                        "1",
+                        "archived",
+                        "apples",
+                        "completed",
+                        "2024-10-19 11:00:00",
+                    ],
+                    &[
+                        "4",
+                        "3",
+                        "created",
+                        "existing",
+                        "in-progress",
+                        "2024-10-18 08:00:00",
+                    ],
+                ],
+            ),
+            get_table(
+                "time_entries",
+                &["id", "task_id", "started_at", "stopped_at"],
+                &[
+                    &["1", "2", "2024-10-18 10:00:00", "2024-10-18 10:45:00"],
+                    &["2", "2", "2024-10-19 10:00:00", ""],
+                ],
+            ),
+            get_table(
+                "pomodoros",
+                &["id", "task_id", "minutes", "completed_at"],
+                &[&["1", "1", "25", "2024-10-19 09:30:00"]],
+            ),
+            get_table("user", &["id", "secret"], &[&["1", "SECRET"]]),
+            get_table("notes", &["id", "text"], &[&["1", "not a tudu table"]]),
+        ];
+        let existing_titles = HashSet::from([String::from("existing")]);
+
+        let summary = restore_tables(&client, &tables, "KEY", &existing_titles)
+            .expect("Could not restore tables");
+        assert_eq!(summary.restored_ids, vec![1, 2]);
+        assert_eq!(summary.get_skipped_count(), 7);
+        assert_eq!(
+            summary.format_skipped(),
+            "1 notes row, 2 task_events rows, 1 time_entries row, 3 todos rows"
+        );
+
+        let tasks = client.get_tasks().expect("Could not get tasks");
+        assert_eq!(
+            decrypt(&tasks[0].title, "KEY").ok(),
+            Some(String::from("apples"))
+        );
+        assert_eq!(tasks[0].created_at, parse_timestamp("2024-10-18 09:00:00"));
+        assert_eq!(
+            tasks[0].completed_at,
+            parse_timestamp("2024-10-19 10:00:00")
+        );
+        assert_eq!(tasks[1].completed_at, None);
+
+        let task_events = client
+            .get_task_events(Some(1), 10)
+            .expect("Could not get task events");
+        // The restore itself is recorded as a new creation
+        assert_eq!(task_events.len(), 3);
+        assert_eq!(
+            task_events[0].created_at,
+            parse_timestamp("2024-10-19 10:00:00")
+        );
+        assert_eq!(
+            decrypt(&task_events[0].title, "KEY").ok(),
+            Some(String::from("apples"))
+        );
+
+        let time_entries = client
+            .get_time_entries()
+            .expect("Could not get time entries");
+        assert_eq!(time_entries.len(), 1);
+        assert_eq!(time_entries[0].task_id, 2);
+        assert_eq!(
+            time_entries[0].stopped_at,
+            parse_timestamp("2024-10-18 10:45:00")
+        );
+        assert_eq!(
+            client
+                .get_pomodoro_counts()
+                .expect("Could not get pomodoros"),
+            HashMap::from([(1, 1)])
+        );
+
+        let result = restore_tables(&client, &tables[1..], "KEY", &existing_titles);
+        assert!(result.is_err());
+
+        // A failure on the last table leaves no task of the bundle behind
+        client
+            .get_connection()
+            .expect("Could not get connection")
+            .execute("DROP TABLE pomodoros", ())
+            .expect("Could not drop pomodoros");
+        let result = restore_tables(&client, &tables, "KEY", &HashSet::new());
+        assert!(result.is_err());
+        assert_eq!(client.get_tasks().expect("Could not get tasks").len(), 2);
+    }
+}
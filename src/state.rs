@@ -1,16 +1,72 @@
-use crate::constants::Screen;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::backup::BackupInfo;
+use crate::config::Config;
+use crate::confirmation::Confirmation;
+use crate::constants::{
+    ExportFormat, PassphrasePurpose, Screen, Severity, SortField, MAX_COMMAND_HISTORY,
+    MAX_NOTIFICATION_HISTORY, MAX_UNDO_HISTORY,
+};
+use crate::history::TaskEvent;
+use crate::keymap::Keymap;
+use crate::notification::Notification;
+use crate::pomodoro::Pomodoro;
+use crate::stats::Stats;
 use crate::task::Task;
+use crate::theme::Theme;
+use crate::timer::TimeEntry;
+use crate::undo::UndoEntry;
+use crate::worker::Loading;
 
 #[derive(Debug, Default)]
 pub struct State {
-    pub task_list: Vec<Task>,
+    // Ids of every task in list order, only the rows around the viewport are loaded
+    pub task_order: Vec<i32>,
+    pub loaded_tasks: HashMap<i32, Task>,
     pub is_running: bool,
     pub screen: Screen,
     pub line: i32,
     pub input: String,
-    pub error: String,
+    pub notification: Option<Notification>,
+    pub notification_history: Vec<Notification>,
+    pub history_line: i32,
     pub is_first_time: bool,
     pub master_key: String,
+    pub export_format: ExportFormat,
+    pub is_overwrite_pending: bool,
+    pub passphrase: String,
+    pub passphrase_purpose: PassphrasePurpose,
+    pub backup_list: Vec<BackupInfo>,
+    pub backup_line: i32,
+    pub config: Config,
+    pub keymap: Keymap,
+    pub editing_task_id: Option<i32>,
+    pub search_query: String,
+    pub pending_keys: String,
+    pub command_history: Vec<String>,
+    pub command_history_line: Option<usize>,
+    pub sort_field: SortField,
+    pub previous_screen: Screen,
+    pub help_line: i32,
+    pub confirmation: Option<Confirmation>,
+    pub list_offset: i32,
+    pub page_size: i32,
+    pub selected_ids: BTreeSet<i32>,
+    pub visual_anchor: Option<i32>,
+    pub undo_history: Vec<UndoEntry>,
+    pub theme: Theme,
+    pub theme_name: String,
+    pub stats: Stats,
+    pub task_events: Vec<TaskEvent>,
+    pub task_events_line: i32,
+    // Latest events of the task in the detail panel, with the id and version they were loaded for
+    pub detail_events: Vec<TaskEvent>,
+    pub detail_task: Option<(i32, i64)>,
+    pub time_entries: Vec<TimeEntry>,
+    pub pomodoro: Option<Pomodoro>,
+    pub pomodoro_counts: HashMap<i32, u32>,
+    pub is_bell_pending: bool,
+    pub loading: Option<Loading>,
 }
 
 impl State {
@@ -18,12 +74,334 @@ impl State {
         State::default()
     }
 
-    pub fn get_error(&self) -> &String {
-        &self.error
+    pub fn get_notification(&self) -> Option<&Notification> {
+        self.notification.as_ref()
+    }
+
+    // Every notification is kept in the history, newest first
+    pub fn set_notification(&mut self, severity: Severity, message: String) {
+        let notification = Notification::new(severity, message);
+        self.notification_history.insert(0, notification.clone());
+        self.notification_history.truncate(MAX_NOTIFICATION_HISTORY);
+        self.notification = Some(notification);
+    }
+
+    pub fn clear_notification(&mut self) {
+        self.notification = None;
+    }
+
+    pub fn get_message(&self) -> &str {
+        self.notification
+            .as_ref()
+            .map_or("", |notification| notification.message.as_str())
     }
 
     pub fn set_error(&mut self, error: String) {
-        self.error = error;
+        self.set_notification(Severity::Error, error);
+    }
+
+    pub fn get_notification_history(&self) -> &Vec<Notification> {
+        &self.notification_history
+    }
+
+    pub fn get_history_line(&self) -> i32 {
+        self.history_line
+    }
+
+    pub fn set_history_line(&mut self, history_line: i32) {
+        self.history_line = history_line;
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    // The keymap and theme follow the config, a config that passed validation always builds them
+    pub fn set_config(&mut self, config: Config) {
+        self.keymap =
+            Keymap::from_config(config.keymap_preset, &config.keybindings).unwrap_or_default();
+        let theme_name = config.get_theme_name();
+        self.theme = config.get_theme(&theme_name).unwrap_or_default();
+        self.theme_name = theme_name;
+        self.config = config;
+    }
+
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn get_theme_name(&self) -> &String {
+        &self.theme_name
+    }
+
+    pub fn set_theme(&mut self, theme_name: &str, theme: Theme) {
+        self.theme_name = String::from(theme_name);
+        self.theme = theme;
+    }
+
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn get_editing_task_id(&self) -> Option<i32> {
+        self.editing_task_id
+    }
+
+    pub fn set_editing_task_id(&mut self, editing_task_id: Option<i32>) {
+        self.editing_task_id = editing_task_id;
+    }
+
+    pub fn get_search_query(&self) -> &String {
+        &self.search_query
+    }
+
+    pub fn set_search_query(&mut self, search_query: &str) {
+        self.search_query = String::from(search_query);
+    }
+
+    pub fn get_pending_keys(&self) -> &String {
+        &self.pending_keys
+    }
+
+    pub fn set_pending_keys(&mut self, pending_keys: String) {
+        self.pending_keys = pending_keys;
+    }
+
+    pub fn get_command_history(&self) -> &Vec<String> {
+        &self.command_history
+    }
+
+    // Oldest first, repeating the previous command does not add a line
+    pub fn push_command_history(&mut self, command: &str) {
+        if command.is_empty()
+            || self
+                .command_history
+                .last()
+                .is_some_and(|last| last == command)
+        {
+            return;
+        }
+        self.command_history.push(String::from(command));
+        if self.command_history.len() > MAX_COMMAND_HISTORY {
+            self.command_history.remove(0);
+        }
+    }
+
+    pub fn get_command_history_line(&self) -> Option<usize> {
+        self.command_history_line
+    }
+
+    pub fn set_command_history_line(&mut self, command_history_line: Option<usize>) {
+        self.command_history_line = command_history_line;
+    }
+
+    pub fn get_sort_field(&self) -> SortField {
+        self.sort_field
+    }
+
+    pub fn set_sort_field(&mut self, sort_field: SortField) {
+        self.sort_field = sort_field;
+    }
+
+    pub fn get_previous_screen(&self) -> &Screen {
+        &self.previous_screen
+    }
+
+    pub fn set_previous_screen(&mut self, previous_screen: Screen) {
+        self.previous_screen = previous_screen;
+    }
+
+    pub fn get_help_line(&self) -> i32 {
+        self.help_line
+    }
+
+    pub fn set_help_line(&mut self, help_line: i32) {
+        self.help_line = help_line;
+    }
+
+    pub fn get_confirmation(&self) -> Option<&Confirmation> {
+        self.confirmation.as_ref()
+    }
+
+    pub fn set_confirmation(&mut self, confirmation: Option<Confirmation>) {
+        self.confirmation = confirmation;
+    }
+
+    pub fn take_confirmation(&mut self) -> Option<Confirmation> {
+        self.confirmation.take()
+    }
+
+    pub fn get_list_offset(&self) -> i32 {
+        self.list_offset
+    }
+
+    pub fn set_list_offset(&mut self, list_offset: i32) {
+        self.list_offset = list_offset;
+    }
+
+    // Number of tasks that fit on screen, at least one before the first draw
+    pub fn get_page_size(&self) -> i32 {
+        self.page_size.max(1)
+    }
+
+    pub fn set_page_size(&mut self, page_size: i32) {
+        self.page_size = page_size;
+    }
+
+    pub fn get_selected_ids(&self) -> &BTreeSet<i32> {
+        &self.selected_ids
+    }
+
+    pub fn toggle_selected_id(&mut self, id: i32) {
+        if !self.selected_ids.remove(&id) {
+            self.selected_ids.insert(id);
+        }
+    }
+
+    pub fn set_selected_ids(&mut self, selected_ids: BTreeSet<i32>) {
+        self.selected_ids = selected_ids;
+    }
+
+    pub fn get_visual_anchor(&self) -> Option<i32> {
+        self.visual_anchor
+    }
+
+    pub fn set_visual_anchor(&mut self, visual_anchor: Option<i32>) {
+        self.visual_anchor = visual_anchor;
+    }
+
+    pub fn has_selection(&self) -> bool {
+        !self.selected_ids.is_empty() || self.visual_anchor.is_some()
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selected_ids.clear();
+        self.visual_anchor = None;
+    }
+
+    // Marks on a task that is gone would otherwise apply to nothing forever
+    pub fn prune_selection(&mut self) {
+        let ids: HashSet<&i32> = self.task_order.iter().collect();
+        self.selected_ids.retain(|id| ids.contains(id));
+        if self
+            .visual_anchor
+            .is_some_and(|anchor| anchor >= self.get_task_list_length())
+        {
+            self.visual_anchor = None;
+        }
+    }
+
+    // Task at the line is marked directly or lies in the visual range
+    pub fn is_marked(&self, line: i32) -> bool {
+        let is_selected = self
+            .get_task_id(line)
+            .is_some_and(|id| self.selected_ids.contains(&id));
+        let is_in_range = self
+            .visual_anchor
+            .is_some_and(|anchor| line >= anchor.min(self.line) && line <= anchor.max(self.line));
+        is_selected || is_in_range
+    }
+
+    pub fn get_undo_history(&self) -> &Vec<UndoEntry> {
+        &self.undo_history
+    }
+
+    pub fn push_undo_entry(&mut self, undo_entry: UndoEntry) {
+        self.undo_history.push(undo_entry);
+        if self.undo_history.len() > MAX_UNDO_HISTORY {
+            self.undo_history.remove(0);
+        }
+    }
+
+    pub fn pop_undo_entry(&mut self) -> Option<UndoEntry> {
+        self.undo_history.pop()
+    }
+
+    pub fn get_stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn set_stats(&mut self, stats: Stats) {
+        self.stats = stats;
+    }
+
+    pub fn get_task_events(&self) -> &Vec<TaskEvent> {
+        &self.task_events
+    }
+
+    pub fn set_task_events(&mut self, task_events: Vec<TaskEvent>) {
+        self.task_events = task_events;
+    }
+
+    pub fn get_detail_events(&self) -> &Vec<TaskEvent> {
+        &self.detail_events
+    }
+
+    pub fn get_detail_task(&self) -> Option<(i32, i64)> {
+        self.detail_task
+    }
+
+    pub fn set_detail_events(&mut self, detail_task: Option<(i32, i64)>, events: Vec<TaskEvent>) {
+        self.detail_task = detail_task;
+        self.detail_events = events;
+    }
+
+    pub fn get_time_entries(&self) -> &Vec<TimeEntry> {
+        &self.time_entries
+    }
+
+    pub fn set_time_entries(&mut self, time_entries: Vec<TimeEntry>) {
+        self.time_entries = time_entries;
+    }
+
+    pub fn get_running_timer(&self) -> Option<&TimeEntry> {
+        self.time_entries
+            .iter()
+            .find(|time_entry| time_entry.is_running())
+    }
+
+    pub fn get_pomodoro(&self) -> Option<&Pomodoro> {
+        self.pomodoro.as_ref()
+    }
+
+    pub fn get_pomodoro_mut(&mut self) -> Option<&mut Pomodoro> {
+        self.pomodoro.as_mut()
+    }
+
+    pub fn set_pomodoro(&mut self, pomodoro: Option<Pomodoro>) {
+        self.pomodoro = pomodoro;
+    }
+
+    pub fn get_pomodoro_count(&self, task_id: i32) -> u32 {
+        self.pomodoro_counts.get(&task_id).copied().unwrap_or(0)
+    }
+
+    pub fn set_pomodoro_counts(&mut self, pomodoro_counts: HashMap<i32, u32>) {
+        self.pomodoro_counts = pomodoro_counts;
+    }
+
+    pub fn get_is_bell_pending(&self) -> bool {
+        self.is_bell_pending
+    }
+
+    pub fn set_is_bell_pending(&mut self, is_bell_pending: bool) {
+        self.is_bell_pending = is_bell_pending;
+    }
+
+    pub fn get_loading(&self) -> Option<&Loading> {
+        self.loading.as_ref()
+    }
+
+    pub fn set_loading(&mut self, loading: Option<Loading>) {
+        self.loading = loading;
+    }
+
+    pub fn get_task_events_line(&self) -> i32 {
+        self.task_events_line
+    }
+
+    pub fn set_task_events_line(&mut self, task_events_line: i32) {
+        self.task_events_line = task_events_line;
     }
 
     pub fn get_input(&self) -> &String {
@@ -58,16 +436,47 @@ impl State {
         self.is_running = is_running;
     }
 
-    pub fn get_task_list(&self) -> &Vec<Task> {
-        &self.task_list
+    pub fn get_task_order(&self) -> &Vec<i32> {
+        &self.task_order
+    }
+
+    pub fn set_task_order(&mut self, task_order: Vec<i32>) {
+        self.task_order = task_order;
+    }
+
+    pub fn get_loaded_tasks(&self) -> &HashMap<i32, Task> {
+        &self.loaded_tasks
     }
 
+    pub fn set_loaded_tasks(&mut self, task_list: Vec<Task>) {
+        self.loaded_tasks = task_list.into_iter().map(|task| (task.id, task)).collect();
+    }
+
+    // Lists every task at once, in the order given
     pub fn set_task_list(&mut self, task_list: Vec<Task>) {
-        self.task_list = task_list;
+        self.task_order = task_list.iter().map(|task| task.id).collect();
+        self.set_loaded_tasks(task_list);
+    }
+
+    pub fn get_task_id(&self, line: i32) -> Option<i32> {
+        usize::try_from(line)
+            .ok()
+            .and_then(|line| self.task_order.get(line))
+            .copied()
+    }
+
+    // None as well when the row at the line is not loaded
+    pub fn get_task(&self, line: i32) -> Option<&Task> {
+        self.get_task_id(line)
+            .and_then(|id| self.loaded_tasks.get(&id))
+    }
+
+    pub fn get_task_by_id(&self, id: i32) -> Option<&Task> {
+        self.loaded_tasks.get(&id)
     }
 
     pub fn get_task_list_length(&self) -> i32 {
-        self.get_task_list().len() as i32
+        self.task_order.len() as i32
     }
 
     pub fn get_is_first_time(&self) -> bool {
@@ -85,11 +494,65 @@ impl State {
     pub fn set_master_key(&mut self, master_key: String) {
         self.master_key = master_key
     }
+
+    pub fn get_export_format(&self) -> ExportFormat {
+        self.export_format
+    }
+
+    pub fn set_export_format(&mut self, export_format: ExportFormat) {
+        self.export_format = export_format;
+    }
+
+    pub fn get_is_overwrite_pending(&self) -> bool {
+        self.is_overwrite_pending
+    }
+
+    pub fn set_is_overwrite_pending(&mut self, is_overwrite_pending: bool) {
+        self.is_overwrite_pending = is_overwrite_pending;
+    }
+
+    pub fn get_passphrase(&self) -> &String {
+        &self.passphrase
+    }
+
+    pub fn set_passphrase(&mut self, passphrase: String) {
+        self.passphrase = passphrase;
+    }
+
+    pub fn get_passphrase_purpose(&self) -> PassphrasePurpose {
+        self.passphrase_purpose
+    }
+
+    pub fn set_passphrase_purpose(&mut self, passphrase_purpose: PassphrasePurpose) {
+        self.passphrase_purpose = passphrase_purpose;
+    }
+
+    pub fn get_backup_list(&self) -> &Vec<BackupInfo> {
+        &self.backup_list
+    }
+
+    pub fn set_backup_list(&mut self, backup_list: Vec<BackupInfo>) {
+        self.backup_list = backup_list;
+    }
+
+    pub fn get_backup_line(&self) -> i32 {
+        self.backup_line
+    }
+
+    pub fn set_backup_line(&mut self, backup_line: i32) {
+        self.backup_line = backup_line;
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::PomodoroConfig;
+    use crate::constants::Action;
+    use crate::history::TaskEventKind;
+    use chrono::NaiveDateTime;
+    use std::path::PathBuf;
+    use std::time::Instant;
 
     #[test]
     fn setter_getter_test() {
@@ -103,12 +566,70 @@ mod tests {
             id: 0,
             title: String::from("title"),
             status: String::from("in-progress"),
+            ..Default::default()
         };
-        let task_list = vec![task];
+        let task_list = vec![task.clone()];
         let is_first_time = true;
         let master_key = String::from("master_key");
+        let export_format = ExportFormat::Ics;
+        let is_overwrite_pending = true;
+        let passphrase = String::from("passphrase");
+        let passphrase_purpose = PassphrasePurpose::Restore;
+        let backup_list = vec![BackupInfo {
+            path: PathBuf::from("tudu-20241019-153000-123.db"),
+            created_at: NaiveDateTime::default(),
+            task_count: 3,
+        }];
+        let backup_line = 0;
+        let history_line = 1;
+        let mut config = Config::default();
+        config.limits.max_task_title_length = 80;
+        let editing_task_id = Some(3);
+        let search_query = "milk";
+        let pending_keys = String::from("5d");
+        let command_history_line = Some(0);
+        let sort_field = SortField::Title;
+        let previous_screen = Screen::Backups;
+        let help_line = 4;
+        let undo_entry = UndoEntry::new(String::from("Removed 1 task"), task_list.clone());
+        let list_offset = 2;
+        let page_size = 20;
+        let stats = Stats {
+            open_count: 2,
+            ..Default::default()
+        };
+        let task_events = vec![TaskEvent {
+            id: 1,
+            task_id: 3,
+            kind: TaskEventKind::Created,
+            title: String::from("title"),
+            status: String::from("in-progress"),
+            created_at: None,
+        }];
+        let task_events_line = 1;
+        let time_entries = vec![
+            TimeEntry {
+                id: 1,
+                task_id: 3,
+                started_at: NaiveDateTime::default(),
+                stopped_at: Some(NaiveDateTime::default()),
+            },
+            TimeEntry {
+                id: 2,
+                task_id: 3,
+                started_at: NaiveDateTime::default(),
+                stopped_at: None,
+            },
+        ];
+        let pomodoro = Pomodoro::new(3, &PomodoroConfig::default(), Instant::now());
+        let confirmation = Confirmation::new(
+            String::from("Remove task?"),
+            Action::RemoveTask,
+            Screen::Main,
+        );
 
         // setters
+        state.set_notification(Severity::Success, String::from("saved"));
         state.set_error(error.clone());
         state.set_input(input);
         state.set_line(line);
@@ -117,16 +638,148 @@ mod tests {
         state.set_task_list(task_list.clone());
         state.set_is_first_time(is_first_time);
         state.set_master_key(master_key.clone());
+        state.set_export_format(export_format);
+        state.set_is_overwrite_pending(is_overwrite_pending);
+        state.set_passphrase(passphrase.clone());
+        state.set_passphrase_purpose(passphrase_purpose);
+        state.set_backup_list(backup_list.clone());
+        state.set_backup_line(backup_line);
+        state.set_history_line(history_line);
+        state.set_config(config.clone());
+        state.set_editing_task_id(editing_task_id);
+        state.set_search_query(search_query);
+        state.set_pending_keys(pending_keys.clone());
+        state.push_command_history("add milk");
+        state.push_command_history("add milk");
+        state.push_command_history("");
+        state.set_command_history_line(command_history_line);
+        state.set_sort_field(sort_field);
+        state.set_previous_screen(previous_screen);
+        state.set_help_line(help_line);
+        state.set_confirmation(Some(confirmation.clone()));
+        state.set_list_offset(list_offset);
+        state.push_undo_entry(undo_entry.clone());
+        state.set_page_size(page_size);
+        state.set_theme("light", Theme::light());
+        state.set_stats(stats.clone());
+        state.set_task_events(task_events.clone());
+        state.set_task_events_line(task_events_line);
+        state.set_time_entries(time_entries.clone());
+        state.set_pomodoro(Some(pomodoro.clone()));
+        state.set_pomodoro_counts(HashMap::from([(3, 2)]));
+        state.set_is_bell_pending(true);
+        let loading = Loading::new(String::from("Exporting csv file"), Instant::now());
+        state.set_loading(Some(loading.clone()));
 
         //getters
-        assert_eq!(state.get_error(), &error);
+        assert_eq!(state.get_message(), &error);
+        assert_eq!(
+            state
+                .get_notification()
+                .map(|notification| notification.severity),
+            Some(Severity::Error)
+        );
+        assert_eq!(state.get_notification_history().len(), 2);
+        assert_eq!(state.get_notification_history()[1].message, "saved");
+        assert_eq!(state.get_history_line(), history_line);
+        assert_eq!(state.get_config(), &config);
+        assert_eq!(state.get_keymap(), &Keymap::default());
+        assert_eq!(state.get_theme_name(), "light");
+        assert_eq!(state.get_theme(), &Theme::light());
+        assert_eq!(state.get_stats(), &stats);
+        assert_eq!(state.get_task_events(), &task_events);
+        assert_eq!(state.get_task_events_line(), task_events_line);
+        assert_eq!(state.get_time_entries(), &time_entries);
+        assert_eq!(state.get_running_timer(), Some(&time_entries[1]));
+        assert_eq!(state.get_pomodoro(), Some(&pomodoro));
+        assert_eq!(state.get_pomodoro_count(3), 2);
+        assert_eq!(state.get_pomodoro_count(4), 0);
+        assert!(state.get_is_bell_pending());
+        assert_eq!(state.get_loading(), Some(&loading));
+        assert_eq!(state.get_editing_task_id(), editing_task_id);
+        assert_eq!(state.get_search_query(), search_query);
+        assert_eq!(state.get_pending_keys(), &pending_keys);
+        assert_eq!(state.get_command_history(), &vec![String::from("add milk")]);
+        assert_eq!(state.get_command_history_line(), command_history_line);
+        assert_eq!(state.get_sort_field(), sort_field);
+        assert_eq!(state.get_previous_screen(), &previous_screen);
+        assert_eq!(state.get_help_line(), help_line);
+        assert_eq!(state.get_confirmation(), Some(&confirmation));
+        assert_eq!(state.get_list_offset(), list_offset);
+        assert_eq!(state.get_undo_history(), &vec![undo_entry.clone()]);
+        assert_eq!(state.pop_undo_entry(), Some(undo_entry));
+        assert_eq!(state.pop_undo_entry(), None);
+        assert_eq!(state.get_page_size(), page_size);
+        assert_eq!(state.take_confirmation(), Some(confirmation));
+        assert_eq!(state.get_confirmation(), None);
         assert_eq!(state.get_input(), input);
         assert_eq!(state.get_line(), line);
         assert_eq!(state.get_screen(), &screen);
         assert_eq!(state.get_is_running(), is_running);
-        assert_eq!(state.get_task_list(), &task_list);
+        assert_eq!(state.get_task(0), Some(&task));
+        assert_eq!(state.get_task_by_id(0), Some(&task));
+        assert_eq!(state.get_task_order(), &vec![0]);
         assert_eq!(state.get_task_list_length(), task_list.len() as i32);
         assert_eq!(state.get_is_first_time(), is_first_time);
         assert_eq!(state.get_master_key(), &master_key);
+        assert_eq!(state.get_export_format(), export_format);
+        assert_eq!(state.get_is_overwrite_pending(), is_overwrite_pending);
+        assert_eq!(state.get_passphrase(), &passphrase);
+        assert_eq!(state.get_passphrase_purpose(), passphrase_purpose);
+        assert_eq!(state.get_backup_list(), &backup_list);
+        assert_eq!(state.get_backup_line(), backup_line);
+
+        state.set_page_size(0);
+        assert_eq!(state.get_page_size(), 1);
+
+        // Selection
+        state.toggle_selected_id(0);
+        state.toggle_selected_id(7);
+        assert!(state.is_marked(0));
+        state.prune_selection();
+        assert_eq!(state.get_selected_ids(), &BTreeSet::from([0]));
+        state.toggle_selected_id(0);
+        assert!(!state.has_selection());
+        state.set_visual_anchor(Some(0));
+        assert!(state.has_selection());
+        assert!(state.is_marked(0));
+        assert!(!state.is_marked(1));
+        state.clear_selection();
+        assert_eq!(state.get_visual_anchor(), None);
+
+        state.clear_notification();
+        assert_eq!(state.get_message(), "");
+        assert_eq!(state.get_notification_history().len(), 2);
+    }
+
+    #[test]
+    fn test_task_window() {
+        let get_task = |id: i32, title: &str| Task {
+            id,
+            title: String::from(title),
+            status: String::from("in-progress"),
+            ..Default::default()
+        };
+        let mut state = State::new();
+        state.set_task_order(vec![3, 1, 2]);
+        state.set_loaded_tasks(vec![get_task(1, "apples"), get_task(2, "bread")]);
+        state.toggle_selected_id(3);
+        state.toggle_selected_id(4);
+        state.set_visual_anchor(Some(3));
+
+        // Rows outside the window are listed but not loaded
+        assert_eq!(state.get_task_list_length(), 3);
+        assert_eq!(state.get_task_id(0), Some(3));
+        assert_eq!(state.get_task(0), None);
+        assert_eq!(
+            state.get_task(1).map(|task| task.title.as_str()),
+            Some("apples")
+        );
+        assert_eq!(state.get_task(-1), None);
+        assert!(state.is_marked(0));
+
+        state.prune_selection();
+        assert_eq!(state.get_selected_ids(), &BTreeSet::from([3]));
+        assert_eq!(state.get_visual_anchor(), None);
     }
 }
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+pub const CHART_DAYS: i64 = 14;
+pub const HEATMAP_WEEKS: i64 = 12;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    pub today: NaiveDate,
+    pub open_count: usize,
+    pub done_count: usize,
+    // Tasks from before timestamps were recorded only show up in the counts
+    pub untracked_count: usize,
+    pub average_completion: Option<Duration>,
+    // Oldest day first, the last entry is today
+    pub completed_per_day: Vec<(NaiveDate, u64)>,
+    // Whole weeks from Monday to Sunday, days after today count nothing
+    pub heatmap_start: NaiveDate,
+    pub heatmap: Vec<[u64; 7]>,
+}
+
+// Counts over the whole list, the database sums them up without reading any title
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TaskTotals {
+    pub open_count: usize,
+    pub done_count: usize,
+    pub untracked_count: usize,
+    pub average_completion: Option<Duration>,
+    // Completed tasks by day, from the start of the heatmap on
+    pub completions: HashMap<NaiveDate, u64>,
+}
+
+// The heatmap reaches further back than the chart
+pub fn get_heatmap_start(today: NaiveDate) -> NaiveDate {
+    let days_since_monday = today.weekday().num_days_from_monday() as i64;
+    today - Duration::days(days_since_monday + (HEATMAP_WEEKS - 1) * 7)
+}
+
+pub fn compute_stats(task_totals: TaskTotals, today: NaiveDate) -> Stats {
+    let completions = task_totals.completions;
+    let mut stats = Stats {
+        today,
+        open_count: task_totals.open_count,
+        done_count: task_totals.done_count,
+        untracked_count: task_totals.untracked_count,
+        average_completion: task_totals.average_completion,
+        ..Default::default()
+    };
+
+    let count_on = |day: NaiveDate| completions.get(&day).copied().unwrap_or(0);
+    stats.completed_per_day = (0..CHART_DAYS)
+        .rev()
+        .map(|days_ago| today - Duration::days(days_ago))
+        .map(|day| (day, count_on(day)))
+        .collect();
+
+    stats.heatmap_start = get_heatmap_start(today);
+    stats.heatmap = (0..HEATMAP_WEEKS)
+        .map(|week| {
+            let mut days = [0; 7];
+            for (weekday, count) in days.iter_mut().enumerate() {
+                let day = stats.heatmap_start + Duration::days(week * 7 + weekday as i64);
+                *count = count_on(day);
+            }
+            days
+        })
+        .collect();
+
+    stats
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, 0) => String::from("<1m"),
+        (0, 0, minutes) => format!("{}m", minutes),
+        (0, hours, minutes) => format!("{}h {}m", hours, minutes),
+        (days, hours, _) => format!("{}d {}h", days, hours),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_stats() {
+        let today = NaiveDate::from_ymd_opt(2024, 10, 19).expect("Could not build date");
+        let task_totals = TaskTotals {
+            open_count: 2,
+            done_count: 3,
+            untracked_count: 2,
+            average_completion: Some(Duration::minutes(25 * 60)),
+            completions: HashMap::from([
+                (today, 2),
+                (today - Duration::days(14), 1),
+                (today - Duration::weeks(12), 1),
+            ]),
+        };
+
+        let stats = compute_stats(task_totals, today);
+        assert_eq!(stats.done_count, 3);
+        assert_eq!(stats.open_count, 2);
+        assert_eq!(stats.untracked_count, 2);
+        assert_eq!(stats.average_completion, Some(Duration::minutes(25 * 60)));
+
+        assert_eq!(stats.completed_per_day.len(), CHART_DAYS as usize);
+        assert_eq!(stats.completed_per_day.last(), Some(&(today, 2)));
+        assert_eq!(stats.completed_per_day[0].0, today - Duration::days(13));
+
+        // 2024-10-19 is a Saturday
+        assert_eq!(stats.heatmap.len(), HEATMAP_WEEKS as usize);
+        assert_eq!(stats.heatmap_start.weekday(), chrono::Weekday::Mon);
+        assert_eq!(stats.heatmap[11], [0, 0, 0, 0, 0, 2, 0]);
+        assert_eq!(stats.heatmap[9], [0, 0, 0, 0, 0, 1, 0]);
+        // Completions from before the heatmap started are not shown
+        let heatmap_count: u64 = stats.heatmap.iter().flatten().sum();
+        assert_eq!(heatmap_count, 3);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::seconds(30)), "<1m");
+        assert_eq!(format_duration(Duration::minutes(45)), "45m");
+        assert_eq!(format_duration(Duration::minutes(192)), "3h 12m");
+        assert_eq!(format_duration(Duration::hours(52)), "2d 4h");
+    }
+}
@@ -1,6 +1,21 @@
-#[derive(Debug, Clone, PartialEq)]
+use chrono::NaiveDateTime;
+
+// Timestamps are local time, tasks from older databases have none
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Task {
     pub id: i32,
     pub title: String,
     pub status: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub due: Option<NaiveDateTime>,
+    // 1 is the highest, like in calendars
+    pub priority: Option<u8>,
+    // An RRULE value such as FREQ=WEEKLY;BYDAY=FR, kept as it came
+    pub recurrence: Option<String>,
+    pub list: Option<String>,
+    pub tags: Vec<String>,
+    // Goes up with every change to the row
+    pub version: i64,
 }
@@ -0,0 +1,115 @@
+use ratatui::style::Color;
+use serde::Deserialize;
+
+pub const DEFAULT_THEME: &str = "dark";
+pub const MONOCHROME_THEME: &str = "monochrome";
+pub const THEME_NAMES: [&str; 4] = [DEFAULT_THEME, "light", "high-contrast", MONOCHROME_THEME];
+
+// Custom themes start from the dark one, so they only list what they change
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub text: Color,
+    pub border: Color,
+    pub title: Color,
+    pub highlight_fg: Color,
+    pub highlight_bg: Color,
+    pub completed: Color,
+    pub marked: Color,
+    pub legend: Color,
+    pub info: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            text: Color::White,
+            border: Color::Reset,
+            title: Color::Reset,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::LightYellow,
+            completed: Color::DarkGray,
+            marked: Color::LightMagenta,
+            legend: Color::Reset,
+            info: Color::Cyan,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+        }
+    }
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Theme {
+            text: Color::Black,
+            border: Color::DarkGray,
+            title: Color::Black,
+            highlight_fg: Color::White,
+            highlight_bg: Color::Blue,
+            completed: Color::Gray,
+            marked: Color::Magenta,
+            legend: Color::DarkGray,
+            info: Color::Blue,
+            success: Color::Green,
+            warning: Color::Rgb(175, 95, 0),
+            error: Color::Red,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            text: Color::White,
+            border: Color::White,
+            title: Color::LightYellow,
+            highlight_fg: Color::Black,
+            highlight_bg: Color::White,
+            completed: Color::LightCyan,
+            marked: Color::LightMagenta,
+            legend: Color::White,
+            info: Color::LightCyan,
+            success: Color::LightGreen,
+            warning: Color::LightYellow,
+            error: Color::LightRed,
+        }
+    }
+
+    // Leaves every colour to the terminal, the selection is drawn reversed instead
+    pub fn monochrome() -> Self {
+        Theme {
+            text: Color::Reset,
+            border: Color::Reset,
+            title: Color::Reset,
+            highlight_fg: Color::Reset,
+            highlight_bg: Color::Reset,
+            completed: Color::Reset,
+            marked: Color::Reset,
+            legend: Color::Reset,
+            info: Color::Reset,
+            success: Color::Reset,
+            warning: Color::Reset,
+            error: Color::Reset,
+        }
+    }
+}
+
+pub fn get_built_in_theme(name: &str) -> Option<Theme> {
+    match name {
+        DEFAULT_THEME => Some(Theme::default()),
+        "light" => Some(Theme::light()),
+        "high-contrast" => Some(Theme::high_contrast()),
+        MONOCHROME_THEME => Some(Theme::monochrome()),
+        _ => None,
+    }
+}
+
+// https://no-color.org, a theme picked in the config still wins
+pub fn get_default_theme_name(no_color: Option<&str>) -> &'static str {
+    match no_color {
+        Some(value) if !value.is_empty() => MONOCHROME_THEME,
+        _ => DEFAULT_THEME,
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use csv::Writer;
+use serde::Serialize;
+
+use crate::constants::ReportFormat;
+use crate::error::{TuduError, TuduResult};
+use crate::task::Task;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeEntry {
+    pub id: i32,
+    pub task_id: i32,
+    pub started_at: NaiveDateTime,
+    // Empty while the timer runs, even after the app was closed
+    pub stopped_at: Option<NaiveDateTime>,
+}
+
+impl TimeEntry {
+    pub fn is_running(&self) -> bool {
+        self.stopped_at.is_none()
+    }
+
+    pub fn get_duration(&self, now: NaiveDateTime) -> Duration {
+        (self.stopped_at.unwrap_or(now) - self.started_at).max(Duration::zero())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimeReportRow {
+    pub day: String,
+    pub task_id: i32,
+    pub title: String,
+    pub seconds: i64,
+    pub duration: String,
+}
+
+pub fn get_task_totals(time_entries: &[TimeEntry], now: NaiveDateTime) -> HashMap<i32, Duration> {
+    let mut totals: HashMap<i32, Duration> = HashMap::new();
+    for time_entry in time_entries.iter() {
+        *totals.entry(time_entry.task_id).or_insert(Duration::zero()) +=
+            time_entry.get_duration(now);
+    }
+    totals
+}
+
+// Entries running past midnight count towards each day they cover,
+// tasks that were deleted since keep their time under a placeholder title
+pub fn build_time_report(
+    time_entries: &[TimeEntry],
+    task_list: &[Task],
+    now: NaiveDateTime,
+) -> Vec<TimeReportRow> {
+    let mut totals: BTreeMap<(NaiveDate, i32), Duration> = BTreeMap::new();
+    for time_entry in time_entries.iter() {
+        let stopped_at = time_entry.stopped_at.unwrap_or(now);
+        let mut start = time_entry.started_at;
+        while start < stopped_at {
+            let next_day = start.date().succ_opt().unwrap_or(start.date());
+            let end = stopped_at.min(next_day.and_time(Default::default()));
+            if end <= start {
+                break;
+            }
+            *totals
+                .entry((start.date(), time_entry.task_id))
+                .or_insert(Duration::zero()) += end - start;
+            start = end;
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|((day, task_id), duration)| TimeReportRow {
+            day: day.format("%Y-%m-%d").to_string(),
+            task_id,
+            title: task_list
+                .iter()
+                .find(|task| task.id == task_id)
+                .map_or(String::from("[deleted]"), |task| task.title.clone()),
+            seconds: duration.num_seconds(),
+            duration: format_elapsed(duration),
+        })
+        .collect()
+}
+
+pub fn write_time_report(
+    rows: &[TimeReportRow],
+    report_format: ReportFormat,
+    path: &Path,
+) -> TuduResult<()> {
+    match report_format {
+        ReportFormat::Csv => {
+            let map_error = |e| TuduError::export("Could not save csv report", e);
+            let mut writer = Writer::from_path(path).map_err(map_error)?;
+            for row in rows.iter() {
+                writer.serialize(row).map_err(map_error)?;
+            }
+            writer
+                .flush()
+                .map_err(|e| TuduError::export("Could not save csv report", e))
+        }
+        ReportFormat::Json => {
+            let content = serde_json::to_string_pretty(rows)
+                .map_err(|e| TuduError::export("Could not save json report", e))?;
+            fs::write(path, content).map_err(|e| TuduError::export("Could not save json report", e))
+        }
+    }
+}
+
+pub fn format_elapsed(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::get_test_dir;
+
+    fn parse(timestamp: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M")
+            .expect("Could not parse timestamp")
+    }
+
+    fn get_time_entries() -> Vec<TimeEntry> {
+        vec![
+            TimeEntry {
+                id: 1,
+                task_id: 1,
+                started_at: parse("2024-10-18 23:30"),
+                stopped_at: Some(parse("2024-10-19 00:15")),
+            },
+            TimeEntry {
+                id: 2,
+                task_id: 2,
+                started_at: parse("2024-10-19 09:00"),
+                stopped_at: Some(parse("2024-10-19 09:20")),
+            },
+            TimeEntry {
+                id: 3,
+                task_id: 1,
+                started_at: parse("2024-10-19 10:00"),
+                stopped_at: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_task_totals() {
+        let now = parse("2024-10-19 10:30");
+        let totals = get_task_totals(&get_time_entries(), now);
+        assert_eq!(totals.get(&1), Some(&Duration::minutes(75)));
+        assert_eq!(totals.get(&2), Some(&Duration::minutes(20)));
+        assert_eq!(totals.get(&3), None);
+        assert_eq!(format_elapsed(Duration::seconds(3723)), "1:02:03");
+    }
+
+    #[test]
+    fn test_time_report() {
+        let now = parse("2024-10-19 10:30");
+        let task_list = vec![Task {
+            id: 1,
+            title: String::from("Write report"),
+            ..Default::default()
+        }];
+        let rows = build_time_report(&get_time_entries(), &task_list, now);
+
+        let summary: Vec<(&str, i32, &str, i64)> = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.day.as_str(),
+                    row.task_id,
+                    row.title.as_str(),
+                    row.seconds,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("2024-10-18", 1, "Write report", 30 * 60),
+                ("2024-10-19", 1, "Write report", 45 * 60),
+                ("2024-10-19", 2, "[deleted]", 20 * 60),
+            ]
+        );
+
+        let report_path = get_test_dir("timer");
+        let csv_path = report_path.join("report.csv");
+        write_time_report(&rows, ReportFormat::Csv, &csv_path).expect("Could not write csv report");
+        let content = fs::read_to_string(&csv_path).expect("Could not read csv report");
+        assert_eq!(
+            content.lines().collect::<Vec<&str>>()[..2],
+            [
+                "day,task_id,title,seconds,duration",
+                "2024-10-18,1,Write report,1800,0:30:00"
+            ]
+        );
+
+        let json_path = report_path.join("report.json");
+        write_time_report(&rows, ReportFormat::Json, &json_path)
+            .expect("Could not write json report");
+        let content = fs::read_to_string(&json_path).expect("Could not read json report");
+        assert!(content.contains("\"title\": \"[deleted]\""));
+    }
+}
@@ -0,0 +1,14 @@
+use crate::task::Task;
+
+// Rows as they were stored before a change, putting them back reverts the change
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoEntry {
+    pub label: String,
+    pub task_list: Vec<Task>,
+}
+
+impl UndoEntry {
+    pub fn new(label: String, task_list: Vec<Task>) -> Self {
+        UndoEntry { label, task_list }
+    }
+}
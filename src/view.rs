@@ -1,49 +1,133 @@
 use std::rc::Rc;
+use std::time::Instant;
 
+use chrono::{Duration, Local, NaiveDateTime};
 use ratatui::{
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     prelude::{Backend, Terminal},
-    style::{Color, Style},
-    text::Span,
-    widgets::{Block, Borders, List, Paragraph},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        block::Title, BarChart, Block, Borders, Clear, Gauge, List, ListState, Paragraph,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap,
+    },
     Frame,
 };
 
-use crate::constants::Screen;
+use crate::constants::{ExportFormat, Screen, Severity};
+use crate::pomodoro::{format_countdown, PomodoroPhase};
 use crate::state::State;
+use crate::stats::{format_duration, CHART_DAYS, HEATMAP_WEEKS};
+use crate::timer::{format_elapsed, get_task_totals};
+
+const NARROW_WIDTH: u16 = 80;
+const DETAIL_PANEL_MIN_WIDTH: u16 = 100;
+const HEATMAP_CELLS: [char; 5] = ['·', '░', '▒', '▓', '█'];
 
 #[derive(Debug, Default)]
 pub struct View {}
 
 impl View {
-    pub fn draw<B: Backend>(
-        terminal: &mut Terminal<B>,
-        state: &State,
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    pub fn draw<B: Backend>(terminal: &mut Terminal<B>, state: &State) -> std::io::Result<()> {
         terminal.draw(|frame| {
             let area = frame.size();
-
-            match state.screen {
-                Screen::Main => View::draw_main_scene(frame, area, state),
-                Screen::Add => View::draw_add_task_scene(frame, area, state),
-                Screen::Greetings => View::draw_greetings_scene(frame, area, state),
-            }
+            View::draw_scene(frame, area, state, *state.get_screen());
         })?;
         Ok(())
     }
 
+    fn draw_scene(frame: &mut Frame, area: Rect, state: &State, screen: Screen) {
+        match screen {
+            Screen::Main => View::draw_main_scene(frame, area, state),
+            Screen::Add => View::draw_add_task_scene(frame, area, state),
+            Screen::Greetings => View::draw_greetings_scene(frame, area, state),
+            Screen::Export => View::draw_export_scene(frame, area, state),
+            Screen::Import => View::draw_import_scene(frame, area, state),
+            Screen::Restore => View::draw_restore_scene(frame, area, state),
+            Screen::Passphrase => View::draw_passphrase_scene(frame, area, state),
+            Screen::Backups => View::draw_backups_scene(frame, area, state),
+            Screen::Messages => View::draw_messages_scene(frame, area, state),
+            Screen::Stats => View::draw_stats_scene(frame, area, state),
+            Screen::History => View::draw_history_scene(frame, area, state),
+            Screen::Focus => View::draw_focus_scene(frame, area, state),
+            Screen::Search => View::draw_prompt_scene(frame, area, state, '/'),
+            Screen::Command => View::draw_prompt_scene(frame, area, state, ':'),
+            Screen::Confirm => {
+                if let Some(confirmation) = state.get_confirmation() {
+                    if confirmation.previous_screen != Screen::Confirm {
+                        View::draw_scene(frame, area, state, confirmation.previous_screen);
+                    }
+                    View::draw_confirm_popup(frame, area, state, &confirmation.message);
+                }
+            }
+            Screen::Help => {
+                // The popup is drawn over the screen help was opened from
+                let previous_screen = *state.get_previous_screen();
+                if previous_screen != Screen::Help {
+                    View::draw_scene(frame, area, state, previous_screen);
+                }
+                View::draw_help_popup(frame, area, state);
+            }
+        }
+    }
+
+    fn get_centered_rect(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+        let vertical_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ])
+            .split(area);
+
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ])
+            .split(vertical_layout[1])[1]
+    }
+
+    // Narrow terminals put the message under the legend instead of next to it
     fn get_chunks(area: Rect) -> (Rc<[Rect]>, Rc<[Rect]>) {
         let outer_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
             .split(area);
 
-        let inner_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(outer_layout[1]);
+        let inner_layout = if area.width < NARROW_WIDTH {
+            Layout::default()
+                .direction(Direction::Vertical)
+                .constraints(vec![Constraint::Length(1), Constraint::Min(0)])
+                .split(outer_layout[1])
+        } else {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(outer_layout[1])
+        };
+
+        (outer_layout, inner_layout)
+    }
 
-        return (outer_layout, inner_layout);
+    // Wide terminals get the detail panel next to the task list
+    pub fn has_detail_panel(area: Rect) -> bool {
+        area.width >= DETAIL_PANEL_MIN_WIDTH
+    }
+
+    fn get_task_list_chunks(area: Rect) -> (Rect, Option<Rect>) {
+        if !View::has_detail_panel(area) {
+            return (area, None);
+        }
+
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area);
+        (layout[0], Some(layout[1]))
     }
 
     fn draw_greetings_scene(frame: &mut Frame, area: Rect, state: &State) {
@@ -53,8 +137,9 @@ impl View {
 
         let message = if state.get_is_first_time() {
             format!(
-            "Hello there 👋, let's set a master key with numbers \nthat is 10 char max and promise you will never forget!\n{}
+            "Hello there 👋, let's set a master key with numbers \nthat is {} char max and promise you will never forget!\n{}
             ",
+            state.get_config().limits.max_master_key_length,
             String::from("*").repeat(state.master_key.len())
             )
         } else {
@@ -65,53 +150,278 @@ impl View {
         };
         let widget = Paragraph::new(message)
             .alignment(Alignment::Left)
-            .block(Block::default().borders(Borders::ALL).title("tudu"));
+            .block(View::get_block(state).title("tudu"));
 
         frame.render_widget(widget, outer_layout[0]);
-        View::draw_legend(frame, "esc: Cancel, enter: Enter", inner_layout[0]);
-        View::draw_error(frame, &state, inner_layout[1]);
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
     }
 
     fn draw_main_scene(frame: &mut Frame, area: Rect, state: &State) {
-        let selected_line = state.line;
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
 
+        View::draw_task_list_with_detail(frame, state, outer_layout[0]);
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    // The task list stays visible while a search or command is typed below it
+    fn draw_prompt_scene(frame: &mut Frame, area: Rect, state: &State, prompt: char) {
         let chunks = View::get_chunks(area);
         let outer_layout = chunks.0;
         let inner_layout = chunks.1;
 
-        let items: Vec<Span> = state
-            .task_list
-            .iter()
-            .enumerate()
-            .map(|e| {
-                let checkbox = if e.1.status == "completed" {
+        let prompt_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+            .split(outer_layout[0]);
+
+        let input_field = Paragraph::new(format!("{}{}", prompt, state.get_input()))
+            .alignment(Alignment::Left)
+            .block(View::get_block(state));
+
+        View::draw_task_list_with_detail(frame, state, prompt_layout[0]);
+        frame.render_widget(input_field, prompt_layout[1]);
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_task_list_with_detail(frame: &mut Frame, state: &State, area: Rect) {
+        let (list_area, detail_area) = View::get_task_list_chunks(area);
+        View::draw_task_list(frame, state, list_area);
+        if let Some(detail_area) = detail_area {
+            View::draw_task_detail(frame, state, detail_area);
+        }
+    }
+
+    fn draw_task_detail(frame: &mut Frame, state: &State, area: Rect) {
+        let line = state.get_line();
+        let now = Local::now().naive_local();
+        let lines = match state.get_task(line) {
+            Some(task) => {
+                let label_style = Style::default().fg(state.get_theme().title);
+                let field = |label: &str, value: String| {
+                    Line::from(vec![
+                        Span::styled(format!("{:<8}", label), label_style),
+                        Span::raw(value),
+                    ])
+                };
+                let tracked = state
+                    .get_time_entries()
+                    .iter()
+                    .filter(|time_entry| time_entry.task_id == task.id)
+                    .fold(Duration::zero(), |total, time_entry| {
+                        total + time_entry.get_duration(now)
+                    });
+                let mut lines = vec![
+                    Line::styled("Title", label_style),
+                    Line::from(task.title.clone()),
+                    Line::default(),
+                    field("Status", task.status.clone()),
+                    field("Created", View::format_timestamp(task.created_at)),
+                    field("Updated", View::format_timestamp(task.updated_at)),
+                    field("Done", View::format_timestamp(task.completed_at)),
+                    field("Due", View::format_timestamp(task.due)),
+                    field(
+                        "Priority",
+                        task.priority
+                            .map_or(String::from("-"), |priority| priority.to_string()),
+                    ),
+                    field(
+                        "Repeats",
+                        task.recurrence.clone().unwrap_or(String::from("-")),
+                    ),
+                    field("List", task.list.clone().unwrap_or(String::from("-"))),
+                    field("Tags", View::format_tags(&task.tags)),
+                    field(
+                        "Focus",
+                        format!("{} pomodoros", state.get_pomodoro_count(task.id)),
+                    ),
+                    field("Tracked", format_elapsed(tracked)),
+                    Line::default(),
+                    Line::styled("History", label_style),
+                ];
+                // Events are loaded for the selected task before the frame is drawn
+                let events = match state.get_detail_task() {
+                    Some((id, _)) if id == task.id => state.get_detail_events().as_slice(),
+                    _ => &[],
+                };
+                lines.extend(events.iter().map(|event| {
+                    Line::from(format!(
+                        "{} {}",
+                        View::format_timestamp(event.created_at),
+                        event.describe()
+                    ))
+                }));
+                lines
+            }
+            None => vec![Line::from("No task selected")],
+        };
+
+        let widget = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(View::get_block(state).title("Details"));
+        frame.render_widget(widget, area);
+    }
+
+    fn format_tags(tags: &[String]) -> String {
+        if tags.is_empty() {
+            String::from("-")
+        } else {
+            tags.iter()
+                .map(|tag| format!("#{}", tag))
+                .collect::<Vec<String>>()
+                .join(" ")
+        }
+    }
+
+    fn format_timestamp(timestamp: Option<NaiveDateTime>) -> String {
+        timestamp.map_or(String::from("-"), |timestamp| {
+            timestamp.format("%Y-%m-%d %H:%M").to_string()
+        })
+    }
+
+    fn draw_task_list(frame: &mut Frame, state: &State, area: Rect) {
+        let selected_line = state.line;
+        let now = Local::now().naive_local();
+        let task_totals = get_task_totals(state.get_time_entries(), now);
+        let running_timer = state.get_running_timer();
+
+        // Only the rows on screen are built, the list can hold tens of thousands of tasks
+        let list_offset = state.get_list_offset().max(0) as usize;
+        let visible_count = area.height.saturating_sub(2) as usize;
+        let items: Vec<Span> = (list_offset..list_offset + visible_count)
+            .map_while(|line| state.get_task_id(line as i32).map(|id| (line, id)))
+            .map(|(line, id)| {
+                // Rows are loaded around the viewport before every frame
+                let Some(task) = state.get_task_by_id(id) else {
+                    return Span::raw("");
+                };
+                let checkbox = if task.status == "completed" {
                     '✓'
                 } else {
                     ' '
                 };
-                let content = format!(" [{}] {} :: {}", checkbox, e.1.title, e.1.status);
-                if e.0 as i32 == selected_line {
-                    Span::styled(
-                        content,
-                        Style::default().bg(Color::LightYellow).fg(Color::Black),
-                    )
+                let marker = if state.is_marked(line as i32) {
+                    '*'
+                } else {
+                    ' '
+                };
+                let mut content =
+                    format!("{}[{}] {} :: {}", marker, checkbox, task.title, task.status);
+                if let Some(list) = &task.list {
+                    content.push_str(&format!(" @{}", list));
+                }
+                for tag in task.tags.iter() {
+                    content.push_str(&format!(" #{}", tag));
+                }
+                if let Some(total) = task_totals.get(&task.id) {
+                    let is_running = running_timer.is_some_and(|timer| timer.task_id == task.id);
+                    let icon = if is_running { "▶ " } else { "" };
+                    content.push_str(&format!(" [{}{}]", icon, format_elapsed(*total)));
+                }
+                let theme = state.get_theme();
+                if line as i32 == selected_line {
+                    Span::styled(content, View::get_highlight_style(state))
+                } else if marker == '*' {
+                    Span::styled(content, Style::default().fg(theme.marked))
+                } else if task.status == "completed" {
+                    Span::styled(content, Style::default().fg(theme.completed))
                 } else {
                     Span::raw(content)
                 }
             })
             .collect();
 
-        let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Task List"))
-            .style(Style::default().fg(Color::White));
-        frame.render_widget(list, outer_layout[0]);
+        let task_count = state.get_task_list_length();
+        let mut title = String::from("Task List");
+        if task_count > 0 {
+            title.push_str(&format!(" {} of {}", selected_line + 1, task_count));
+        }
+        let marked_count = (0..task_count)
+            .filter(|line| state.is_marked(*line))
+            .count();
+        if marked_count > 0 {
+            title.push_str(&format!(", {} marked", marked_count));
+        }
+        if state.get_visual_anchor().is_some() {
+            title.push_str(" [visual]");
+        }
+        // Keys of an unfinished sequence like "5d" are shown next to the title
+        if !state.get_pending_keys().is_empty() {
+            title.push_str(&format!(" [{}]", state.get_pending_keys()));
+        }
 
-        View::draw_legend(
-            frame,
-            "esc: Exit, a: Add, x: Remove, enter: Check/Uncheck, ↑: Up, ↓: Down e: Export",
-            inner_layout[0],
-        );
-        View::draw_error(frame, &state, inner_layout[1]);
+        // The running timer is shown in the header of the list
+        let mut block = View::get_block(state).title(title);
+        if let Some(timer) = running_timer {
+            let task_title = state
+                .get_task_by_id(timer.task_id)
+                .map_or("", |task| task.title.as_str());
+            let indicator = format!(
+                " ▶ {} {} ",
+                task_title,
+                format_elapsed(timer.get_duration(now))
+            );
+            block = block.title(
+                Title::from(Span::styled(
+                    indicator,
+                    Style::default().fg(state.get_theme().success),
+                ))
+                .alignment(Alignment::Right),
+            );
+        }
+        let list = List::new(items).block(block);
+        frame.render_widget(list, area);
+
+        if task_count > area.height.saturating_sub(2) as i32 {
+            let mut scrollbar_state =
+                ScrollbarState::new(task_count as usize).position(selected_line.max(0) as usize);
+            frame.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                area.inner(&Margin {
+                    vertical: 1,
+                    horizontal: 0,
+                }),
+                &mut scrollbar_state,
+            );
+        }
+    }
+
+    // Rows left for tasks on the main screen once the footer and borders are drawn
+    pub fn get_list_height(area: Rect) -> u16 {
+        area.height.saturating_sub(5)
+    }
+
+    // The line under a click on the main screen, and whether it hit the checkbox
+    pub fn get_clicked_task(
+        area: Rect,
+        state: &State,
+        column: u16,
+        row: u16,
+    ) -> Option<(i32, bool)> {
+        let list_area = View::get_task_list_chunks(View::get_chunks(area).0[0])
+            .0
+            .inner(&Margin {
+                vertical: 1,
+                horizontal: 1,
+            });
+        let is_inside = (list_area.left()..list_area.right()).contains(&column)
+            && (list_area.top()..list_area.bottom()).contains(&row);
+        if !is_inside {
+            return None;
+        }
+
+        let line = state.get_list_offset() + (row - list_area.y) as i32;
+        // Each line starts with the mark and then "[✓]"
+        let is_checkbox = (1..=3).contains(&(column - list_area.x));
+        (line < state.get_task_list_length()).then_some((line, is_checkbox))
+    }
+
+    pub fn get_legend_area(area: Rect) -> Rect {
+        View::get_chunks(area).1[0]
     }
 
     fn draw_add_task_scene(frame: &mut Frame, area: Rect, state: &State) {
@@ -123,28 +433,461 @@ impl View {
 
         let input_field = Paragraph::new(String::from(content))
             .alignment(Alignment::Left)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Write the task, max 40 characters"),
-            );
+            .block(View::get_block(state).title(format!(
+                "{} the task, max {} characters",
+                if state.get_editing_task_id().is_some() {
+                    "Edit"
+                } else {
+                    "Write"
+                },
+                state.get_config().limits.max_task_title_length
+            )));
 
         frame.render_widget(input_field, outer_layout[0]);
 
-        View::draw_legend(frame, "esc: Cancel, enter: Save", inner_layout[0]);
-        View::draw_error(frame, &state, inner_layout[1]);
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_export_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let export_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(outer_layout[0]);
+
+        let formats: Vec<Span> = [
+            ExportFormat::Csv,
+            ExportFormat::Ics,
+            ExportFormat::Markdown,
+            ExportFormat::Bundle,
+        ]
+        .iter()
+        .map(|export_format| {
+            let content = format!(" {} ", export_format.extension());
+            if *export_format == state.get_export_format() {
+                Span::styled(content, View::get_highlight_style(state))
+            } else {
+                Span::raw(content)
+            }
+        })
+        .collect();
+        let format_field = Paragraph::new(Line::from(formats))
+            .alignment(Alignment::Left)
+            .block(View::get_block(state).title("Format"));
+
+        let path_field = Paragraph::new(state.get_input().as_str())
+            .alignment(Alignment::Left)
+            .block(View::get_block(state).title("Export path"));
+
+        frame.render_widget(format_field, export_layout[0]);
+        frame.render_widget(path_field, export_layout[1]);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_import_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let path_field = Paragraph::new(state.get_input().as_str())
+            .alignment(Alignment::Left)
+            .block(View::get_block(state).title("Ics path"));
+
+        frame.render_widget(path_field, outer_layout[0]);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_restore_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let path_field = Paragraph::new(state.get_input().as_str())
+            .alignment(Alignment::Left)
+            .block(View::get_block(state).title("Bundle path"));
+
+        frame.render_widget(path_field, outer_layout[0]);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_passphrase_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let message = format!(
+            "Enter the bundle passphrase, leave it empty to use the master key\n{}",
+            String::from("*").repeat(state.get_passphrase().len())
+        );
+        let widget = Paragraph::new(message)
+            .alignment(Alignment::Left)
+            .block(View::get_block(state).title("Passphrase"));
+
+        frame.render_widget(widget, outer_layout[0]);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_backups_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let selected_line = state.get_backup_line();
+
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let items: Vec<Span> = state
+            .get_backup_list()
+            .iter()
+            .enumerate()
+            .map(|e| {
+                let content = format!(
+                    " {} :: {} tasks :: {}",
+                    e.1.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    e.1.task_count,
+                    e.1.get_file_name()
+                );
+                if e.0 as i32 == selected_line {
+                    Span::styled(content, View::get_highlight_style(state))
+                } else {
+                    Span::raw(content)
+                }
+            })
+            .collect();
+
+        let list = List::new(items).block(View::get_block(state).title("Backups"));
+        let mut list_state = ListState::default().with_selected(Some(selected_line as usize));
+        frame.render_stateful_widget(list, outer_layout[0], &mut list_state);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_messages_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let lines: Vec<Line> = state
+            .get_notification_history()
+            .iter()
+            .map(|notification| {
+                Line::from(vec![
+                    Span::raw(format!(
+                        " {} ",
+                        notification.created_at.format("%Y-%m-%d %H:%M:%S")
+                    )),
+                    Span::styled(
+                        format!("{:<8}", notification.severity.label()),
+                        Style::default().fg(View::get_severity_color(state, notification.severity)),
+                    ),
+                    Span::raw(notification.message.clone()),
+                ])
+            })
+            .collect();
+
+        let history = Paragraph::new(lines)
+            .block(View::get_block(state).title("Messages"))
+            .scroll((state.get_history_line() as u16, 0));
+        frame.render_widget(history, outer_layout[0]);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_history_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let lines: Vec<Line> = state
+            .get_task_events()
+            .iter()
+            .map(|event| Line::from(format!(" {}", event.format_line())))
+            .collect();
+
+        let history = Paragraph::new(lines)
+            .block(View::get_block(state).title("History"))
+            .scroll((state.get_task_events_line() as u16, 0));
+        frame.render_widget(history, outer_layout[0]);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_focus_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        if let Some(pomodoro) = state.get_pomodoro() {
+            let now = Instant::now();
+            let theme = state.get_theme();
+            let focus_layout = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(4),
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                ])
+                .split(outer_layout[0]);
+
+            let task_title = state
+                .get_task_by_id(pomodoro.task_id)
+                .map_or("", |task| task.title.as_str());
+            let summary = Paragraph::new(vec![
+                Line::from(task_title.to_string()),
+                Line::from(format!(
+                    "{} pomodoros this session, {} for this task",
+                    pomodoro.completed_count,
+                    state.get_pomodoro_count(pomodoro.task_id)
+                )),
+            ])
+            .block(View::get_block(state).title("Focus"));
+            frame.render_widget(summary, focus_layout[0]);
+
+            let color = match pomodoro.phase {
+                PomodoroPhase::Work => theme.warning,
+                PomodoroPhase::ShortBreak | PomodoroPhase::LongBreak => theme.success,
+            };
+            let mut label = format_countdown(pomodoro.get_remaining(now));
+            if pomodoro.is_paused() {
+                label.push_str(" paused");
+            }
+            let gauge = Gauge::default()
+                .block(View::get_block(state).title(pomodoro.phase.label().to_string()))
+                .gauge_style(Style::default().fg(color))
+                .ratio(pomodoro.get_progress(now))
+                .label(label);
+            frame.render_widget(gauge, focus_layout[1]);
+        }
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    fn draw_stats_scene(frame: &mut Frame, area: Rect, state: &State) {
+        let chunks = View::get_chunks(area);
+        let outer_layout = chunks.0;
+        let inner_layout = chunks.1;
+
+        let stats = state.get_stats();
+        let theme = state.get_theme();
+        let stats_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(4),
+                Constraint::Min(6),
+                Constraint::Length(9),
+            ])
+            .split(outer_layout[0]);
+        let summary_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(stats_layout[0]);
+
+        let task_count = stats.open_count + stats.done_count;
+        let done_ratio = if task_count == 0 {
+            0.0
+        } else {
+            stats.done_count as f64 / task_count as f64
+        };
+        let gauge = Gauge::default()
+            .block(View::get_block(state).title("Done"))
+            .gauge_style(Style::default().fg(theme.success))
+            .ratio(done_ratio)
+            .label(format!(
+                "{} done, {} open",
+                stats.done_count, stats.open_count
+            ));
+        frame.render_widget(gauge, summary_layout[0]);
+
+        let average = stats
+            .average_completion
+            .map_or(String::from("-"), format_duration);
+        let mut summary = vec![Line::from(format!("Average time to complete: {}", average))];
+        if stats.untracked_count > 0 {
+            summary.push(Line::from(format!(
+                "{} older tasks have no dates",
+                stats.untracked_count
+            )));
+        }
+        let summary = Paragraph::new(summary).block(View::get_block(state).title("Summary"));
+        frame.render_widget(summary, summary_layout[1]);
+
+        let labels: Vec<String> = stats
+            .completed_per_day
+            .iter()
+            .map(|(day, _)| day.format("%d").to_string())
+            .collect();
+        let bars: Vec<(&str, u64)> = labels
+            .iter()
+            .zip(stats.completed_per_day.iter())
+            .map(|(label, (_, count))| (label.as_str(), *count))
+            .collect();
+        let chart = BarChart::default()
+            .block(
+                View::get_block(state).title(format!("Completed in the last {} days", CHART_DAYS)),
+            )
+            .data(&bars)
+            .bar_width(3)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(theme.success))
+            .value_style(Style::default().fg(theme.highlight_fg).bg(theme.success));
+        frame.render_widget(chart, stats_layout[1]);
+
+        // Darker cells mean more tasks completed that day, relative to the busiest day
+        let busiest_day = stats.heatmap.iter().flatten().copied().max().unwrap_or(0);
+        let lines: Vec<Line> = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+            .iter()
+            .enumerate()
+            .map(|(weekday, name)| {
+                let mut spans = vec![Span::raw(format!("{} ", name))];
+                for (week, days) in stats.heatmap.iter().enumerate() {
+                    let day =
+                        stats.heatmap_start + Duration::days(week as i64 * 7 + weekday as i64);
+                    let count = days[weekday];
+                    let cell = if day > stats.today {
+                        Span::raw("  ")
+                    } else if count == 0 {
+                        Span::styled(
+                            format!("{} ", HEATMAP_CELLS[0]),
+                            Style::default().fg(theme.completed),
+                        )
+                    } else {
+                        let level = ((count * 4).div_ceil(busiest_day)).clamp(1, 4) as usize;
+                        Span::styled(
+                            format!("{} ", HEATMAP_CELLS[level]),
+                            Style::default().fg(theme.success),
+                        )
+                    };
+                    spans.push(cell);
+                }
+                Line::from(spans)
+            })
+            .collect();
+        let heatmap = Paragraph::new(lines)
+            .block(View::get_block(state).title(format!("Last {} weeks", HEATMAP_WEEKS)));
+        frame.render_widget(heatmap, stats_layout[2]);
+
+        View::draw_legend(frame, state, inner_layout[0]);
+        View::draw_error(frame, state, inner_layout[1]);
+    }
+
+    // Reusable modal for yes/no questions, the keys are inside since the legend gets cut
+    fn draw_confirm_popup(frame: &mut Frame, area: Rect, state: &State, message: &str) {
+        let mut popup_area = View::get_centered_rect(area, 60, 20);
+        popup_area.height = area.height.min(6);
+        popup_area.y = area.y + (area.height - popup_area.height) / 2;
+
+        let legend = state.get_keymap().get_legend(&Screen::Confirm);
+        let widget = Paragraph::new(vec![
+            Line::from(message),
+            Line::default(),
+            Line::styled(
+                legend,
+                Style::default()
+                    .fg(state.get_theme().legend)
+                    .add_modifier(Modifier::DIM),
+            ),
+        ])
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(View::get_block(state).title("Confirm"));
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(widget, popup_area);
     }
 
-    fn draw_legend(frame: &mut Frame, text: &str, area: Rect) {
-        let widget = Paragraph::new(text)
+    fn draw_help_popup(frame: &mut Frame, area: Rect, state: &State) {
+        let popup_area = View::get_centered_rect(area, 80, 80);
+
+        let mut lines: Vec<Line> = Vec::new();
+        for (screen, entries) in state.get_keymap().get_help_sections() {
+            lines.push(Line::styled(
+                format!("[{}]", screen.get_name()),
+                Style::default()
+                    .fg(state.get_theme().title)
+                    .add_modifier(Modifier::BOLD),
+            ));
+            lines.extend(entries.into_iter().map(Line::from));
+            lines.push(Line::default());
+        }
+
+        let help = Paragraph::new(lines)
+            .block(View::get_block(state).title("Help"))
+            .scroll((state.get_help_line() as u16, 0));
+        frame.render_widget(Clear, popup_area);
+        frame.render_widget(help, popup_area);
+    }
+
+    fn draw_legend(frame: &mut Frame, state: &State, area: Rect) {
+        let legend = state.get_keymap().get_legend(state.get_screen());
+        let widget = Paragraph::new(legend)
+            .style(Style::default().fg(state.get_theme().legend))
             .alignment(Alignment::Left)
             .block(Block::default().borders(Borders::NONE));
 
         frame.render_widget(widget, area);
     }
 
+    fn get_block(state: &State) -> Block<'static> {
+        let theme = state.get_theme();
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(theme.text))
+            .border_style(Style::default().fg(theme.border))
+            .title_style(Style::default().fg(theme.title))
+    }
+
+    // Without colours the selection is reversed so it still stands out
+    fn get_highlight_style(state: &State) -> Style {
+        let theme = state.get_theme();
+        let style = Style::default()
+            .bg(theme.highlight_bg)
+            .fg(theme.highlight_fg);
+        if theme.highlight_bg == Color::Reset {
+            style.add_modifier(Modifier::REVERSED)
+        } else {
+            style
+        }
+    }
+
+    fn get_severity_color(state: &State, severity: Severity) -> Color {
+        let theme = state.get_theme();
+        match severity {
+            Severity::Info => theme.info,
+            Severity::Success => theme.success,
+            Severity::Warning => theme.warning,
+            Severity::Error => theme.error,
+        }
+    }
+
+    // A running background operation takes the line over until it is done
     fn draw_error(frame: &mut Frame, state: &State, area: Rect) {
-        let widget = Paragraph::new(state.get_error().as_str())
+        let (message, style) = match (state.get_loading(), state.get_notification()) {
+            (Some(loading), _) => (
+                loading.format_line(Instant::now()),
+                Style::default().fg(View::get_severity_color(state, Severity::Info)),
+            ),
+            (None, Some(notification)) => (
+                notification.message.clone(),
+                Style::default().fg(View::get_severity_color(state, notification.severity)),
+            ),
+            (None, None) => (String::new(), Style::default()),
+        };
+        let widget = Paragraph::new(message)
+            .style(style)
+            .wrap(Wrap { trim: true })
             .alignment(Alignment::Left)
             .block(Block::default().borders(Borders::NONE));
 
@@ -0,0 +1,163 @@
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use crate::client::Client;
+use crate::constants::{ExportFormat, ReportFormat};
+use crate::error::{TuduError, TuduResult};
+use crate::event::AppEvent;
+use crate::restore::RestoreSummary;
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+type Job = Box<dyn FnOnce(&mut Client) + Send>;
+
+// What a long operation sends back to the ui thread once it is done
+#[derive(Debug)]
+pub enum JobOutcome {
+    // The path written to and the number of rows left out of it
+    Exported(ExportFormat, TuduResult<(PathBuf, usize)>),
+    ReportExported(ReportFormat, TuduResult<PathBuf>),
+    Imported(TuduResult<Vec<i32>>),
+    Restored(PathBuf, TuduResult<RestoreSummary>),
+    BackedUp(TuduResult<PathBuf>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loading {
+    pub label: String,
+    pub started_at: Instant,
+}
+
+impl Loading {
+    pub fn new(label: String, started_at: Instant) -> Self {
+        Loading { label, started_at }
+    }
+
+    pub fn format_line(&self, now: Instant) -> String {
+        let elapsed = now.saturating_duration_since(self.started_at);
+        let frame = SPINNER_FRAMES[(elapsed.as_millis() / 100) as usize % SPINNER_FRAMES.len()];
+        format!("{} {}... {}s", frame, self.label, elapsed.as_secs())
+    }
+}
+
+// Owns the database connection, jobs run one at a time in the order they were sent
+pub struct DatabaseWorker {
+    sender: Option<Sender<Job>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Default for DatabaseWorker {
+    fn default() -> Self {
+        DatabaseWorker::new()
+    }
+}
+
+impl DatabaseWorker {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let thread = thread::spawn(move || {
+            let mut client = Client::default();
+            for job in receiver {
+                job(&mut client);
+            }
+        });
+        DatabaseWorker {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    fn send(&self, job: Job) -> TuduResult<()> {
+        self.sender
+            .as_ref()
+            .and_then(|sender| sender.send(job).ok())
+            .ok_or_else(|| TuduError::Storage {
+                context: String::from("Database worker stopped"),
+                source: None,
+            })
+    }
+
+    // Waits for the result, meant for the quick queries behind every keypress
+    pub fn call<T, F>(&self, job: F) -> TuduResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> TuduResult<T> + Send + 'static,
+    {
+        let (result_sender, result_receiver) = mpsc::channel();
+        self.send(Box::new(move |client| {
+            let _ = result_sender.send(job(client));
+        }))?;
+        result_receiver
+            .recv()
+            .map_err(|e| TuduError::storage("Database worker stopped", e))?
+    }
+
+    // Returns right away, the outcome arrives later as an event
+    pub fn spawn<F>(&self, job: F, event_sender: Sender<AppEvent>) -> TuduResult<()>
+    where
+        F: FnOnce(&mut Client) -> JobOutcome + Send + 'static,
+    {
+        self.send(Box::new(move |client| {
+            let _ = event_sender.send(AppEvent::Job(job(client)));
+        }))
+    }
+
+    pub fn stop(&mut self) {
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for DatabaseWorker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_database_worker() {
+        let mut worker = DatabaseWorker::new();
+        worker
+            .call(|client| client.open_connection(PathBuf::new(), ":memory:"))
+            .expect("Could not open connection");
+        worker
+            .call(|client| client.create_todos_table())
+            .expect("Could not create todos table");
+
+        let (event_sender, event_receiver) = mpsc::channel();
+        worker
+            .spawn(
+                |client| {
+                    let result = client.create_task(String::from("title")).map(|id| vec![id]);
+                    JobOutcome::Imported(result)
+                },
+                event_sender,
+            )
+            .expect("Could not spawn job");
+        // Calls and jobs run one at a time in the order they were sent
+        let tasks = worker
+            .call(|client| client.get_tasks())
+            .expect("Could not get tasks");
+        assert_eq!(tasks.len(), 1);
+        assert!(matches!(
+            event_receiver.recv(),
+            Ok(AppEvent::Job(JobOutcome::Imported(Ok(ids)))) if ids == vec![1]
+        ));
+
+        worker.stop();
+        assert!(worker.call(|client| client.get_tasks()).is_err());
+
+        let loading = Loading::new(String::from("Importing tasks"), Instant::now());
+        let line = loading.format_line(loading.started_at + Duration::from_millis(2300));
+        assert_eq!(line, "⠸ Importing tasks... 2s");
+    }
+}